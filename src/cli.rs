@@ -8,6 +8,11 @@ use clap_complete::Generator;
 pub struct Args {
 	#[clap(subcommand)]
 	pub command: GeodeCommands,
+
+	/// Don't access the network, using cached dependencies and installed
+	/// mods instead where possible
+	#[clap(long, global = true)]
+	pub offline: bool,
 }
 
 #[derive(Debug, ValueEnum, Clone)]
@@ -88,6 +93,12 @@ pub enum GeodeCommands {
 		commands: crate::package::Package,
 	},
 
+	/// Tools for working with bitmap fonts
+	Font {
+		#[clap(subcommand)]
+		commands: crate::font::Font,
+	},
+
 	/// Tools for interacting with the Geode mod index
 	Index {
 		#[clap(subcommand)]
@@ -0,0 +1,264 @@
+use std::path::{Path, PathBuf};
+
+use clap::{Subcommand, ValueEnum};
+use image::{imageops, Rgba, RgbaImage};
+use imageproc::drawing::{draw_hollow_rect_mut, draw_line_segment_mut};
+use imageproc::rect::Rect;
+
+use crate::fnt::FntFile;
+use crate::mod_file::{parse_mod_info, Color};
+use crate::util::{bmfont, spritesheet};
+use crate::{done, fatal, info, NiceUnwrap};
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum FontQuality {
+	Sd,
+	Hd,
+	Uhd,
+}
+
+#[derive(Subcommand, Debug)]
+#[clap(rename_all = "kebab-case")]
+pub enum Font {
+	/// Render a sample string with a bitmap font to a PNG
+	Preview {
+		/// Either a generated .fnt file, or the name of a font in the
+		/// resources of the project's mod.json
+		font: String,
+
+		/// Text to render. Newlines start a new line
+		#[clap(
+			long,
+			short,
+			default_value = "The quick brown fox jumps over the lazy dog"
+		)]
+		text: String,
+
+		/// Location of the output PNG. Defaults to {font}-preview.png in the
+		/// current directory
+		#[clap(long, short)]
+		output: Option<PathBuf>,
+
+		/// Location of the mod's folder, when building the font from mod.json
+		#[clap(long)]
+		root_path: Option<PathBuf>,
+
+		/// Which tier of the font to render, when building the font from
+		/// mod.json
+		#[clap(long, short, default_value = "uhd")]
+		quality: FontQuality,
+
+		/// Background color as a hex string. Transparent if not specified
+		#[clap(long)]
+		background: Option<String>,
+
+		/// Draw glyph bounds, pen advances and baselines
+		#[clap(long)]
+		guides: bool,
+	},
+}
+
+/// A glyph placed on the preview canvas
+struct PlacedGlyph {
+	page: usize,
+	src: (u32, u32, u32, u32),
+	/// Top left corner of the glyph
+	x: i32,
+	y: i32,
+	/// Pen position the glyph was placed at, before applying offsets
+	pen_x: i32,
+	line_top: i32,
+	advance: i32,
+}
+
+fn layout(fnt: &FntFile, text: &str) -> (Vec<PlacedGlyph>, Vec<i32>) {
+	let mut glyphs = Vec::new();
+	let mut baselines = Vec::new();
+
+	for (line_num, line) in text.lines().enumerate() {
+		let top = line_num as i32 * fnt.line_height;
+		baselines.push(top + fnt.base);
+
+		let mut pen_x = 0;
+		let mut prev: Option<u32> = None;
+		for ch in line.chars() {
+			let Some(c) = fnt.get_char(ch as u32) else {
+				info!("Character '{}' is not in the font, skipping", ch);
+				continue;
+			};
+			if let Some(prev) = prev {
+				pen_x += fnt.kerning(prev, c.id);
+			}
+			glyphs.push(PlacedGlyph {
+				page: c.page as usize,
+				src: (c.x, c.y, c.width, c.height),
+				x: pen_x + c.xoffset,
+				y: top + c.yoffset,
+				pen_x,
+				line_top: top,
+				advance: c.xadvance,
+			});
+			pen_x += c.xadvance;
+			prev = Some(c.id);
+		}
+	}
+
+	(glyphs, baselines)
+}
+
+/// Render text with a .fnt file and its pages the same way cocos2d's
+/// CCLabelBMFont does: glyphs are placed at the pen position plus their
+/// offsets, the pen moves by the advance plus the kerning amount of the pair
+pub fn render_preview(
+	fnt_path: &Path,
+	text: &str,
+	background: Option<Color>,
+	guides: bool,
+) -> Result<RgbaImage, String> {
+	let fnt = FntFile::load(fnt_path)?;
+	let pages: Vec<RgbaImage> = fnt
+		.page_paths(fnt_path)
+		.iter()
+		.map(|p| spritesheet::read_to_image(p))
+		.collect::<Result<_, _>>()
+		.map_err(|e| e.to_string())?;
+
+	let (glyphs, baselines) = layout(&fnt, text);
+
+	// Fit the canvas around everything that was drawn
+	let margin = 8;
+	let min_x = glyphs.iter().map(|g| g.x).min().unwrap_or(0).min(0);
+	let min_y = glyphs.iter().map(|g| g.y).min().unwrap_or(0).min(0);
+	let max_x = glyphs
+		.iter()
+		.map(|g| (g.x + g.src.2 as i32).max(g.pen_x + g.advance))
+		.max()
+		.unwrap_or(0);
+	let max_y = glyphs
+		.iter()
+		.map(|g| g.y + g.src.3 as i32)
+		.chain(std::iter::once(baselines.len() as i32 * fnt.line_height))
+		.max()
+		.unwrap_or(0);
+
+	let origin_x = margin - min_x;
+	let origin_y = margin - min_y;
+	let mut canvas = RgbaImage::from_pixel(
+		(max_x - min_x + margin * 2).max(1) as u32,
+		(max_y - min_y + margin * 2).max(1) as u32,
+		background
+			.map(|c| Rgba([c.red, c.green, c.blue, 255]))
+			.unwrap_or(Rgba([0, 0, 0, 0])),
+	);
+
+	for glyph in &glyphs {
+		let page = pages
+			.get(glyph.page)
+			.ok_or(format!("Font refers to missing page {}", glyph.page))?;
+		let (x, y, w, h) = glyph.src;
+		if w == 0 || h == 0 {
+			continue;
+		}
+		imageops::overlay(
+			&mut canvas,
+			&*imageops::crop_imm(page, x, y, w, h),
+			(origin_x + glyph.x) as i64,
+			(origin_y + glyph.y) as i64,
+		);
+	}
+
+	if guides {
+		let baseline_color = Rgba([255, 0, 0, 255]);
+		let bounds_color = Rgba([0, 200, 0, 255]);
+		let advance_color = Rgba([0, 120, 255, 255]);
+
+		let width = canvas.width() as f32;
+		for baseline in &baselines {
+			let y = (origin_y + baseline) as f32;
+			draw_line_segment_mut(&mut canvas, (0.0, y), (width, y), baseline_color);
+		}
+		for glyph in &glyphs {
+			if glyph.src.2 > 0 && glyph.src.3 > 0 {
+				draw_hollow_rect_mut(
+					&mut canvas,
+					Rect::at(origin_x + glyph.x, origin_y + glyph.y)
+						.of_size(glyph.src.2, glyph.src.3),
+					bounds_color,
+				);
+			}
+			let pen_x = (origin_x + glyph.pen_x) as f32;
+			let top = (origin_y + glyph.line_top) as f32;
+			draw_line_segment_mut(
+				&mut canvas,
+				(pen_x, top),
+				(pen_x, top + fnt.line_height as f32),
+				advance_color,
+			);
+		}
+	}
+
+	Ok(canvas)
+}
+
+fn preview_font(
+	font: String,
+	text: String,
+	output: Option<PathBuf>,
+	root_path: Option<PathBuf>,
+	quality: FontQuality,
+	background: Option<String>,
+	guides: bool,
+) {
+	let background = background.map(|b| Color::parse_hex(&b).nice_unwrap("Invalid background"));
+	let output = output.unwrap_or_else(|| {
+		let name = Path::new(&font).file_stem().unwrap().to_string_lossy();
+		PathBuf::from(format!("{name}-preview.png"))
+	});
+
+	// Keep the temporary directory alive until rendering is done
+	let mut _temp_dir = None;
+
+	let fnt_path = if font.ends_with(".fnt") {
+		PathBuf::from(&font)
+	} else {
+		let root_path = root_path.unwrap_or_else(|| std::env::current_dir().unwrap());
+		let mod_info = parse_mod_info(&root_path);
+		let Some(bitmap_font) = mod_info.resources.fonts.get(&font) else {
+			fatal!("Font '{}' not found in mod.json", font);
+		};
+
+		let dir = tempfile::tempdir().nice_unwrap("Could not create temporary directory");
+		let bundles = bmfont::get_font_bundles(bitmap_font, dir.path(), &mut None, &mod_info, true)
+			.nice_unwrap("Unable to build font");
+		_temp_dir = Some(dir);
+
+		match quality {
+			FontQuality::Sd => bundles.sd.fnt,
+			FontQuality::Hd => bundles.hd.fnt,
+			FontQuality::Uhd => bundles.uhd.fnt,
+		}
+	};
+
+	let text = text.replace("\\n", "\n");
+	let image = render_preview(&fnt_path, &text, background, guides)
+		.nice_unwrap("Unable to render font preview");
+	image
+		.save(&output)
+		.nice_unwrap("Unable to write preview image");
+
+	done!("Preview written to {}", output.display());
+}
+
+pub fn subcommand(cmd: Font) {
+	match cmd {
+		Font::Preview {
+			font,
+			text,
+			output,
+			root_path,
+			quality,
+			background,
+			guides,
+		} => preview_font(font, text, output, root_path, quality, background, guides),
+	}
+}
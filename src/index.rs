@@ -1,18 +1,23 @@
-use crate::config::Config;
+use crate::config::{self, Config};
+use crate::index_search::SearchFilters;
 use crate::server::{ApiResponse, PaginatedData};
+use crate::util::error::Error;
 use crate::util::logging::ask_value;
-use crate::{done, fatal, index_admin, index_auth, index_dev, info, NiceUnwrap};
+use crate::{
+	fatal, index_admin, index_auth, index_dev, index_install, index_local, index_search,
+	index_server, info, warn, NiceUnwrap,
+};
 use clap::Subcommand;
 use reqwest::header::USER_AGENT;
 use semver::VersionReq;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::fs;
 use std::io::Cursor;
 use std::path::PathBuf;
 use zip::read::ZipFile;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct ServerModVersion {
 	#[allow(unused)]
 	pub name: String,
@@ -47,6 +52,41 @@ pub enum Index {
 		action: MyModAction,
 	},
 
+	/// Search for mods on the index
+	Search {
+		/// Text to look for in mod IDs and names
+		query: Option<String>,
+
+		#[clap(flatten)]
+		filters: SearchFilters,
+
+		/// Page of results to show
+		#[clap(long, default_value_t = 1)]
+		page: u32,
+
+		/// Number of results per page
+		#[clap(long, default_value_t = 10)]
+		per_page: u32,
+
+		/// Print the results as JSON
+		#[clap(long)]
+		json: bool,
+	},
+
+	/// Show details about a mod on the index
+	Info {
+		/// Mod ID to show
+		id: String,
+
+		/// Version to show dependencies of, defaults to latest
+		#[clap(long)]
+		version: Option<String>,
+
+		/// Print the details as JSON
+		#[clap(long)]
+		json: bool,
+	},
+
 	/// Install a mod from the index to the current profile
 	Install {
 		/// Mod ID to install
@@ -54,6 +94,10 @@ pub enum Index {
 
 		/// Mod version to install, defaults to latest
 		version: Option<VersionReq>,
+
+		/// Install without asking for confirmation
+		#[clap(long, short)]
+		yes: bool,
 	},
 
 	/// Set the URL for the index (pass default to reset)
@@ -63,6 +107,21 @@ pub enum Index {
 		url: Option<String>,
 	},
 
+	/// Run a local index for testing, backed by a directory of packages
+	Serve {
+		/// Directory to keep packages and the index state in
+		#[clap(default_value = ".")]
+		dir: PathBuf,
+
+		/// Address to listen on
+		#[clap(long, default_value = "127.0.0.1")]
+		host: String,
+
+		/// Port to listen on
+		#[clap(long, short, default_value_t = 8080)]
+		port: u16,
+	},
+
 	/// Secrets...
 	Admin {
 		#[clap(subcommand)]
@@ -98,65 +157,6 @@ pub enum AdminAction {
 	DevStatus,
 }
 
-pub fn install_mod(
-	config: &Config,
-	id: &String,
-	version: &VersionReq,
-	ignore_platform: bool,
-) -> PathBuf {
-	let compare = {
-		let string = version.to_string();
-		if string == "*" {
-			None
-		} else {
-			Some(string)
-		}
-	};
-	let found = get_mod_versions(id, 1, 1, config, !ignore_platform, compare)
-		.nice_unwrap("Couldn't fetch versions from index");
-
-	if found.data.is_empty() {
-		fatal!("Couldn't find dependency on index");
-	}
-
-	let found_version = found.data.first().unwrap();
-
-	info!(
-		"Installing mod '{}' version '{}'",
-		id, found_version.version
-	);
-
-	let bytes = reqwest::blocking::get(get_index_url(
-		format!("v1/mods/{}/versions/{}/download", id, found_version.version),
-		config,
-	))
-	.nice_unwrap("Unable to download mod")
-	.bytes()
-	.nice_unwrap("Unable to download mod");
-
-	let dest = config
-		.get_current_profile()
-		.mods_dir()
-		.join(format!("{id}.geode"));
-
-	let hash = sha256::digest(bytes.as_ref());
-
-	if hash != found_version.hash {
-		fatal!(
-			"Downloaded file doesn't match expected hash\n\
-			    {hash}\n\
-			 vs {}\n\
-			Try again, and if the issue persists, report this on GitHub: \
-			https://github.com/geode-sdk/cli/issues/new",
-			found_version.hash
-		);
-	}
-
-	fs::write(&dest, bytes).nice_unwrap("Unable to install .geode file");
-
-	dest
-}
-
 fn submit(action: MyModAction, config: &mut Config) {
 	let mut is_update = false;
 	let download_link = match action {
@@ -182,13 +182,8 @@ fn submit(action: MyModAction, config: &mut Config) {
 
 	if is_update {
 		info!("Fetching mod id from .geode file");
-		let mut zip_data: Cursor<Vec<u8>> = Cursor::new(vec![]);
-
-		let mut response =
-			reqwest::blocking::get(&download_link).nice_unwrap("Unable to download mod");
-		response
-			.copy_to(&mut zip_data)
-			.nice_unwrap("Unable to write to index");
+		let zip_data =
+			Cursor::new(read_download_link(&download_link).nice_unwrap("Unable to download mod"));
 
 		let mut zip_archive =
 			zip::ZipArchive::new(zip_data).nice_unwrap("Unable to decode .geode file");
@@ -289,6 +284,48 @@ fn set_index_url(url: String, config: &mut Config) {
 	config.index_token = None;
 	config.save();
 	info!("Index URL set to: {}", config.index_url);
+	if let Some(dir) = index_local::index_dir(config) {
+		if !dir.is_dir() {
+			warn!("Local index directory {} does not exist", dir.display());
+		}
+	}
+}
+
+/// Whether the index can be used, which is always the case for a local
+/// index, while a remote one can't be reached in offline mode
+pub fn is_available(config: &Config) -> bool {
+	index_local::index_dir(config).is_some() || !config::is_offline()
+}
+
+/// Exit with an error if the index can't be used, saying what needed it
+pub fn require_available(config: &Config, what: &str) {
+	if index_local::index_dir(config).is_none() {
+		config::require_online(what);
+	}
+}
+
+/// Read the package behind a download link, which may also be a `file://`
+/// URL or a path when submitting to a local index server
+pub fn read_download_link(link: &str) -> Result<Vec<u8>, String> {
+	if link.starts_with("http://") || link.starts_with("https://") {
+		if config::is_offline() {
+			return Err(Error::Offline(format!("Downloading {link}")).to_string());
+		}
+		let response = reqwest::blocking::get(link).map_err(|e| e.to_string())?;
+		if !response.status().is_success() {
+			return Err(format!("Download failed with status {}", response.status()));
+		}
+		return response
+			.bytes()
+			.map(|b| b.to_vec())
+			.map_err(|e| e.to_string());
+	}
+	let path = reqwest::Url::parse(link)
+		.ok()
+		.filter(|url| url.scheme() == "file")
+		.and_then(|url| url.to_file_path().ok())
+		.unwrap_or_else(|| PathBuf::from(link));
+	fs::read(&path).map_err(|e| format!("{}: {e}", path.display()))
 }
 
 pub fn get_index_url(path: impl AsRef<str>, config: &Config) -> String {
@@ -299,6 +336,53 @@ pub fn get_index_url(path: impl AsRef<str>, config: &Config) -> String {
 	)
 }
 
+/// Download the .geode package of a mod version, checking it against the
+/// expected SHA-256 hash, such as the one reported by the index
+pub fn download_mod(
+	id: &str,
+	version: &str,
+	expected_hash: &str,
+	config: &Config,
+) -> Result<Vec<u8>, Error> {
+	let bytes = if let Some(dir) = index_local::index_dir(config) {
+		index_local::read_package(&dir, id, version)?
+	} else {
+		if config::is_offline() {
+			return Err(Error::Offline(format!("Downloading {id} {version}")));
+		}
+
+		let response = reqwest::blocking::Client::new()
+			.get(get_index_url(
+				format!("v1/mods/{}/versions/{}/download", id, version),
+				config,
+			))
+			.header(USER_AGENT, "GeodeCLI")
+			.send()?;
+
+		if !response.status().is_success() {
+			return Err(Error::Index(format!(
+				"Downloading {id} {version} failed with status {}",
+				response.status()
+			)));
+		}
+
+		response.bytes()?.to_vec()
+	};
+
+	let hash = sha256::digest(&bytes);
+	if hash != expected_hash {
+		return Err(Error::Index(format!(
+			"Downloaded file doesn't match expected hash\n\
+			    {hash}\n\
+			 vs {expected_hash}\n\
+			Try again, and if the issue persists, report this on GitHub: \
+			https://github.com/geode-sdk/cli/issues/new"
+		)));
+	}
+
+	Ok(bytes)
+}
+
 pub fn get_mod_versions(
 	id: &str,
 	page: u32,
@@ -306,7 +390,24 @@ pub fn get_mod_versions(
 	config: &Config,
 	check_platform: bool,
 	compare: Option<String>,
-) -> Result<PaginatedData<ServerModVersion>, String> {
+) -> Result<PaginatedData<ServerModVersion>, Error> {
+	if let Some(dir) = index_local::index_dir(config) {
+		let platform = config.get_current_profile().platform_str().to_string();
+		return index_local::get_mod_versions(
+			&dir,
+			id,
+			page,
+			per_page,
+			check_platform.then_some(platform.as_str()),
+			compare.as_deref(),
+		);
+	}
+	if config::is_offline() {
+		return Err(Error::Offline(format!(
+			"Fetching versions of {id} from the index"
+		)));
+	}
+
 	let url = get_index_url(format!("v1/mods/{}/versions", id), config);
 
 	let client = reqwest::blocking::Client::new();
@@ -327,19 +428,18 @@ pub fn get_mod_versions(
 		.get(url)
 		.query(&query)
 		.header(USER_AGENT, "GeodeCLI")
-		.send()
-		.nice_unwrap("Couldn't connect to the index");
+		.send()?;
 
 	if !response.status().is_success() {
-		return Err("Failed to fetch mod versions".to_string());
+		return Err(Error::Index(format!(
+			"Failed to fetch versions of {id}: status {}",
+			response.status()
+		)));
 	}
 
-	let body = match response.json::<ApiResponse<PaginatedData<ServerModVersion>>>() {
-		Err(e) => {
-			return Err(format!("Failed to parse index response: {}", e));
-		}
-		Ok(b) => b,
-	};
+	let body = response
+		.json::<ApiResponse<PaginatedData<ServerModVersion>>>()
+		.map_err(|e| Error::Index(format!("Failed to parse index response: {e}")))?;
 
 	Ok(body.payload)
 }
@@ -348,10 +448,27 @@ pub fn subcommand(cmd: Index) {
 	let mut _config = Config::new();
 	let config = &mut _config;
 	match cmd {
-		Index::Install { id, version } => {
-			let config = Config::new().assert_is_setup();
-			install_mod(&config, &id, &version.unwrap_or(VersionReq::STAR), false);
-			done!("Mod installed");
+		Index::Url { .. } | Index::Serve { .. } => {}
+		Index::Search { .. } => require_available(config, "Searching the index"),
+		Index::Info { .. } => require_available(config, "Looking up mods on the index"),
+		Index::Install { .. } => require_available(config, "Installing mods from the index"),
+		_ if index_local::index_dir(config).is_some() => {
+			fatal!("This command isn't supported by a local index, only by the Geode Index")
+		}
+		_ => config::require_online("Talking to the index"),
+	}
+
+	match cmd {
+		Index::Search {
+			query,
+			filters,
+			page,
+			per_page,
+			json,
+		} => index_search::search(query, filters, page, per_page, json),
+		Index::Info { id, version, json } => index_search::info(id, version, json),
+		Index::Install { id, version, yes } => {
+			index_install::install(id, version.unwrap_or(VersionReq::STAR), yes)
 		}
 		Index::Login {
 			token,
@@ -373,6 +490,7 @@ pub fn subcommand(cmd: Index) {
 			MyModAction::Edit => index_dev::edit_own_mods(config),
 		},
 		Index::Profile => index_dev::edit_profile(config),
+		Index::Serve { dir, host, port } => index_server::serve(dir, &host, port),
 		Index::Admin { commands } => index_admin::subcommand(commands, config),
 	}
 	config.save();
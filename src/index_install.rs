@@ -0,0 +1,226 @@
+//! Installing mods from the index into the current profile along with the
+//! dependencies they need to load
+
+use crate::config::Config;
+use crate::file::read_dir_recursive;
+use crate::index::{download_mod, get_mod_versions};
+use crate::mod_file::{try_parse_mod_info, DependencyImportance, ModFileInfo, PlatformName};
+use crate::project::applies_to_platform;
+use crate::util::dep_cache::DependencyCache;
+use crate::util::logging::ask_confirm;
+use crate::{done, fatal, info, NiceUnwrap};
+use clap::ValueEnum;
+use colored::Colorize;
+use semver::{Version, VersionReq};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::PathBuf;
+
+/// A mod that's going to be installed
+struct Planned {
+	id: String,
+	version: Version,
+	/// Downloaded package in the dependency cache
+	package: PathBuf,
+	/// The mod that needs it, or None for the mod that was asked for
+	required_by: Option<String>,
+	/// Installed package that this one replaces
+	replaces: Option<(Version, PathBuf)>,
+}
+
+/// A dependency that's already installed with a version that satisfies it
+struct Satisfied {
+	id: String,
+	version: Version,
+	required_by: String,
+	/// Everything that's required of it so far
+	requirement: VersionReq,
+}
+
+/// Installed mods in a mods directory by ID
+fn installed_mods(mods_dir: &PathBuf) -> HashMap<String, (Version, PathBuf)> {
+	read_dir_recursive(mods_dir)
+		.unwrap_or_default()
+		.into_iter()
+		.filter_map(|path| {
+			let info = try_parse_mod_info(&path).ok()?;
+			Some((info.id, (info.version, path)))
+		})
+		.collect()
+}
+
+/// Download the newest version of a mod matching a version requirement into
+/// the dependency cache
+fn fetch(
+	id: &str,
+	version: &VersionReq,
+	platform: PlatformName,
+	config: &Config,
+	cache: &DependencyCache,
+) -> Result<(ModFileInfo, PathBuf), String> {
+	// The index can only compare against a single version, so combined
+	// requirements are matched here instead
+	let single = version.comparators.len() <= 1;
+	let compare = Some(version.to_string()).filter(|v| single && v != "*");
+	let per_page = if single { 1 } else { 100 };
+	let found = get_mod_versions(id, 1, per_page, config, true, compare)
+		.map_err(|e| format!("Couldn't fetch versions from index: {e}"))?;
+	let Some(latest) = found.data.iter().find(|v| {
+		single
+			|| Version::parse(v.version.trim_start_matches('v')).is_ok_and(|v| version.matches(&v))
+	}) else {
+		return Err(if *version == VersionReq::STAR {
+			format!("It wasn't found on the index for {platform}")
+		} else {
+			format!("No version matching '{version}' was found on the index for {platform}")
+		});
+	};
+
+	let package = match cache.get(id, &latest.version, platform, &latest.hash) {
+		Some(cached) => cached,
+		None => {
+			info!("Downloading '{}' version '{}'", id, latest.version);
+			let bytes = download_mod(id, &latest.version, &latest.hash, config)
+				.map_err(|e| format!("Unable to download mod: {e}"))?;
+			cache.store(id, &latest.version, platform, &bytes, &latest.hash)?
+		}
+	};
+	let info = try_parse_mod_info(&package).map_err(|e| format!("Couldn't parse mod.json: {e}"))?;
+	Ok((info, package))
+}
+
+/// Install a mod from the index to the current profile, along with the
+/// required dependencies it and those dependencies have for the profile's
+/// platform. Dependencies that are already installed with a matching
+/// version are kept as they are
+pub fn install(id: String, version: VersionReq, yes: bool) {
+	let config = Config::new().assert_is_setup();
+	let profile = config.get_current_profile();
+	let mods_dir = profile.mods_dir();
+	let platform = PlatformName::from_str(profile.platform_str(), true).unwrap_or_else(|_| {
+		fatal!(
+			"Profile '{}' has an unknown platform '{}'",
+			profile.name,
+			profile.platform_str()
+		)
+	});
+
+	let cache = DependencyCache::open().nice_unwrap("Unable to open dependency cache");
+	let installed = installed_mods(&mods_dir);
+
+	let mut planned: Vec<Planned> = Vec::new();
+	let mut satisfied: Vec<Satisfied> = Vec::new();
+	let mut queue = VecDeque::from([(id.clone(), version, None::<String>)]);
+
+	while let Some((id, mut version, required_by)) = queue.pop_front() {
+		if let Some(other) = planned.iter().find(|p| p.id == id) {
+			if !version.matches(&other.version) {
+				fatal!(
+					"'{}' requires '{}' version '{}', which conflicts with version '{}' that's being installed",
+					required_by.unwrap_or_default(),
+					id,
+					version,
+					other.version
+				);
+			}
+			continue;
+		}
+		if let Some(pos) = satisfied.iter().position(|s| s.id == id) {
+			let earlier = &mut satisfied[pos];
+			if version.matches(&earlier.version) {
+				earlier.requirement.comparators.extend(version.comparators);
+				continue;
+			}
+			// The installed version was enough for what required it before
+			// but not for this, so it's replaced with one that works for both
+			let earlier = satisfied.remove(pos);
+			version.comparators.extend(earlier.requirement.comparators);
+		}
+
+		let current = installed.get(&id).cloned();
+		if let (Some(parent), Some((current, _))) = (&required_by, &current) {
+			if version.matches(current) {
+				satisfied.push(Satisfied {
+					id,
+					version: current.clone(),
+					required_by: parent.clone(),
+					requirement: version,
+				});
+				continue;
+			}
+		}
+
+		let (info, package) =
+			fetch(&id, &version, platform, &config, &cache).unwrap_or_else(
+				|e| match &required_by {
+					Some(parent) => fatal!(
+						"Unable to install '{}' ('{}'), which '{}' requires: {}",
+						id,
+						version,
+						parent,
+						e
+					),
+					None => fatal!("Unable to install '{}': {}", id, e),
+				},
+			);
+
+		for dep in &info.dependencies {
+			if dep.importance() == DependencyImportance::Required
+				&& applies_to_platform(&dep.platforms, platform)
+			{
+				queue.push_back((dep.id.clone(), dep.version.clone(), Some(info.id.clone())));
+			}
+		}
+		planned.push(Planned {
+			id,
+			version: info.version,
+			package,
+			required_by,
+			replaces: current,
+		});
+	}
+
+	info!("Installing '{}' to profile '{}':", id, profile.name);
+	for mod_info in &planned {
+		let mut line = format!("  {} {} {}", "+".green(), mod_info.id, mod_info.version);
+		if let Some((version, _)) = &mod_info.replaces {
+			line += &format!(", replacing {version}");
+		}
+		if let Some(parent) = &mod_info.required_by {
+			line += &format!(" (required by {parent})");
+		}
+		println!("{line}");
+	}
+	for mod_info in &satisfied {
+		println!(
+			"  {} {} {} is already installed (required by {})",
+			"=".bright_black(),
+			mod_info.id,
+			mod_info.version,
+			mod_info.required_by
+		);
+	}
+
+	if !yes && !ask_confirm("Do you want to install these mods?", true) {
+		fatal!("Aborting");
+	}
+
+	for mod_info in &planned {
+		let dest = mods_dir.join(format!("{}.geode", mod_info.id));
+		fs::copy(&mod_info.package, &dest).nice_unwrap(format!(
+			"Unable to install '{}' to {}",
+			mod_info.id,
+			dest.display()
+		));
+		// Geode would otherwise find two copies of the mod
+		if let Some((_, old)) = mod_info.replaces.as_ref().filter(|(_, old)| *old != dest) {
+			fs::remove_file(old).nice_unwrap(format!("Unable to remove {}", old.display()));
+		}
+	}
+
+	if planned.len() == 1 {
+		done!("Mod installed");
+	} else {
+		done!("Installed {} mods", planned.len());
+	}
+}
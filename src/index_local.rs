@@ -0,0 +1,315 @@
+//! Index backed by a local directory of packages laid out as
+//! `{id}/{version}.geode`, which is used instead of the HTTP API when the
+//! index URL is a `file://` URL. Versions, platforms and hashes all come
+//! from the packages themselves
+
+use crate::config::Config;
+use crate::index::ServerModVersion;
+use crate::index_dev::ModDeveloper;
+use crate::mod_file::{try_parse_mod_info, GDVersion, ModFileInfo};
+use crate::server::{PaginatedData, ServerDependency, ServerMod, ServerModVersionInfo};
+use crate::util::error::{Error, WithPath};
+use reqwest::Url;
+use semver::{Version, VersionReq};
+use serde_json::Value;
+use std::fs;
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
+
+/// The directory of the index, if the index URL is a `file://` URL
+pub fn index_dir(config: &Config) -> Option<PathBuf> {
+	Url::parse(&config.index_url)
+		.ok()
+		.filter(|url| url.scheme() == "file")?
+		.to_file_path()
+		.ok()
+}
+
+/// Whether a mod supports a profile platform, going by the GD versions it
+/// lists like the index does
+fn supports_platform(gd: &GDVersion, platform: &str) -> bool {
+	match gd {
+		GDVersion::Simple(_) => true,
+		GDVersion::Detailed(gd) => match platform {
+			"win" => gd.win.is_some(),
+			"ios" => gd.ios.is_some(),
+			p if p.starts_with("mac") => gd.mac.is_some(),
+			p if p.starts_with("android") => gd.android.is_some(),
+			_ => false,
+		},
+	}
+}
+
+/// Every package of a mod in the index, newest first
+pub(crate) fn packages(dir: &Path, id: &str) -> Result<Vec<(ModFileInfo, PathBuf)>, Error> {
+	let mod_dir = dir.join(id);
+	let entries = match fs::read_dir(&mod_dir) {
+		Ok(entries) => entries,
+		Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(e) => return Err(e).with_path(&mod_dir),
+	};
+
+	let mut found = Vec::new();
+	for entry in entries {
+		let path = entry.with_path(&mod_dir)?.path();
+		if path.extension().is_none_or(|ext| ext != "geode") {
+			continue;
+		}
+		let info = try_parse_mod_info(&path)
+			.map_err(|e| Error::Index(format!("Invalid package {}: {e}", path.display())))?;
+		if info.id != id {
+			return Err(Error::Index(format!(
+				"Package {} is for mod '{}', not '{id}'",
+				path.display(),
+				info.id
+			)));
+		}
+		found.push((info, path));
+	}
+	found.sort_by(|a, b| b.0.version.cmp(&a.0.version));
+	Ok(found)
+}
+
+/// Versions of a mod in the index, filtered and paginated like the HTTP
+/// API does
+pub fn get_mod_versions(
+	dir: &Path,
+	id: &str,
+	page: u32,
+	per_page: u32,
+	platform: Option<&str>,
+	compare: Option<&str>,
+) -> Result<PaginatedData<ServerModVersion>, Error> {
+	get_mod_versions_where(dir, id, page, per_page, platform, compare, |_| true)
+}
+
+/// Like [`get_mod_versions`], but only for the versions that `keep` allows
+pub(crate) fn get_mod_versions_where(
+	dir: &Path,
+	id: &str,
+	page: u32,
+	per_page: u32,
+	platform: Option<&str>,
+	compare: Option<&str>,
+	keep: impl Fn(&Version) -> bool,
+) -> Result<PaginatedData<ServerModVersion>, Error> {
+	let compare = compare
+		.map(|c| {
+			VersionReq::parse(&c.replace('v', ""))
+				.map_err(|e| Error::Index(format!("Invalid version '{c}': {e}")))
+		})
+		.transpose()?;
+
+	let matching: Vec<_> = packages(dir, id)?
+		.into_iter()
+		.filter(|(info, _)| keep(&info.version))
+		.filter(|(info, _)| platform.is_none_or(|p| supports_platform(&info.gd, p)))
+		.filter(|(info, _)| compare.as_ref().is_none_or(|c| c.matches(&info.version)))
+		.collect();
+
+	let data = matching
+		.iter()
+		.skip(page.saturating_sub(1) as usize * per_page as usize)
+		.take(per_page as usize)
+		.map(|(info, path)| {
+			Ok(ServerModVersion {
+				name: info.name.clone(),
+				version: info.version.to_string(),
+				download_link: Url::from_file_path(path)
+					.map(String::from)
+					.unwrap_or_else(|_| path.display().to_string()),
+				hash: sha256::try_digest(path.as_path()).with_path(path)?,
+			})
+		})
+		.collect::<Result<_, Error>>()?;
+
+	Ok(PaginatedData {
+		data,
+		count: matching.len() as i32,
+	})
+}
+
+/// Read the package of a mod version from the index
+pub fn read_package(dir: &Path, id: &str, version: &str) -> Result<Vec<u8>, Error> {
+	let version = Version::parse(version.trim_start_matches('v'))
+		.map_err(|e| Error::Index(format!("Invalid version '{version}': {e}")))?;
+	let (_, path) = packages(dir, id)?
+		.into_iter()
+		.find(|(info, _)| info.version == version)
+		.ok_or_else(|| {
+			Error::Index(format!(
+				"Version '{version}' of {id} is not in {}",
+				dir.display()
+			))
+		})?;
+	fs::read(&path).with_path(&path)
+}
+
+/// Read mod.json, and about.md if there is one, from a package
+pub(crate) fn read_package_json(path: &Path) -> Result<(Value, Option<String>), Error> {
+	let mut zip = zip::ZipArchive::new(fs::File::open(path).with_path(path)?)?;
+	let mut mod_json = String::new();
+	zip.by_name("mod.json")?
+		.read_to_string(&mut mod_json)
+		.with_path(path)?;
+	let about = zip.by_name("about.md").ok().and_then(|mut f| {
+		let mut about = String::new();
+		f.read_to_string(&mut about).ok().map(|_| about)
+	});
+	let json = serde_json::from_str(&mod_json).map_err(|e| Error::ModJson(e.to_string()))?;
+	Ok((json, about))
+}
+
+/// Dependencies or incompatibilities of a mod.json in the form the index
+/// reports them, from either the object or the legacy array form
+fn list_requirements(
+	value: Option<&Value>,
+	default_importance: &str,
+) -> Option<Vec<ServerDependency>> {
+	let entries: Vec<(String, &Value)> = match value? {
+		Value::Object(map) => map.iter().map(|(id, v)| (id.clone(), v)).collect(),
+		Value::Array(list) => list
+			.iter()
+			.filter_map(|v| Some((v.get("id")?.as_str()?.to_string(), v)))
+			.collect(),
+		_ => return None,
+	};
+	Some(
+		entries
+			.into_iter()
+			.map(|(mod_id, v)| ServerDependency {
+				mod_id,
+				version: v
+					.as_str()
+					.or_else(|| v.get("version").and_then(Value::as_str))
+					.unwrap_or("*")
+					.to_string(),
+				importance: v
+					.get("importance")
+					.and_then(Value::as_str)
+					.unwrap_or(default_importance)
+					.to_string(),
+			})
+			.collect(),
+	)
+}
+
+/// A version of a mod in the form the index reports it, from its mod.json
+pub(crate) fn version_info(mod_json: &Value) -> ServerModVersionInfo {
+	let field = |key: &str| mod_json.get(key).and_then(Value::as_str);
+	let gd = |platform: &str| {
+		let gd = mod_json.get("gd")?;
+		gd.as_str()
+			.or_else(|| gd.get(platform)?.as_str())
+			.map(str::to_string)
+	};
+	let version = field("version").unwrap_or_default();
+	ServerModVersionInfo {
+		mod_id: field("id").unwrap_or_default().to_string(),
+		name: field("name").unwrap_or_default().to_string(),
+		version: Version::parse(version.trim_start_matches('v'))
+			.map(|v| v.to_string())
+			.unwrap_or_else(|_| version.to_string()),
+		description: field("description").map(str::to_string),
+		download_count: 0,
+		geode: field("geode").unwrap_or_default().to_string(),
+		early_load: mod_json
+			.get("early-load")
+			.and_then(Value::as_bool)
+			.unwrap_or(false),
+		api: mod_json.get("api").is_some(),
+		gd: [
+			("win", "win"),
+			("mac-intel", "mac"),
+			("mac-arm", "mac"),
+			("android32", "android"),
+			("android64", "android"),
+			("ios", "ios"),
+		]
+		.into_iter()
+		.filter_map(|(platform, key)| Some((platform.to_string(), Some(gd(key)?))))
+		.collect(),
+		dependencies: list_requirements(mod_json.get("dependencies"), "required"),
+		incompatibilities: list_requirements(mod_json.get("incompatibilities"), "breaking"),
+	}
+}
+
+/// A mod in the form the index reports it, from its packages ordered newest
+/// first. The developers, tags and about of the newest one are used
+pub(crate) fn mod_info(
+	id: &str,
+	packages: &[(ModFileInfo, PathBuf)],
+) -> Result<Option<ServerMod>, Error> {
+	let mut versions = Vec::new();
+	let mut latest = None;
+	for (_, path) in packages {
+		let (mod_json, about) = read_package_json(path)?;
+		versions.push(version_info(&mod_json));
+		latest.get_or_insert((mod_json, about));
+	}
+	let Some((mod_json, about)) = latest else {
+		return Ok(None);
+	};
+
+	let names: Vec<&str> = match (mod_json.get("developer"), mod_json.get("developers")) {
+		(Some(Value::String(dev)), _) => vec![dev],
+		(_, Some(Value::Array(devs))) => devs.iter().filter_map(Value::as_str).collect(),
+		_ => Vec::new(),
+	};
+	Ok(Some(ServerMod {
+		id: id.to_string(),
+		featured: false,
+		download_count: 0,
+		developers: names
+			.into_iter()
+			.enumerate()
+			.map(|(i, name)| ModDeveloper {
+				id: 0,
+				username: name.to_string(),
+				display_name: name.to_string(),
+				is_owner: i == 0,
+			})
+			.collect(),
+		versions,
+		tags: mod_json
+			.get("tags")
+			.and_then(Value::as_array)
+			.map(|tags| {
+				tags.iter()
+					.filter_map(Value::as_str)
+					.map(str::to_string)
+					.collect()
+			})
+			.unwrap_or_default(),
+		about,
+		repository: mod_json
+			.get("repository")
+			.or_else(|| mod_json.pointer("/links/source"))
+			.and_then(Value::as_str)
+			.map(str::to_string),
+	}))
+}
+
+/// Every mod in the index
+pub fn get_mods(dir: &Path) -> Result<Vec<ServerMod>, Error> {
+	let mut mods = Vec::new();
+	for entry in fs::read_dir(dir).with_path(dir)? {
+		let entry = entry.with_path(dir)?;
+		let Some(id) = entry.file_name().to_str().map(str::to_string) else {
+			continue;
+		};
+		if !entry.path().is_dir() {
+			continue;
+		}
+		if let Some(info) = mod_info(&id, &packages(dir, &id)?)? {
+			mods.push(info);
+		}
+	}
+	mods.sort_by(|a, b| a.id.cmp(&b.id));
+	Ok(mods)
+}
+
+/// A mod in the index, if it's there
+pub fn get_mod(dir: &Path, id: &str) -> Result<Option<ServerMod>, Error> {
+	mod_info(id, &packages(dir, id)?)
+}
@@ -0,0 +1,388 @@
+//! Read-only commands for browsing the mods on the index
+
+use crate::config::{self, Config};
+use crate::index::{self, get_index_url};
+use crate::index_local;
+use crate::mod_file::PlatformName;
+use crate::server::{ApiResponse, PaginatedData, ServerMod, ServerModVersionInfo};
+use crate::util::error::Error;
+use crate::util::logging::print_table;
+use crate::{fatal, info, NiceUnwrap};
+use clap::Args;
+use colored::Colorize;
+use reqwest::header::USER_AGENT;
+use reqwest::StatusCode;
+use semver::Version;
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+
+/// Filters for searching the index
+#[derive(Args, Debug, Default, Clone)]
+pub struct SearchFilters {
+	/// Only show mods with all of these tags
+	#[clap(long, value_delimiter = ',')]
+	pub tags: Vec<String>,
+
+	/// Only show mods for this GD version
+	#[clap(long)]
+	pub gd: Option<String>,
+
+	/// Only show mods available for this platform
+	#[clap(long, short)]
+	pub platform: Option<PlatformName>,
+
+	/// Only show featured mods
+	#[clap(long)]
+	pub featured: bool,
+
+	/// Only show mods by this developer
+	#[clap(long)]
+	pub developer: Option<String>,
+}
+
+/// Platforms the index lists GD versions for that a platform covers
+pub(crate) fn index_platforms(platform: PlatformName) -> &'static [&'static str] {
+	use PlatformName as P;
+	match platform {
+		P::Windows => &["win"],
+		P::MacOS => &["mac-intel", "mac-arm"],
+		P::MacIntel => &["mac-intel"],
+		P::MacArm => &["mac-arm"],
+		P::Android => &["android32", "android64"],
+		P::Android32 => &["android32"],
+		P::Android64 => &["android64"],
+		P::Ios => &["ios"],
+	}
+}
+
+/// The newest version of a mod
+pub fn latest_version(mod_info: &ServerMod) -> Option<&ServerModVersionInfo> {
+	mod_info
+		.versions
+		.iter()
+		.max_by_key(|v| Version::parse(v.version.trim_start_matches('v')).ok())
+}
+
+impl SearchFilters {
+	/// Whether a mod matches a search query and these filters, going by its
+	/// newest version like the index does
+	pub fn matches(&self, query: Option<&str>, mod_info: &ServerMod) -> bool {
+		let Some(latest) = latest_version(mod_info) else {
+			return false;
+		};
+		let has_gd = |platform: &str| latest.gd.get(platform).is_some_and(|gd| gd.is_some());
+
+		query.is_none_or(|q| {
+			let q = q.to_lowercase();
+			mod_info.id.to_lowercase().contains(&q) || latest.name.to_lowercase().contains(&q)
+		}) && self
+			.tags
+			.iter()
+			.all(|tag| mod_info.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+			&& self
+				.gd
+				.as_ref()
+				.is_none_or(|gd| latest.gd.values().flatten().any(|v| v == gd || v == "*"))
+			&& self
+				.platform
+				.is_none_or(|p| index_platforms(p).iter().any(|p| has_gd(p)))
+			&& (!self.featured || mod_info.featured)
+			&& self.developer.as_ref().is_none_or(|dev| {
+				mod_info.developers.iter().any(|d| {
+					d.username.eq_ignore_ascii_case(dev) || d.display_name.eq_ignore_ascii_case(dev)
+				})
+			})
+	}
+
+	fn query_params(&self) -> Vec<(&'static str, String)> {
+		let mut params = Vec::new();
+		if !self.tags.is_empty() {
+			params.push(("tags", self.tags.join(",")));
+		}
+		if let Some(gd) = &self.gd {
+			params.push(("gd", gd.clone()));
+		}
+		if let Some(platform) = self.platform {
+			params.push(("platforms", index_platforms(platform).join(",")));
+		}
+		if self.featured {
+			params.push(("featured", "true".into()));
+		}
+		if let Some(developer) = &self.developer {
+			params.push(("developer", developer.clone()));
+		}
+		params
+	}
+}
+
+/// Send a GET request to the index, returning None if what was asked for
+/// doesn't exist
+fn get_json<T: DeserializeOwned>(
+	path: &str,
+	query: &[(&str, String)],
+	config: &Config,
+) -> Result<Option<T>, Error> {
+	if config::is_offline() {
+		return Err(Error::Offline(format!("Requesting {path} from the index")));
+	}
+	let response = reqwest::blocking::Client::new()
+		.get(get_index_url(path, config))
+		.query(query)
+		.header(USER_AGENT, "GeodeCLI")
+		.send()?;
+
+	if response.status() == StatusCode::NOT_FOUND {
+		return Ok(None);
+	}
+	if !response.status().is_success() {
+		return Err(Error::Index(format!(
+			"Request to {path} failed with status {}",
+			response.status()
+		)));
+	}
+	response
+		.json::<ApiResponse<T>>()
+		.map(|body| Some(body.payload))
+		.map_err(|e| Error::Index(format!("Failed to parse index response: {e}")))
+}
+
+/// Search the index for mods
+pub fn search_mods(
+	query: Option<&str>,
+	filters: &SearchFilters,
+	page: u32,
+	per_page: u32,
+	config: &Config,
+) -> Result<PaginatedData<ServerMod>, Error> {
+	if let Some(dir) = index_local::index_dir(config) {
+		let found: Vec<_> = index_local::get_mods(&dir)?
+			.into_iter()
+			.filter(|m| filters.matches(query, m))
+			.collect();
+		let count = found.len() as i32;
+		let data = found
+			.into_iter()
+			.skip(page.saturating_sub(1) as usize * per_page as usize)
+			.take(per_page as usize)
+			.collect();
+		return Ok(PaginatedData { data, count });
+	}
+
+	let mut params = filters.query_params();
+	params.push(("page", page.to_string()));
+	params.push(("per_page", per_page.to_string()));
+	if let Some(query) = query {
+		params.push(("query", query.to_string()));
+	}
+	Ok(
+		get_json("v1/mods", &params, config)?.unwrap_or(PaginatedData {
+			data: Vec::new(),
+			count: 0,
+		}),
+	)
+}
+
+/// A mod on the index, if it's there
+pub fn get_mod(id: &str, config: &Config) -> Result<Option<ServerMod>, Error> {
+	if let Some(dir) = index_local::index_dir(config) {
+		return index_local::get_mod(&dir, id);
+	}
+	get_json(&format!("v1/mods/{id}"), &[], config)
+}
+
+/// A version of a mod on the index, including its dependencies
+pub fn get_mod_version(
+	id: &str,
+	version: &str,
+	config: &Config,
+) -> Result<Option<ServerModVersionInfo>, Error> {
+	if let Some(dir) = index_local::index_dir(config) {
+		return Ok(index_local::get_mod(&dir, id)?.and_then(|m| {
+			m.versions
+				.into_iter()
+				.find(|v| v.version.trim_start_matches('v') == version.trim_start_matches('v'))
+		}));
+	}
+	get_json(&format!("v1/mods/{id}/versions/{version}"), &[], config)
+}
+
+fn developer_names(mod_info: &ServerMod) -> String {
+	mod_info
+		.developers
+		.iter()
+		.map(|d| d.display_name.as_str())
+		.collect::<Vec<_>>()
+		.join(", ")
+}
+
+/// GD versions of a mod version along with the platforms they're for
+fn gd_versions(version: &ServerModVersionInfo) -> String {
+	let mut by_gd: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+	for (platform, gd) in &version.gd {
+		if let Some(gd) = gd {
+			by_gd.entry(gd).or_default().push(platform);
+		}
+	}
+	by_gd
+		.iter()
+		.map(|(gd, platforms)| format!("{gd} ({})", platforms.join(", ")))
+		.collect::<Vec<_>>()
+		.join(", ")
+}
+
+/// Search the index and print the results
+pub fn search(query: Option<String>, filters: SearchFilters, page: u32, per_page: u32, json: bool) {
+	let config = Config::new();
+	index::require_available(&config, "Searching the index");
+	let page = page.max(1);
+	let found = search_mods(query.as_deref(), &filters, page, per_page, &config)
+		.nice_unwrap("Unable to search the index");
+
+	if json {
+		println!("{}", serde_json::to_string_pretty(&found).unwrap());
+		return;
+	}
+
+	if found.data.is_empty() {
+		info!("No mods found");
+		return;
+	}
+
+	let rows: Vec<_> = found
+		.data
+		.iter()
+		.map(|m| {
+			let latest = latest_version(m);
+			vec![
+				m.id.clone(),
+				latest.map(|v| v.name.clone()).unwrap_or_default(),
+				latest.map(|v| v.version.clone()).unwrap_or_default(),
+				m.download_count.to_string(),
+				developer_names(m),
+			]
+		})
+		.collect();
+	print_table(
+		&["ID", "Name", "Version", "Downloads", "Developers"],
+		&rows,
+		|_, _| false,
+	);
+
+	let pages = (found.count as u32).div_ceil(per_page.max(1));
+	if pages > 1 {
+		info!(
+			"Page {} of {} ({} mods), use --page to see more",
+			page, pages, found.count
+		);
+	}
+}
+
+/// Print everything the index knows about a mod
+pub fn info(id: String, version: Option<String>, json: bool) {
+	let config = Config::new();
+	index::require_available(&config, "Looking up mods on the index");
+	let mut mod_info = get_mod(&id, &config)
+		.nice_unwrap("Unable to look up mod")
+		.unwrap_or_else(|| fatal!("Mod '{}' was not found on the index", id));
+
+	// Dependencies are only listed for a single version, which is the newest
+	// one unless asked otherwise
+	let version = match version {
+		Some(v) => v,
+		None => match latest_version(&mod_info) {
+			Some(latest) => latest.version.clone(),
+			None => fatal!("Mod '{}' has no versions on the index", id),
+		},
+	};
+	let details = get_mod_version(&id, &version, &config)
+		.nice_unwrap("Unable to look up mod version")
+		.unwrap_or_else(|| {
+			fatal!(
+				"Version '{}' of '{}' was not found on the index",
+				version,
+				id
+			)
+		});
+	match mod_info
+		.versions
+		.iter_mut()
+		.find(|v| v.version == details.version)
+	{
+		Some(listed) => *listed = details.clone(),
+		None => mod_info.versions.push(details.clone()),
+	}
+	mod_info
+		.versions
+		.sort_by_key(|v| std::cmp::Reverse(Version::parse(v.version.trim_start_matches('v')).ok()));
+
+	if json {
+		println!("{}", serde_json::to_string_pretty(&mod_info).unwrap());
+		return;
+	}
+
+	let shown = &details;
+	println!(
+		"{} ({}){}",
+		shown.name.bold(),
+		mod_info.id,
+		if mod_info.featured { " - featured" } else { "" }
+	);
+	if let Some(description) = &shown.description {
+		println!("{description}");
+	}
+	println!();
+	println!(
+		"{} {}",
+		"Developers:".bold(),
+		mod_info
+			.developers
+			.iter()
+			.map(|d| if d.is_owner {
+				format!("{} (owner)", d.display_name)
+			} else {
+				d.display_name.clone()
+			})
+			.collect::<Vec<_>>()
+			.join(", ")
+	);
+	println!("{} {}", "Downloads:".bold(), mod_info.download_count);
+	if !mod_info.tags.is_empty() {
+		println!("{} {}", "Tags:".bold(), mod_info.tags.join(", "));
+	}
+	if let Some(repository) = &mod_info.repository {
+		println!("{} {}", "Repository:".bold(), repository);
+	}
+
+	println!();
+	println!("{}", "Versions:".bold());
+	print_table(
+		&["Version", "Geode", "GD", "Downloads"],
+		&mod_info
+			.versions
+			.iter()
+			.map(|v| {
+				vec![
+					v.version.clone(),
+					v.geode.clone(),
+					gd_versions(v),
+					v.download_count.to_string(),
+				]
+			})
+			.collect::<Vec<_>>(),
+		|_, _| false,
+	);
+
+	for (title, list) in [
+		("Dependencies", &shown.dependencies),
+		("Incompatibilities", &shown.incompatibilities),
+	] {
+		let Some(list) = list.as_ref().filter(|l| !l.is_empty()) else {
+			continue;
+		};
+		println!();
+		println!("{}", format!("{title} of {}:", shown.version).bold());
+		for dep in list {
+			println!("  {} {} ({})", dep.mod_id, dep.version, dep.importance);
+		}
+	}
+}
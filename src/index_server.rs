@@ -0,0 +1,695 @@
+//! A minimal local implementation of the Geode Index API, for testing mod
+//! submissions and dependency resolution without touching the production
+//! index. Packages are stored as `{id}/{version}.geode`, the same layout a
+//! `file://` index uses, and developers, tokens and review status are kept
+//! in `index.json` next to them
+
+use crate::config::Config;
+use crate::index_dev::ModDeveloper;
+use crate::index_search::{self, SearchFilters};
+use crate::mod_file::try_parse_mod_info;
+use crate::server::ServerMod;
+use crate::{done, fatal, info, warn, NiceUnwrap};
+use crate::{index, index_local};
+use rand::Rng;
+use reqwest::Url;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+const STATE_FILE: &str = "index.json";
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+enum VersionStatus {
+	Pending,
+	Accepted,
+	Rejected,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct VersionState {
+	status: VersionStatus,
+	#[serde(default)]
+	download_count: i32,
+	/// Why the version was rejected
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	info: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct ModState {
+	/// Developer IDs, the first one being the owner
+	developers: Vec<i32>,
+	#[serde(default)]
+	featured: bool,
+	versions: BTreeMap<String, VersionState>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Developer {
+	id: i32,
+	username: String,
+	display_name: String,
+	verified: bool,
+	admin: bool,
+	token: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct State {
+	developers: Vec<Developer>,
+	mods: BTreeMap<String, ModState>,
+}
+
+/// A response, or an error with its status code
+type Reply = Result<Response<std::io::Cursor<Vec<u8>>>, (u16, String)>;
+
+fn new_token() -> String {
+	let mut rng = rand::rng();
+	(0..32)
+		.map(|_| format!("{:x}", rng.random_range(0..16)))
+		.collect()
+}
+
+fn json_reply(payload: Value) -> Reply {
+	Ok(
+		Response::from_string(json!({ "error": "", "payload": payload }).to_string())
+			.with_header(Header::from_bytes("Content-Type", "application/json").unwrap()),
+	)
+}
+
+fn empty_reply() -> Reply {
+	Ok(Response::from_data(Vec::new()).with_status_code(204))
+}
+
+fn error<T>(status: u16, message: impl Into<String>) -> Result<T, (u16, String)> {
+	Err((status, message.into()))
+}
+
+struct IndexServer {
+	dir: PathBuf,
+	base_url: String,
+	state: State,
+}
+
+impl IndexServer {
+	fn open(dir: PathBuf, base_url: String) -> IndexServer {
+		let state_path = dir.join(STATE_FILE);
+		let mut state: State = if state_path.exists() {
+			serde_json::from_str(
+				&fs::read_to_string(&state_path).nice_unwrap("Unable to read index state"),
+			)
+			.nice_unwrap("Unable to parse index state")
+		} else {
+			State::default()
+		};
+
+		if state.developers.is_empty() {
+			state.developers.push(Developer {
+				id: 1,
+				username: "dev".into(),
+				display_name: "Developer".into(),
+				verified: true,
+				admin: true,
+				token: new_token(),
+			});
+		}
+
+		// Packages that were put into the directory by hand are treated as
+		// published by the first developer
+		let owner = state.developers[0].id;
+		for entry in fs::read_dir(&dir).nice_unwrap("Unable to read index directory") {
+			let entry = entry.nice_unwrap("Unable to read index directory");
+			let Some(id) = entry.file_name().to_str().map(str::to_string) else {
+				continue;
+			};
+			if !entry.path().is_dir() {
+				continue;
+			}
+			let packages = index_local::packages(&dir, &id).unwrap_or_else(|e| {
+				warn!("Skipping {}: {}", id, e);
+				Vec::new()
+			});
+			for (info, _) in packages {
+				state
+					.mods
+					.entry(id.clone())
+					.or_insert_with(|| ModState {
+						developers: vec![owner],
+						..Default::default()
+					})
+					.versions
+					.entry(info.version.to_string())
+					.or_insert(VersionState {
+						status: VersionStatus::Accepted,
+						download_count: 0,
+						info: None,
+					});
+			}
+		}
+
+		let server = IndexServer {
+			dir,
+			base_url,
+			state,
+		};
+		server.save();
+		server
+	}
+
+	fn save(&self) {
+		fs::write(
+			self.dir.join(STATE_FILE),
+			serde_json::to_string_pretty(&self.state).unwrap(),
+		)
+		.nice_unwrap("Unable to save index state");
+	}
+
+	fn package_path(&self, id: &str, version: &str) -> PathBuf {
+		self.dir.join(id).join(format!("{version}.geode"))
+	}
+
+	fn developer(&self, id: i32) -> Option<&Developer> {
+		self.state.developers.iter().find(|d| d.id == id)
+	}
+
+	fn profile(dev: &Developer) -> Value {
+		json!({
+			"id": dev.id,
+			"username": dev.username,
+			"display_name": dev.display_name,
+			"verified": dev.verified,
+			"admin": dev.admin,
+		})
+	}
+
+	/// The developer a request's bearer token belongs to
+	fn authenticate(&self, request: &Request) -> Result<Developer, (u16, String)> {
+		let token = request
+			.headers()
+			.iter()
+			.find(|h| h.field.equiv("Authorization"))
+			.and_then(|h| h.value.as_str().strip_prefix("Bearer "));
+		match token.and_then(|t| self.state.developers.iter().find(|d| d.token == t)) {
+			Some(dev) => Ok(dev.clone()),
+			None => error(401, "Invalid or missing token"),
+		}
+	}
+
+	fn require_admin(&self, request: &Request) -> Result<Developer, (u16, String)> {
+		let dev = self.authenticate(request)?;
+		if !dev.admin {
+			return error(403, "Only admins can do this");
+		}
+		Ok(dev)
+	}
+
+	fn mod_developers(&self, state: &ModState) -> Vec<ModDeveloper> {
+		state
+			.developers
+			.iter()
+			.enumerate()
+			.filter_map(|(i, dev)| {
+				let dev = self.developer(*dev)?;
+				Some(ModDeveloper {
+					id: dev.id,
+					username: dev.username.clone(),
+					display_name: dev.display_name.clone(),
+					is_owner: i == 0,
+				})
+			})
+			.collect()
+	}
+
+	/// A mod in the form the index lists it, with only the versions that
+	/// have a status
+	fn server_mod(&self, id: &str, status: VersionStatus) -> Option<ServerMod> {
+		let state = self.state.mods.get(id)?;
+		let packages: Vec<_> = index_local::packages(&self.dir, id)
+			.ok()?
+			.into_iter()
+			.filter(|(info, _)| {
+				state
+					.versions
+					.get(&info.version.to_string())
+					.is_some_and(|v| v.status == status)
+			})
+			.collect();
+		let mut server_mod = index_local::mod_info(id, &packages).ok()??;
+		server_mod.featured = state.featured;
+		server_mod.download_count = state.versions.values().map(|v| v.download_count).sum();
+		server_mod.developers = self.mod_developers(state);
+		for version in &mut server_mod.versions {
+			version.download_count = state
+				.versions
+				.get(&version.version)
+				.map_or(0, |v| v.download_count);
+		}
+		Some(server_mod)
+	}
+
+	/// A mod as listed to its developers
+	fn dev_mod_json(&self, id: &str, status: VersionStatus) -> Option<Value> {
+		let server_mod = self.server_mod(id, status)?;
+		let versions: Vec<_> = server_mod
+			.versions
+			.iter()
+			.map(|v| {
+				json!({
+					"name": v.name,
+					"version": v.version,
+					"download_count": v.download_count,
+					"validated": status == VersionStatus::Accepted,
+				})
+			})
+			.collect();
+		Some(json!({
+			"id": server_mod.id,
+			"featured": server_mod.featured,
+			"download_count": server_mod.download_count,
+			"versions": versions,
+			"developers": server_mod.developers,
+		}))
+	}
+
+	/// Read a submitted package, which has to be downloadable or already be
+	/// in the index directory, so requests can't read any other files on
+	/// this machine
+	fn read_submitted(&self, link: &str) -> Result<Vec<u8>, (u16, String)> {
+		if link.starts_with("http://") || link.starts_with("https://") {
+			return index::read_download_link(link).or_else(|e| error(400, e));
+		}
+		let path = Url::parse(link)
+			.ok()
+			.filter(|url| url.scheme() == "file")
+			.and_then(|url| url.to_file_path().ok())
+			.unwrap_or_else(|| PathBuf::from(link));
+		let inside = fs::canonicalize(self.dir.join(path))
+			.ok()
+			.filter(|path| fs::canonicalize(&self.dir).is_ok_and(|dir| path.starts_with(dir)));
+		let Some(path) = inside else {
+			return error(
+				400,
+				"download_link must be an http(s) URL or the path of a package in the index directory",
+			);
+		};
+		fs::read(&path).or_else(|e| error(400, format!("{}: {e}", path.display())))
+	}
+
+	/// Store a submitted package as a pending version, checking that it can
+	/// be submitted by this developer
+	fn submit(&mut self, dev: &Developer, body: &Value, mod_id: Option<&str>) -> Reply {
+		let Some(link) = body.get("download_link").and_then(Value::as_str) else {
+			return error(400, "Missing download_link");
+		};
+		let data = self.read_submitted(link)?;
+
+		let temp = tempfile::Builder::new()
+			.suffix(".geode")
+			.tempfile()
+			.or_else(|e| error(500, e.to_string()))?;
+		fs::write(temp.path(), &data).or_else(|e| error(500, e.to_string()))?;
+		let info = try_parse_mod_info(temp.path())
+			.or_else(|e| error(400, format!("Invalid package: {e}")))?;
+		let version = info.version.to_string();
+
+		match (mod_id, self.state.mods.get(&info.id)) {
+			(None, Some(_)) => return error(409, format!("Mod '{}' already exists", info.id)),
+			(Some(id), _) if id != info.id => {
+				return error(400, format!("Package is for mod '{}', not '{id}'", info.id))
+			}
+			(Some(id), None) => return error(404, format!("Mod '{id}' does not exist")),
+			(Some(_), Some(state)) if !state.developers.contains(&dev.id) => {
+				return error(403, "You are not a developer of this mod")
+			}
+			(Some(_), Some(state)) if state.versions.contains_key(&version) => {
+				return error(409, format!("Version '{version}' already exists"))
+			}
+			_ => {}
+		}
+
+		let dest = self.package_path(&info.id, &version);
+		fs::create_dir_all(dest.parent().unwrap()).or_else(|e| error(500, e.to_string()))?;
+		fs::write(&dest, &data).or_else(|e| error(500, e.to_string()))?;
+
+		self.state
+			.mods
+			.entry(info.id.clone())
+			.or_insert_with(|| ModState {
+				developers: vec![dev.id],
+				..Default::default()
+			})
+			.versions
+			.insert(
+				version.clone(),
+				VersionState {
+					status: VersionStatus::Pending,
+					download_count: 0,
+					info: None,
+				},
+			);
+		self.save();
+		info!("Received {} {} (pending)", info.id, version);
+		empty_reply()
+	}
+
+	fn handle(&mut self, request: &mut Request) -> Reply {
+		let url = Url::parse(&format!("http://localhost{}", request.url()))
+			.or_else(|_| error(400, "Invalid URL"))?;
+		let query = |name: &str| {
+			url.query_pairs()
+				.find(|(k, _)| k == name)
+				.map(|(_, v)| v.into_owned())
+		};
+		let number =
+			|name: &str, default: u32| query(name).and_then(|v| v.parse().ok()).unwrap_or(default);
+		let segments: Vec<String> = url
+			.path_segments()
+			.into_iter()
+			.flatten()
+			.filter(|s| !s.is_empty())
+			.map(str::to_string)
+			.collect();
+		let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+		let mut body = String::new();
+		request
+			.as_reader()
+			.read_to_string(&mut body)
+			.or_else(|_| error(400, "Unable to read request body"))?;
+		let body: Value = serde_json::from_str(&body).unwrap_or(Value::Null);
+
+		match (request.method(), segments.as_slice()) {
+			// Tokens are only ever shown in the terminal running the index,
+			// as anyone who can reach it could log in otherwise
+			(Method::Post, ["v1", "login", "github", ..]) => error(
+				403,
+				"The local index doesn't support logging in through GitHub, log in \
+				with the token it printed on startup using `geode index login --token`",
+			),
+
+			(Method::Get, ["v1", "me"]) => json_reply(Self::profile(&self.authenticate(request)?)),
+			(Method::Put, ["v1", "me"]) => {
+				let dev = self.authenticate(request)?;
+				let Some(name) = body.get("display_name").and_then(Value::as_str) else {
+					return error(400, "Missing display_name");
+				};
+				let dev = self.state.developers.iter_mut().find(|d| d.id == dev.id);
+				dev.unwrap().display_name = name.to_string();
+				self.save();
+				empty_reply()
+			}
+			(Method::Delete, ["v1", "me", "tokens"]) => {
+				let dev = self.authenticate(request)?;
+				let dev = self.state.developers.iter_mut().find(|d| d.id == dev.id);
+				dev.unwrap().token = new_token();
+				self.save();
+				empty_reply()
+			}
+			(Method::Get, ["v1", "me", "mods"]) => {
+				let dev = self.authenticate(request)?;
+				let status = match query("status").as_deref() {
+					Some("pending") => VersionStatus::Pending,
+					_ => VersionStatus::Accepted,
+				};
+				let mods: Vec<_> = self
+					.state
+					.mods
+					.iter()
+					.filter(|(_, m)| m.developers.contains(&dev.id))
+					.filter_map(|(id, _)| self.dev_mod_json(id, status))
+					.collect();
+				json_reply(json!(mods))
+			}
+
+			(Method::Get, ["v1", "mods"]) => {
+				let status = match query("status").as_deref() {
+					Some("pending") => VersionStatus::Pending,
+					Some("rejected") => VersionStatus::Rejected,
+					_ => VersionStatus::Accepted,
+				};
+				if status != VersionStatus::Accepted {
+					self.require_admin(request)?;
+				}
+				let (page, per_page) = (number("page", 1).max(1), number("per_page", 10));
+				let filters = SearchFilters {
+					tags: query("tags")
+						.map(|t| t.split(',').map(str::to_string).collect())
+						.unwrap_or_default(),
+					gd: query("gd"),
+					platform: None,
+					featured: query("featured").is_some_and(|f| f == "true"),
+					developer: query("developer"),
+				};
+				let platforms = query("platforms");
+				let search = query("query");
+				let mods: Vec<_> = self
+					.state
+					.mods
+					.keys()
+					.filter_map(|id| self.server_mod(id, status))
+					.filter(|m| filters.matches(search.as_deref(), m))
+					.filter(|m| {
+						platforms.as_ref().is_none_or(|platforms| {
+							let latest = index_search::latest_version(m);
+							platforms.split(',').any(|p| {
+								latest.is_some_and(|v| v.gd.get(p).is_some_and(Option::is_some))
+							})
+						})
+					})
+					.collect();
+				let data: Vec<_> = mods
+					.iter()
+					.skip((page - 1) as usize * per_page as usize)
+					.take(per_page as usize)
+					.collect();
+				json_reply(json!({ "data": data, "count": mods.len() }))
+			}
+			(Method::Get, ["v1", "mods", id]) => match self.server_mod(id, VersionStatus::Accepted)
+			{
+				Some(server_mod) => json_reply(json!(server_mod)),
+				None => error(404, format!("Mod '{id}' not found")),
+			},
+			(Method::Post, ["v1", "mods"]) => {
+				let dev = self.authenticate(request)?;
+				self.submit(&dev, &body, None)
+			}
+
+			(Method::Get, ["v1", "mods", id, "versions"]) => {
+				let accepted = |v: &Version| {
+					self.state
+						.mods
+						.get(*id)
+						.and_then(|m| m.versions.get(&v.to_string()))
+						.is_some_and(|v| v.status == VersionStatus::Accepted)
+				};
+				let mut versions = index_local::get_mod_versions_where(
+					&self.dir,
+					id,
+					number("page", 1),
+					number("per_page", 10),
+					query("platforms").as_deref(),
+					query("compare").as_deref(),
+					accepted,
+				)
+				.or_else(|e| error(500, e.to_string()))?;
+				for version in &mut versions.data {
+					version.download_link = format!(
+						"{}/v1/mods/{id}/versions/{}/download",
+						self.base_url, version.version
+					);
+				}
+				json_reply(json!(versions))
+			}
+			(Method::Post, ["v1", "mods", id, "versions"]) => {
+				let dev = self.authenticate(request)?;
+				self.submit(&dev, &body, Some(id))
+			}
+			(Method::Get, ["v1", "mods", id, "versions", version]) => {
+				let found = self
+					.server_mod(id, VersionStatus::Accepted)
+					.and_then(|m| m.versions.into_iter().find(|v| v.version == *version));
+				match found {
+					Some(found) => json_reply(json!(found)),
+					None => error(404, format!("Version '{version}' of '{id}' not found")),
+				}
+			}
+			(Method::Put, ["v1", "mods", id, "versions", version]) => {
+				self.require_admin(request)?;
+				let status = match body.get("status").and_then(Value::as_str) {
+					Some("accepted") => VersionStatus::Accepted,
+					Some("rejected") => VersionStatus::Rejected,
+					_ => return error(400, "Status must be 'accepted' or 'rejected'"),
+				};
+				let reason = body.get("info").and_then(Value::as_str).map(str::to_string);
+				let Some(state) = self
+					.state
+					.mods
+					.get_mut(*id)
+					.and_then(|m| m.versions.get_mut(*version))
+				else {
+					return error(404, format!("Version '{version}' of '{id}' not found"));
+				};
+				state.status = status;
+				state.info = reason;
+				self.save();
+				info!("Marked {} {} as {:?}", id, version, status);
+				empty_reply()
+			}
+			(Method::Get, ["v1", "mods", id, "versions", version, "download"]) => {
+				let Some(state) = self.state.mods.get(*id) else {
+					return error(404, format!("Mod '{id}' not found"));
+				};
+				let Some(status) = state.versions.get(*version).map(|v| v.status) else {
+					return error(404, format!("Version '{version}' of '{id}' not found"));
+				};
+				// Unpublished versions can only be downloaded by the mod's
+				// developers and admins
+				if status != VersionStatus::Accepted {
+					let dev = self.authenticate(request)?;
+					if !dev.admin && !state.developers.contains(&dev.id) {
+						return error(404, format!("Version '{version}' of '{id}' not found"));
+					}
+				}
+				let data = fs::read(self.package_path(id, version))
+					.or_else(|e| error(500, e.to_string()))?;
+				if let Some(v) = self
+					.state
+					.mods
+					.get_mut(*id)
+					.and_then(|m| m.versions.get_mut(*version))
+				{
+					v.download_count += 1;
+				}
+				self.save();
+				Ok(Response::from_data(data))
+			}
+
+			(Method::Post, ["v1", "mods", id, "developers"]) => {
+				let dev = self.authenticate(request)?;
+				let Some(username) = body.get("username").and_then(Value::as_str) else {
+					return error(400, "Missing username");
+				};
+				let Some(added) = self
+					.state
+					.developers
+					.iter()
+					.find(|d| d.username == username)
+				else {
+					return error(404, format!("Developer '{username}' not found"));
+				};
+				let added = added.id;
+				let Some(state) = self.state.mods.get_mut(*id) else {
+					return error(404, format!("Mod '{id}' not found"));
+				};
+				if state.developers.first() != Some(&dev.id) {
+					return error(403, "Only the owner of the mod can add developers");
+				}
+				if !state.developers.contains(&added) {
+					state.developers.push(added);
+				}
+				self.save();
+				empty_reply()
+			}
+			(Method::Delete, ["v1", "mods", id, "developers", username]) => {
+				let dev = self.authenticate(request)?;
+				let Some(removed) = self
+					.state
+					.developers
+					.iter()
+					.find(|d| d.username == *username)
+				else {
+					return error(404, format!("Developer '{username}' not found"));
+				};
+				let removed = removed.id;
+				let Some(state) = self.state.mods.get_mut(*id) else {
+					return error(404, format!("Mod '{id}' not found"));
+				};
+				if state.developers.first() != Some(&dev.id) {
+					return error(403, "Only the owner of the mod can remove developers");
+				}
+				if state.developers.first() == Some(&removed) {
+					return error(400, "The owner of a mod can't be removed");
+				}
+				state.developers.retain(|d| *d != removed);
+				self.save();
+				empty_reply()
+			}
+
+			(Method::Get, ["v1", "developers"]) => {
+				let search = query("query").unwrap_or_default().to_lowercase();
+				let found: Vec<_> = self
+					.state
+					.developers
+					.iter()
+					.filter(|d| d.username.to_lowercase().contains(&search))
+					.map(Self::profile)
+					.collect();
+				json_reply(json!({ "data": found, "count": found.len() }))
+			}
+			(Method::Put, ["v1", "developers", id]) => {
+				self.require_admin(request)?;
+				let Some(verified) = body.get("verified").and_then(Value::as_bool) else {
+					return error(400, "Missing verified");
+				};
+				let Some(dev) = self
+					.state
+					.developers
+					.iter_mut()
+					.find(|d| d.id.to_string() == *id)
+				else {
+					return error(404, format!("Developer '{id}' not found"));
+				};
+				dev.verified = verified;
+				self.save();
+				empty_reply()
+			}
+
+			_ => error(404, "Not found"),
+		}
+	}
+}
+
+/// Serve the index API from a directory of packages until interrupted
+pub fn serve(dir: PathBuf, host: &str, port: u16) {
+	fs::create_dir_all(&dir).nice_unwrap("Unable to create index directory");
+	let address = format!("{host}:{port}");
+	let server =
+		Server::http(&address).unwrap_or_else(|e| fatal!("Unable to listen on {}: {}", address, e));
+	let base_url = format!("http://{address}");
+	let mut index = IndexServer::open(dir, base_url.clone());
+
+	done!(
+		"Serving local index from {} at {}",
+		index.dir.display(),
+		base_url
+	);
+	if Config::new().index_url.trim_end_matches('/') != base_url {
+		info!("Point the CLI at it with `geode index url -u {}`", base_url);
+	}
+	info!(
+		"Log in as '{}' with `geode index login --token {}`",
+		index.state.developers[0].username, index.state.developers[0].token
+	);
+
+	for mut request in server.incoming_requests() {
+		let line = format!("{} {}", request.method(), request.url());
+		let response = match index.handle(&mut request) {
+			Ok(response) => response,
+			Err((status, message)) => {
+				Response::from_string(json!({ "error": message, "payload": "" }).to_string())
+					.with_status_code(status)
+					.with_header(Header::from_bytes("Content-Type", "application/json").unwrap())
+			}
+		};
+		info!("{} -> {}", line, response.status_code().0);
+		if let Err(e) = request.respond(response) {
+			warn!("Unable to respond to {}: {}", line, e);
+		}
+	}
+}
@@ -39,13 +39,14 @@ pub enum Info {
 	Setup {},
 }
 
-const CONFIGURABLES: [&str; 6] = [
+const CONFIGURABLES: [&str; 7] = [
 	"default-developer",
 	"sdk-path",
 	"sdk-nightly",
 	"current-profile",
 	"index-token",
 	"index-url",
+	"offline",
 ];
 
 fn get_bool(value: &str) -> Option<bool> {
@@ -77,6 +78,9 @@ pub fn subcommand(cmd: Info) {
 				return;
 			} else if field == "index-token" {
 				config.index_token = Some(value);
+			} else if field == "offline" {
+				config.offline =
+					get_bool(&value).nice_unwrap(format!("'{}' cannot be parsed as a bool", value));
 			} else if field == "index-url" {
 				fail!("Set the Index URL using `geode index set-url <URL>`");
 				return;
@@ -111,6 +115,12 @@ pub fn subcommand(cmd: Info) {
 				config.index_token.as_deref().unwrap_or("")
 			} else if field == "index-url" {
 				config.index_url.as_ref()
+			} else if field == "offline" {
+				if config.offline {
+					"true"
+				} else {
+					"false"
+				}
 			} else if raw {
 				std::process::exit(1);
 			} else {
@@ -0,0 +1,34 @@
+//! Internals of the Geode CLI, for tools that want to parse mod.json files,
+//! build resources and packages or talk to the index without going through
+//! the `geode` executable. Functions that make up this API return [`Error`]
+//! instead of exiting the process
+
+pub mod cli;
+pub mod file;
+pub mod font;
+pub mod index;
+pub mod index_admin;
+pub mod index_auth;
+pub mod index_dev;
+pub mod index_install;
+pub mod index_local;
+pub mod index_search;
+pub mod index_server;
+pub mod info;
+pub mod package;
+pub mod profile;
+pub mod project;
+pub mod project_build;
+pub mod project_codegen;
+pub mod project_deps;
+pub mod project_lint;
+pub mod project_lock;
+pub mod project_migrate;
+pub mod sdk;
+pub mod server;
+pub mod template;
+pub mod util;
+
+pub use util::error::Error;
+pub use util::mod_file::ModFileInfo;
+pub use util::*;
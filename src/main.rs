@@ -1,23 +1,7 @@
-mod cli;
-mod file;
-mod index;
-mod index_admin;
-mod index_auth;
-mod index_dev;
-mod info;
-mod package;
-mod profile;
-mod project;
-mod project_build;
-mod sdk;
-mod server;
-mod template;
-mod util;
-
-use crate::profile::RunBackground;
 use clap::{CommandFactory, Parser};
-use cli::{Args, GeodeCommands};
-use util::*;
+use geode::cli::{Args, GeodeCommands};
+use geode::profile::RunBackground;
+use geode::*;
 
 fn main() {
 	#[cfg(windows)]
@@ -27,6 +11,9 @@ fn main() {
 	};
 
 	let args = Args::parse();
+	if args.offline {
+		config::set_offline(true);
+	}
 
 	match args.command {
 		GeodeCommands::New { path } => template::build_template(path),
@@ -34,6 +21,7 @@ fn main() {
 		GeodeCommands::Config { commands } => info::subcommand(commands),
 		GeodeCommands::Sdk { commands } => sdk::subcommand(commands),
 		GeodeCommands::Package { commands } => package::subcommand(commands),
+		GeodeCommands::Font { commands } => font::subcommand(commands),
 		GeodeCommands::Project { commands } => project::subcommand(commands),
 		GeodeCommands::Index { commands } => index::subcommand(commands),
 		GeodeCommands::Run {
@@ -7,11 +7,15 @@ use zip::write::FileOptions;
 use zip::ZipWriter;
 
 use crate::config::Config;
+use crate::project::ResolveArgs;
+use crate::project_lock::LockMode;
 use crate::util::bmfont;
 use crate::util::cache::CacheBundle;
-use crate::util::mod_file::{parse_mod_info, ModFileInfo};
+use crate::util::error::{Error, WithPath};
+use crate::util::mod_file::{parse_mod_info, try_parse_mod_info, ModFileInfo};
+use crate::util::mod_schema;
 use crate::util::spritesheet;
-use crate::{cache, project};
+use crate::{cache, project, project_codegen};
 use crate::{done, fatal, info, warn, NiceUnwrap};
 
 #[derive(Subcommand, Debug)]
@@ -80,6 +84,11 @@ pub enum Package {
 		/// Less verbose output
 		#[clap(long)]
 		shut_up: bool,
+
+		/// Where to write the C++ header with the names of the created
+		/// resources. Defaults to Resources.hpp in the output folder
+		#[clap(long)]
+		header: Option<PathBuf>,
 	},
 }
 
@@ -100,20 +109,23 @@ pub fn install(config: &Config, pkg_path: &Path) {
 	);
 }
 
-fn zip_folder(path: &Path, output: &Path) {
+fn zip_folder(path: &Path, output: &Path) -> Result<(), Error> {
 	info!("Zipping");
 
 	// Setup zip
-	let mut zip_file = ZipWriter::new(fs::File::create(output).unwrap());
+	let mut zip_file = ZipWriter::new(fs::File::create(output).with_path(output)?);
 	let zip_options =
 		FileOptions::<()>::default().compression_method(zip::CompressionMethod::Deflated);
 
 	// Iterate files in target path
 	for item in walkdir::WalkDir::new(path) {
-		let item = item.unwrap();
+		let item = item.map_err(|e| Error::Io {
+			path: e.path().unwrap_or(path).to_path_buf(),
+			source: e.into(),
+		})?;
 
 		// Only look at files
-		if item.metadata().unwrap().is_file() {
+		if item.file_type().is_file() {
 			// Relativize
 			let mut relative_path = item
 				.path()
@@ -125,12 +137,14 @@ fn zip_folder(path: &Path, output: &Path) {
 
 			relative_path = relative_path.replace('\\', "/");
 
-			zip_file.start_file(relative_path, zip_options).unwrap();
-			zip_file.write_all(&fs::read(item.path()).unwrap()).unwrap();
+			zip_file.start_file(relative_path, zip_options)?;
+			zip_file
+				.write_all(&fs::read(item.path()).with_path(item.path())?)
+				.with_path(output)?;
 		}
 	}
 
-	zip_file.finish().nice_unwrap("Unable to zip");
+	zip_file.finish()?;
 
 	done!(
 		"Successfully packaged {}",
@@ -141,18 +155,27 @@ fn zip_folder(path: &Path, output: &Path) {
 			.unwrap()
 			.bright_yellow()
 	);
+	Ok(())
+}
+
+/// Copy a file, reporting the source path if it fails
+fn copy_file(from: &Path, to: &Path) -> Result<(), Error> {
+	fs::copy(from, to).with_path(from).map(|_| ())
 }
 
-fn create_resources(
+/// Build the spritesheets, fonts and sprites of a mod and copy its other
+/// resources. Libraries are placed in `working_dir`, everything else in
+/// `output_dir`
+pub fn create_resources(
 	mod_info: &ModFileInfo,
 	#[allow(unused_mut)] mut cache_bundle: &mut Option<CacheBundle>,
 	cache: &mut cache::ResourceCache,
 	working_dir: &Path,
 	output_dir: &PathBuf,
 	shut_up: bool,
-) {
+) -> Result<(), Error> {
 	// Make sure output directory exists
-	fs::create_dir_all(output_dir).nice_unwrap("Could not create resource directory");
+	fs::create_dir_all(output_dir).with_path(output_dir)?;
 
 	// Create spritesheets
 	for sheet in mod_info.resources.spritesheets.values() {
@@ -162,13 +185,14 @@ fn create_resources(
 			cache_bundle,
 			mod_info,
 			shut_up,
-		);
+		)?;
 		cache.add_sheet(sheet, sheet_file.cache_name(working_dir));
 	}
 
 	// Create fonts
 	for font in mod_info.resources.fonts.values() {
-		let font_file = bmfont::get_font_bundles(font, output_dir, cache_bundle, mod_info, shut_up);
+		let font_file =
+			bmfont::get_font_bundles(font, output_dir, cache_bundle, mod_info, shut_up)?;
 		cache.add_font(font, font_file.cache_name(working_dir));
 	}
 
@@ -177,26 +201,16 @@ fn create_resources(
 	}
 	// Resize sprites
 	for sprite_path in &mod_info.resources.sprites {
-		let mut sprite = spritesheet::read_to_image(sprite_path);
+		let mut sprite = spritesheet::read_to_image(sprite_path)?;
 
 		// Sprite base name
 		let base = sprite_path.file_stem().and_then(|x| x.to_str()).unwrap();
 
-		// Collect all errors
-		(|| {
-			spritesheet::downscale(&mut sprite, 1);
-			sprite.save(output_dir.join(base.to_string() + "-uhd.png"))?;
-
-			spritesheet::downscale(&mut sprite, 2);
-			sprite.save(output_dir.join(base.to_string() + "-hd.png"))?;
-
-			spritesheet::downscale(&mut sprite, 2);
-			sprite.save(output_dir.join(base.to_string() + ".png"))
-		})()
-		.nice_unwrap(format!(
-			"Unable to copy sprite at {}",
-			sprite_path.display()
-		));
+		for (factor, suffix) in [(1, "-uhd"), (2, "-hd"), (2, "")] {
+			spritesheet::downscale(&mut sprite, factor);
+			let out = output_dir.join(format!("{base}{suffix}.png"));
+			sprite.save(&out).with_path(&out)?;
+		}
 	}
 
 	if !&mod_info.resources.files.is_empty() {
@@ -204,8 +218,7 @@ fn create_resources(
 	}
 	// Move other resources
 	for file in &mod_info.resources.files {
-		std::fs::copy(file, output_dir.join(file.file_name().unwrap()))
-			.nice_unwrap(format!("Unable to copy file at '{}'", file.display()));
+		copy_file(file, &output_dir.join(file.file_name().unwrap()))?;
 	}
 
 	if !&mod_info.resources.libraries.is_empty() {
@@ -213,12 +226,17 @@ fn create_resources(
 	}
 	// Move other resources
 	for file in &mod_info.resources.libraries {
-		std::fs::copy(file, working_dir.join(file.file_name().unwrap()))
-			.nice_unwrap(format!("Unable to copy file at '{}'", file.display()));
+		copy_file(file, &working_dir.join(file.file_name().unwrap()))?;
 	}
+	Ok(())
 }
 
-fn create_package_resources_only(root_path: &Path, output_dir: &PathBuf, shut_up: bool) {
+fn create_package_resources_only(
+	root_path: &Path,
+	output_dir: &PathBuf,
+	header: Option<PathBuf>,
+	shut_up: bool,
+) {
 	// Parse mod.json
 	let mod_info = parse_mod_info(root_path);
 
@@ -233,24 +251,33 @@ fn create_package_resources_only(root_path: &Path, output_dir: &PathBuf, shut_up
 		output_dir,
 		output_dir,
 		shut_up,
-	);
+	)
+	.nice_unwrap("Unable to create resources");
+
+	new_cache
+		.save(output_dir)
+		.nice_unwrap("Unable to save resource cache");
 
-	new_cache.save(output_dir);
+	let header = header.unwrap_or_else(|| output_dir.join("Resources.hpp"));
+	project_codegen::write_if_changed(&header, &project_codegen::resources_header(&mod_info))
+		.nice_unwrap("Unable to write resources header");
 
 	done!("Resources created at {}", output_dir.to_str().unwrap());
 }
 
-fn create_package(
+/// Create a .geode package from a mod's folder, returning the path of the
+/// package. If `output` isn't given, the package is placed at the root path
+/// and named after the mod ID
+pub fn create_package(
 	root_path: &Path,
-	binaries: Vec<PathBuf>,
-	raw_output: Option<PathBuf>,
-	do_install: bool,
-) {
+	binaries: &[PathBuf],
+	output: Option<PathBuf>,
+) -> Result<PathBuf, Error> {
 	// Parse mod.json
-	let mod_file_info = parse_mod_info(root_path);
+	let mod_file_info = try_parse_mod_info(root_path)?;
 
 	// path to the final .geode file
-	let mut output = raw_output.unwrap_or(root_path.join(format!("{}.geode", mod_file_info.id)));
+	let mut output = output.unwrap_or(root_path.join(format!("{}.geode", mod_file_info.id)));
 
 	// If it's a directory, add file path to it
 	if output.is_dir() {
@@ -264,16 +291,16 @@ fn create_package(
 
 	// Test if possible to create file
 	if !output.exists() || output.is_dir() {
-		fs::write(&output, "").nice_unwrap("Could not create package");
-		fs::remove_file(&output).unwrap();
+		fs::write(&output, "").with_path(&output)?;
+		fs::remove_file(&output).with_path(&output)?;
 	}
 
 	// Setup working directory
-	let temp_working_dir = tempfile::tempdir().nice_unwrap("Could not create temporary directory");
+	let temp_working_dir = tempfile::tempdir().with_path(&std::env::temp_dir())?;
 	let working_dir = temp_working_dir.path();
 
 	// Move mod.json
-	fs::copy(root_path.join("mod.json"), working_dir.join("mod.json")).unwrap();
+	copy_file(&root_path.join("mod.json"), &working_dir.join("mod.json"))?;
 
 	// Setup cache from the previously built .geode archive
 	let mut cache_bundle = cache::get_cache_bundle(&output);
@@ -287,14 +314,13 @@ fn create_package(
 		working_dir,
 		&working_dir.join("resources").join(&mod_file_info.id),
 		false,
-	);
+	)?;
 
 	// Custom hardcoded resources
 	for file in ["logo.png", "about.md", "changelog.md", "support.md"] {
 		let path = root_path.join(file);
 		if path.exists() {
-			std::fs::copy(path, working_dir.join(file))
-				.nice_unwrap(format!("Could not copy {file}"));
+			copy_file(&path, &working_dir.join(file))?;
 		}
 	}
 
@@ -303,8 +329,7 @@ fn create_package(
 		for file in ["README.md", "readme.md", "Readme.md"] {
 			let path = root_path.join(file);
 			if path.exists() {
-				std::fs::copy(path, working_dir.join("about.md"))
-					.nice_unwrap(format!("Could not copy {file}"));
+				copy_file(&path, &working_dir.join("about.md"))?;
 				break;
 			}
 		}
@@ -315,16 +340,12 @@ fn create_package(
 		for header in &api.include {
 			let out = working_dir.join(header);
 			out.parent().map(fs::create_dir_all);
-			fs::copy(root_path.join(header), &out).nice_unwrap(format!(
-				"Unable to copy header {} to {}",
-				header.display(),
-				out.display()
-			));
+			copy_file(&root_path.join(header), &out)?;
 		}
 	}
 
 	let mut binaries_added = false;
-	for file in read_dir(root_path).nice_unwrap("Unable to read root directory") {
+	for file in read_dir(root_path).with_path(root_path)? {
 		let Ok(file) = file else {
 			continue;
 		};
@@ -341,14 +362,13 @@ fn create_package(
 				"ios.dylib" | "dylib" | "dll" | "lib" | "so" | "android32.so" | "android64.so"
 			) {
 			let binary = name.to_string_lossy().to_string() + "." + ext.to_string_lossy().as_ref();
-			std::fs::copy(path, working_dir.join(&binary))
-				.nice_unwrap(format!("Unable to copy binary '{}'", binary));
+			copy_file(&path, &working_dir.join(&binary))?;
 			binaries_added = true;
 		}
 	}
 
 	// Copy other binaries
-	for binary in &binaries {
+	for binary in binaries {
 		let mut binary_name = binary.file_name().unwrap().to_str().unwrap().to_string();
 		if let Some(ext) = [
 			".ios.dylib",
@@ -366,8 +386,7 @@ fn create_package(
 			binary_name = mod_file_info.id.to_string() + ext;
 		}
 
-		std::fs::copy(binary, working_dir.join(binary_name))
-			.nice_unwrap(format!("Unable to copy binary at '{}'", binary.display()));
+		copy_file(binary, &working_dir.join(binary_name))?;
 		binaries_added = true;
 	}
 
@@ -377,9 +396,22 @@ fn create_package(
 		info!("Help: Add a binary with `--binary <bin_path>`");
 	}
 
-	new_cache.save(working_dir);
+	new_cache.save(working_dir)?;
 
-	zip_folder(working_dir, &output);
+	zip_folder(working_dir, &output)?;
+
+	Ok(output)
+}
+
+fn package_new(
+	root_path: &Path,
+	binaries: Vec<PathBuf>,
+	output: Option<PathBuf>,
+	do_install: bool,
+) {
+	mod_schema::check_mod_json(root_path);
+	let output =
+		create_package(root_path, &binaries, output).nice_unwrap("Unable to create package");
 
 	if do_install {
 		let config = Config::new().assert_is_setup();
@@ -477,7 +509,7 @@ pub fn subcommand(cmd: Package) {
 			binary: binaries,
 			output,
 			install,
-		} => create_package(&root_path, binaries, output, install),
+		} => package_new(&root_path, binaries, output, install),
 
 		Package::Merge { packages } => {
 			if packages.len() < 2 {
@@ -491,12 +523,29 @@ pub fn subcommand(cmd: Package) {
 			input,
 			output,
 			externals,
-		} => project::check_dependencies(input, output, None, externals),
+		} => {
+			mod_schema::check_mod_json(&input);
+			project::check_dependencies(
+				input,
+				output,
+				&ResolveArgs {
+					platform: None,
+					externals,
+					with_recommended: false,
+					with_suggested: false,
+				},
+				LockMode::Use,
+			)
+		}
 
 		Package::Resources {
 			root_path,
 			output,
 			shut_up,
-		} => create_package_resources_only(&root_path, &output, shut_up),
+			header,
+		} => {
+			mod_schema::check_mod_json(&root_path);
+			create_package_resources_only(&root_path, &output, header, shut_up)
+		}
 	}
 }
@@ -4,22 +4,33 @@ use crate::util::mod_file::GDVersion;
 use crate::{done, fail, fatal, index, info, warn, NiceUnwrap};
 use crate::{
 	file::read_dir_recursive,
-	template,
+	project_codegen, project_deps, project_lint,
+	project_lock::{lock_path, LockFile, LockMode, LockedDependency, LOCK_FILE},
+	project_migrate, template,
 	util::{
 		config::Config,
-		mod_file::{parse_mod_info, try_parse_mod_info, Dependency, ModFileInfo},
+		dep_cache::DependencyCache,
+		json_edit::JsonEditor,
+		json_span::PathSegment,
+		mod_file::{
+			parse_mod_info, try_parse_mod_info, Dependency, DependencyImportance,
+			IncompatibilityImportance, ModFileInfo, MOD_ID_PATTERN,
+		},
+		mod_schema,
 	},
 };
-use clap::Subcommand;
 use clap::ValueEnum;
+use clap::{Args, Subcommand};
 use edit_distance::edit_distance;
-use semver::Version;
-use serde::Deserialize;
+use regex::Regex;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use serde_json::Value;
-use std::env;
+
 use std::{
-	collections::HashMap,
+	cell::RefCell,
+	collections::{HashMap, HashSet, VecDeque},
 	fs,
 	path::{Path, PathBuf},
 };
@@ -32,6 +43,42 @@ pub enum ResourceType {
 	File,
 }
 
+#[derive(Args, Debug)]
+pub struct CheckArgs {
+	/// Where to install the dependencies; usually the project's build
+	/// directory. A directory called geode-deps will be created inside
+	/// the specified installation directory. If not specified, "build"
+	/// is assumed
+	pub install_dir: Option<PathBuf>,
+
+	#[clap(flatten)]
+	pub resolve: ResolveArgs,
+}
+
+/// Options for which dependencies get resolved
+#[derive(Args, Debug)]
+pub struct ResolveArgs {
+	/// The platform checked used for platform-specific dependencies. If
+	/// not specified, uses current host platform if possible
+	#[clap(long, short)]
+	pub platform: Option<PlatformName>,
+
+	/// Any external dependencies as a list in the form of `mod.id:version`.
+	/// An external dependency is one that the CLI will not verify exists in
+	/// any way; it will just assume you have it installed through some
+	/// other means (usually through building it as part of the same project)
+	#[clap(long, num_args(0..))]
+	pub externals: Vec<String>,
+
+	/// Also fetch dependencies marked as recommended
+	#[clap(long)]
+	pub with_recommended: bool,
+
+	/// Also fetch dependencies marked as suggested
+	#[clap(long)]
+	pub with_suggested: bool,
+}
+
 #[derive(Subcommand, Debug)]
 #[clap(rename_all = "kebab-case")]
 pub enum Project {
@@ -46,23 +93,19 @@ pub enum Project {
 
 	/// Check & install the dependencies for this project
 	Check {
-		/// Where to install the dependencies; usually the project's build
-		/// directory. A directory called geode-deps will be created inside
-		/// the specified installation directory. If not specified, "build"
-		/// is assumed
-		install_dir: Option<PathBuf>,
+		#[clap(flatten)]
+		args: CheckArgs,
 
-		/// The platform checked used for platform-specific dependencies. If
-		/// not specified, uses current host platform if possible
-		#[clap(long, short)]
-		platform: Option<PlatformName>,
+		/// Fail instead of changing geode.lock, for example in CI
+		#[clap(long)]
+		frozen: bool,
+	},
 
-		/// Any external dependencies as a list in the form of `mod.id:version`.
-		/// An external dependency is one that the CLI will not verify exists in
-		/// any way; it will just assume you have it installed through some
-		/// other means (usually through building it as part of the same project)
-		#[clap(long, num_args(0..))]
-		externals: Vec<String>,
+	/// Resolve the dependencies of this project again, ignoring the
+	/// versions locked in geode.lock, and install them
+	Update {
+		#[clap(flatten)]
+		args: CheckArgs,
 	},
 
 	/// Add a resource to the mod.json file
@@ -71,6 +114,91 @@ pub enum Project {
 		resource: ResourceType,
 		files: Vec<PathBuf>,
 	},
+
+	/// Add a dependency to the mod.json file, or update an existing one
+	AddDep {
+		/// ID of the mod to depend on, optionally followed by a version
+		/// requirement like `mod.id@>=1.2.0`. Without one, the latest version
+		/// on the index is required
+		#[clap(value_name = "MOD_ID[@REQ]")]
+		dependency: String,
+
+		/// How important the dependency is
+		#[clap(long, conflicts_with = "optional")]
+		importance: Option<DependencyImportance>,
+
+		/// Only depend on the mod on these platforms
+		#[clap(long, value_delimiter = ',', num_args(1..))]
+		platforms: Vec<PlatformName>,
+
+		/// Mark the dependency as optional, same as `--importance suggested`
+		#[clap(long)]
+		optional: bool,
+	},
+
+	/// Remove a dependency from the mod.json file
+	RemoveDep {
+		/// ID of the mod to stop depending on
+		id: String,
+	},
+
+	/// Check mod.json and the project for common mistakes and index rules.
+	/// Rules can be turned off by listing their IDs under `lint.ignore` in
+	/// mod.json
+	Lint {
+		/// List every rule with its ID and severity instead
+		#[clap(long)]
+		rules: bool,
+	},
+
+	/// Rewrite mod.json from legacy formats to the current one
+	Migrate {
+		/// Only check whether mod.json needs to be migrated, failing if it
+		/// does
+		#[clap(long)]
+		check: bool,
+	},
+
+	/// Generate C++ code from mod.json
+	Codegen {
+		#[clap(subcommand)]
+		what: project_codegen::Codegen,
+	},
+
+	/// Inspect the dependencies of this project
+	Deps {
+		#[clap(subcommand)]
+		what: project_deps::Deps,
+	},
+
+	/// Show which dependencies have newer versions on the index
+	Outdated {
+		/// Platform whose locked versions to show. If not specified, uses
+		/// current host platform if possible
+		#[clap(long, short)]
+		platform: Option<PlatformName>,
+	},
+
+	/// Raise the version requirements of dependencies in mod.json to the
+	/// latest versions on the index, and check the dependencies again
+	Upgrade {
+		/// Dependency to upgrade. If not specified, all are upgraded
+		id: Option<String>,
+
+		/// Where to install the dependencies, see `geode project check`
+		#[clap(long)]
+		install_dir: Option<PathBuf>,
+
+		#[clap(flatten)]
+		args: ResolveArgs,
+	},
+
+	/// Write the JSON Schema for mod.json, for use with editors. Prints it
+	/// if no output file is specified
+	Schema {
+		/// Where to write the schema, for example mod.schema.json
+		output: Option<PathBuf>,
+	},
 }
 
 fn find_build_directory(root: &Path) -> Option<PathBuf> {
@@ -158,74 +286,61 @@ impl Found {
 	}
 }
 
-fn find_existing_dependency(dep: &Dependency, _config: &Config) -> Result<Found, String> {
-	let mut path = env::temp_dir();
-	path.push(format!("{}.geode", dep.id));
-
-	if path.exists() {
-		let mod_info =
-			try_parse_mod_info(&path).map_err(|x| format!("Couldn't parse mod.json: {}", x))?;
+fn find_existing_dependency(
+	dep: &Dependency,
+	platform: PlatformName,
+	cache: &DependencyCache,
+) -> Result<Found, String> {
+	let cached = cache.versions(&dep.id, platform);
+	let Some((newest, _)) = cached.first() else {
+		return Ok(Found::None);
+	};
 
-		if !dep.version.matches(&mod_info.version) {
-			info!(
-				"Dependency '{}' found in cache, but version '{}' does not match required '{}'",
-				dep.id, mod_info.version, dep.version
-			);
-			return Ok(Found::Wrong(mod_info.version));
-		}
+	let Some((_, path)) = cached.iter().find(|(v, _)| dep.version.matches(v)) else {
+		info!(
+			"Dependency '{}' found in cache, but version '{}' does not match required '{}'",
+			dep.id, newest, dep.version
+		);
+		return Ok(Found::Wrong(newest.clone()));
+	};
 
-		Ok(Found::Some(path, mod_info))
-	} else {
-		Ok(Found::None)
-	}
+	let mod_info =
+		try_parse_mod_info(path).map_err(|x| format!("Couldn't parse mod.json: {}", x))?;
+	Ok(Found::Some(path.clone(), mod_info))
 }
 
-fn find_index_dependency(dep: &Dependency, config: &Config) -> Result<Found, String> {
+fn find_index_dependency(
+	dep: &Dependency,
+	platform: PlatformName,
+	config: &Config,
+	cache: &DependencyCache,
+) -> Result<Found, String> {
 	info!("Fetching dependency from index");
-	let found = index::get_mod_versions(
-		&dep.id,
-		1,
-		10,
-		config,
-		false,
-		Some(dep.version.to_geode_string()),
-	)?;
-
-	if found.data.is_empty() {
+	// The index can only compare against a single version, so requirements
+	// combined from several mods are matched here instead
+	let compare = (dep.version.comparators.len() == 1).then(|| dep.version.to_geode_string());
+	let per_page = if compare.is_some() { 10 } else { 100 };
+	let found = index::get_mod_versions(&dep.id, 1, per_page, config, false, compare.clone())
+		.map_err(|e| e.to_string())?;
+
+	let Some(first) = found.data.iter().find(|v| {
+		compare.is_some()
+			|| Version::parse(v.version.trim_start_matches('v'))
+				.is_ok_and(|v| dep.version.matches(&v))
+	}) else {
 		return Ok(Found::None);
-	}
-
-	let first = found.data.first().unwrap();
+	};
 	info!("Dependency found: {}, version {}", dep.id, first.version);
 	info!("Downloading dependency");
 
-	let client = reqwest::blocking::Client::new();
-
-	let result = client
-		.get(&first.download_link)
-		.send()
+	let bytes = index::download_mod(&dep.id, &first.version, &first.hash, config)
 		.map_err(|x| format!("Failed to download dependency: {}", x))?;
 
-	if !result.status().is_success() {
-		return Err(format!(
-			"Failed to download dependency. Bad status code: {}",
-			result.status()
-		));
-	}
-
-	let bytes = result
-		.bytes()
-		.map_err(|x| format!("Failed to parse dependency binary: {}", x))?;
-
 	info!("Success");
-	info!("Writing dependency to temp file");
 
-	let mut path = env::temp_dir();
-	path.push(format!("{}.geode", dep.id));
-
-	if let Err(e) = std::fs::write(&path, bytes) {
-		return Err(format!("Failed to write dependency to temp file: {}", e));
-	}
+	let path = cache
+		.store(&dep.id, &first.version, platform, &bytes, &first.hash)
+		.map_err(|e| format!("Failed to cache dependency: {e}"))?;
 
 	let mod_info =
 		try_parse_mod_info(&path).map_err(|x| format!("Couldn't parse mod.json: {}", x))?;
@@ -282,175 +397,345 @@ fn find_dependency(
 	Ok(found)
 }
 
-pub fn check_dependencies(
-	input: PathBuf,
-	output: PathBuf,
-	platform: Option<PlatformName>,
-	externals: Vec<String>,
-) {
-	let config = Config::new();
-
-	let mod_info = parse_mod_info(&input);
+pub fn importance_name(importance: DependencyImportance) -> &'static str {
+	match importance {
+		DependencyImportance::Required => "Required",
+		DependencyImportance::Recommended => "Recommended",
+		DependencyImportance::Suggested => "Suggested",
+	}
+}
 
-	let platform = platform.unwrap_or_else(|| {
-		PlatformName::current().nice_unwrap("Unknown platform, please specify one with --platform")
-	});
+/// Whether a dependency or incompatibility restricted to some platforms
+/// applies when building for `platform`
+pub(crate) fn applies_to_platform(
+	platforms: &HashSet<PlatformName>,
+	platform: PlatformName,
+) -> bool {
+	let mut platforms = platforms.clone();
+
+	// Fix platform aliases
+	if platforms.contains(&PlatformName::Android) {
+		platforms.insert(PlatformName::Android64);
+		platforms.insert(PlatformName::Android32);
+	}
 
-	// Check if platform is supported
-	match mod_info.gd {
-		GDVersion::Simple(_) => {}
-		GDVersion::Detailed(gd) => match platform {
-			PlatformName::Windows => {
-				if gd.win.is_none() {
-					fatal!("Geometry Dash version not specified for Windows, please specify one in mod.json");
-				}
-			}
-			PlatformName::MacOS | PlatformName::MacArm | PlatformName::MacIntel => {
-				if gd.mac.is_none() {
-					fatal!("Geometry Dash version not specified for macOS, please specify one in mod.json");
-				}
-			}
-			PlatformName::Android | PlatformName::Android32 | PlatformName::Android64 => {
-				if gd.android.is_none() {
-					fatal!("Geometry Dash version not specified for Android, please specify one in mod.json");
-				}
-			}
-			PlatformName::Ios => {
-				if gd.ios.is_none() {
-					fatal!("Geometry Dash version not specified for iOS, please specify one in mod.json");
-				}
-			}
-		},
+	if platforms.contains(&PlatformName::MacOS) {
+		platforms.insert(PlatformName::MacArm);
+		platforms.insert(PlatformName::MacIntel);
+	}
+	if platform == PlatformName::MacOS
+		&& (platforms.contains(&PlatformName::MacArm)
+			|| platforms.contains(&PlatformName::MacIntel))
+	{
+		platforms.insert(PlatformName::MacOS);
 	}
 
-	// If no dependencies, skippy wippy
-	if mod_info.dependencies.is_empty() {
-		return;
+	platforms.contains(&platform)
+}
+
+/// Look for installed mods that the mod is incompatible with, returning
+/// whether any of them break it
+fn check_incompatibilities(
+	mod_info: &ModFileInfo,
+	config: &Config,
+	platform: PlatformName,
+) -> bool {
+	if mod_info.incompatibilities.is_empty() {
+		return false;
 	}
+	let Some(profile) = config.get_profile(&config.current_profile) else {
+		return false;
+	};
 
-	// Parse externals
-	let externals = externals
-		.into_iter()
-		.map(|ext|
-			// If the external is provided as name:version get those, otherwise
-			// assume it's just the name
-			if ext.contains(':') {
-				let mut split = ext.split(':');
-				let name = split.next().unwrap().to_string();
-				let ver = split.next().unwrap();
-				(name, Some(Version::parse(ver.strip_prefix('v').unwrap_or(ver))
-					.nice_unwrap("Invalid version in external {name}")
-				))
-			}
-			else {
-				(ext, None)
-			}
-		)
-		.collect::<HashMap<_, _>>();
+	let mods_dir = profile.borrow().mods_dir();
+	let Ok(installed) = read_dir_recursive(&mods_dir) else {
+		return false;
+	};
 
 	let mut errors = false;
+	for path in installed {
+		let Ok(installed) = try_parse_mod_info(&path) else {
+			continue;
+		};
+		let Some(incompat) = mod_info.incompatibilities.get(&installed.id) else {
+			continue;
+		};
+		if !applies_to_platform(&incompat.platforms, platform)
+			|| !incompat.version.matches(&installed.version)
+		{
+			continue;
+		}
 
-	let dep_dir = output.join("geode-deps");
-	fs::create_dir_all(&dep_dir).nice_unwrap("Unable to create dependency directory");
+		match incompat.importance {
+			IncompatibilityImportance::Breaking => {
+				fail!(
+					"Mod '{}' version '{}' is installed, but this mod is \
+					incompatible with it ('{}'). Geode won't load both, remove \
+					{} from your profile to test this mod",
+					installed.id,
+					installed.version,
+					incompat.version,
+					path.display()
+				);
+				errors = true;
+			}
+			IncompatibilityImportance::Conflicting => warn!(
+				"Mod '{}' version '{}' is installed, which conflicts with this mod",
+				installed.id, installed.version
+			),
+			IncompatibilityImportance::Superseded => info!(
+				"Mod '{}' version '{}' is installed, which is superseded by this mod",
+				installed.id, installed.version
+			),
+		}
+	}
+	errors
+}
 
-	// check all dependencies
-	for dep in &mod_info.dependencies {
-		let mut platforms = dep.platforms.clone();
+/// Get a locked dependency from the cache, installed mods or the index, as
+/// long as it is exactly the locked package
+fn fetch_locked_dependency(
+	dep: &Dependency,
+	locked: &LockedDependency,
+	platform: PlatformName,
+	config: &Config,
+	cache: &DependencyCache,
+) -> Result<ResolvedDependency, String> {
+	let is_locked = |path: &Path| sha256::try_digest(path).is_ok_and(|h| h == locked.hash);
+	let parse = |path: PathBuf, source| {
+		try_parse_mod_info(&path)
+			.map(|info| ResolvedDependency { path, info, source })
+			.map_err(|e| format!("Couldn't parse mod.json: {e}"))
+	};
 
-		// Fix platform aliases
-		if platforms.contains(&PlatformName::Android) {
-			platforms.insert(PlatformName::Android64);
-			platforms.insert(PlatformName::Android32);
-		}
+	if let Some(cached) = cache.get(&dep.id, &locked.version, platform, &locked.hash) {
+		return parse(cached, DependencySource::Cache);
+	}
 
-		if platforms.contains(&PlatformName::MacOS) {
-			platforms.insert(PlatformName::MacArm);
-			platforms.insert(PlatformName::MacIntel);
-		}
-		if platform == PlatformName::MacOS
-			&& (platforms.contains(&PlatformName::MacArm)
-				|| platforms.contains(&PlatformName::MacIntel))
+	if let Some(profile) = config.get_profile(&config.current_profile) {
+		if let Found::Some(path, _) =
+			find_dependency(dep, &profile.borrow().mods_dir(), true, false).unwrap_or(Found::None)
 		{
-			platforms.insert(PlatformName::MacOS);
+			if is_locked(&path) {
+				return parse(path, DependencySource::Installed);
+			}
 		}
+	}
 
-		// Skip dependencies not on this platform
-		if !platforms.contains(&platform) {
-			continue;
+	if locked.installed {
+		return Err(format!(
+			"it was locked from installed mods as it isn't on the index, and \
+			isn't installed here. Install it, or if it's a closed-source mod, \
+			mark it as external in your CMake with setup_geode_mod(... EXTERNALS {}:{})",
+			dep.id, locked.version
+		));
+	}
+	if !index::is_available(config) {
+		return Err(
+			"it isn't in the cache nor installed, and can't be downloaded in offline mode"
+				.to_string(),
+		);
+	}
+	info!(
+		"Downloading locked version '{}' of dependency '{}'",
+		locked.version, dep.id
+	);
+	let bytes = index::download_mod(&dep.id, &locked.version, &locked.hash, config)
+		.map_err(|e| e.to_string())?;
+	let cached = cache
+		.store(&dep.id, &locked.version, platform, &bytes, &locked.hash)
+		.map_err(|e| format!("Failed to cache dependency: {e}"))?;
+	parse(cached, DependencySource::Index)
+}
+
+/// Write the updated lockfile, or fail if it's frozen and would change
+fn save_lock(root: &Path, mut lock: LockFile, old_lock: &LockFile, mode: LockMode) {
+	// A lockfile is only kept around if there's anything to lock
+	let exists = lock_path(root).exists();
+	if lock.same_as(old_lock) && exists != lock.dependencies.is_empty() {
+		return;
+	}
+	if mode == LockMode::Frozen {
+		fatal!(
+			"{} is out of date, run `geode project update` to update it",
+			LOCK_FILE
+		);
+	}
+	if lock.dependencies.is_empty() {
+		if lock_path(root).exists() {
+			fs::remove_file(lock_path(root)).nice_unwrap("Unable to remove lockfile");
 		}
+		return;
+	}
+	if lock.save(root).nice_unwrap("Unable to write lockfile") {
+		info!("Updated {}", LOCK_FILE);
+	}
+}
 
-		// is this an external dependency?
-		if let Some(ext) = externals.get(&dep.id) {
-			// did we get a version?
-			if let Some(version) = ext {
-				// is it valid?
-				if dep.version.matches(version) {
-					info!("Dependency '{}' found as external", dep.id);
-				}
-				// external dependency version must match regardless of whether
-				// it's optional or not as most external dependencies are other
-				// projects being built at the same time and if those have a
-				// version mismatch you've screwed something up and should fix
-				// that
-				else {
-					fail!(
-						"External dependency '{}' version '{version}' does not \
-						match required version '{}' (note: optionality is \
-						ignored when verifying external dependencies)",
-						dep.id,
-						dep.version
+/// What's needed to find a single dependency
+struct Resolver<'a> {
+	config: &'a Config,
+	cache: &'a DependencyCache,
+	platform: PlatformName,
+	lock: &'a LockFile,
+	lock_mode: LockMode,
+	/// Whether dependencies downloaded from the index are installed into the
+	/// profile, rather than only read from the cache
+	install: bool,
+	/// Mods installed from the index while resolving, whose versions may get
+	/// replaced later on
+	installed_now: RefCell<HashSet<String>>,
+}
+
+impl Resolver<'_> {
+	/// Install a package downloaded from the index into the profile, returning
+	/// where it's installed, or where it's cached if not installing
+	fn install_from_index(&self, id: &str, package: PathBuf) -> PathBuf {
+		if !self.install {
+			return package;
+		}
+		let geode_path = self
+			.config
+			.get_current_profile()
+			.mods_dir()
+			.join(format!("{id}.geode"));
+		std::fs::copy(package, &geode_path).nice_unwrap("Failed to install .geode");
+		self.installed_now.borrow_mut().insert(id.to_string());
+		geode_path
+	}
+
+	/// Find a version of a dependency from the lockfile, the cache, the
+	/// index or installed mods. Returns None if there isn't one, in which
+	/// case the reason has already been printed and `errors` is set if that
+	/// should fail the check
+	fn resolve(&self, dep: &Dependency, errors: &mut bool) -> Option<ResolvedDependency> {
+		// use the locked version if there is one that still fits
+		let locked = match self.lock_mode {
+			LockMode::Update => None,
+			_ => self
+				.lock
+				.get(&dep.id, self.platform)
+				.filter(|l| Version::parse(&l.version).is_ok_and(|v| dep.version.matches(&v)))
+				.cloned(),
+		};
+		let is_locked = locked.is_some();
+		let from_lock = locked.and_then(|locked| {
+			match fetch_locked_dependency(dep, &locked, self.platform, self.config, self.cache) {
+				Ok(found) => Some(found),
+				Err(e) => {
+					warn!(
+						"Unable to get locked version '{}' of dependency '{}': {}",
+						locked.version, dep.id, e
 					);
-					errors = true;
+					None
 				}
 			}
-			// otherwise warn that a version prolly should be provided, but let
-			// it slide this time
-			else {
-				warn!(
-					"Dependency '{}' marked as external with no version specified",
-					dep.id
-				);
-			}
-			continue;
+		});
+		if self.lock_mode == LockMode::Frozen && is_locked && from_lock.is_none() {
+			fail!("Locked version of dependency '{}' is not available", dep.id);
+			*errors = true;
+			return None;
+		}
+		if self.lock_mode == LockMode::Frozen && from_lock.is_none() {
+			fail!(
+				"Dependency '{}' is not locked for {} at a version matching '{}' \
+				(run `geode project update` to update {})",
+				dep.id,
+				self.platform,
+				dep.version,
+				LOCK_FILE
+			);
+			*errors = true;
+			return None;
 		}
 
 		// otherwise try to find it on installed mods and then on index
 
 		// dont check others if already downloaded
 		let path_to_dep_geode;
-		let _geode_info;
-
-		let found_existing = match find_existing_dependency(dep, &config) {
-			Ok(f) => f,
-			Err(e) => {
+		let geode_info;
+		let source;
+
+		// Updating is meant to pick up newer versions, so the index is asked
+		// before the cache then
+		let offline = !index::is_available(self.config);
+		let index_first = self.lock_mode == LockMode::Update && !offline;
+		let from_cache = || {
+			find_existing_dependency(dep, self.platform, self.cache).unwrap_or_else(|e| {
 				warn!("Failed to fetch dependency {} from cache: {}", &dep.id, e);
 				Found::None
-			}
+			})
 		};
 
-		if let Found::Some(inst_path, inst_info) = found_existing {
+		if let Some(locked) = from_lock {
+			info!(
+				"Dependency '{}' found at locked version '{}'",
+				dep.id, locked.info.version
+			);
+			return Some(locked);
+		} else if let Found::Some(inst_path, inst_info) = if index_first {
+			Found::None
+		} else {
+			from_cache()
+		} {
 			info!("Dependency '{}' found in cache", dep.id);
 			path_to_dep_geode = inst_path;
-			_geode_info = inst_info;
+			geode_info = inst_info;
+			source = DependencySource::Cache;
 		} else {
-			// check index
-			let found_in_index = match find_index_dependency(dep, &config) {
-				Ok(f) => f,
-				Err(e) => {
-					warn!("Failed to fetch dependency {} from index: {}", &dep.id, e);
-					Found::None
+			// check index, unless offline
+			let found_in_index = if offline {
+				Found::None
+			} else {
+				match find_index_dependency(dep, self.platform, self.config, self.cache) {
+					Ok(f) => f,
+					Err(e) => {
+						warn!("Failed to fetch dependency {} from index: {}", &dep.id, e);
+						// The cache was skipped in favour of the index, so
+						// fall back to it now that the index can't be reached
+						if let (true, Found::Some(path, info)) = (index_first, from_cache()) {
+							info!("Dependency '{}' found in cache", dep.id);
+							return Some(ResolvedDependency {
+								path,
+								info,
+								source: DependencySource::Cache,
+							});
+						}
+						Found::None
+					}
 				}
 			};
 			// check installed mods
-			let found_in_installed =
-				find_dependency(dep, &config.get_current_profile().mods_dir(), true, false)
-					.nice_unwrap("Unable to read installed mods");
+			let found_in_installed = find_dependency(
+				dep,
+				&self.config.get_current_profile().mods_dir(),
+				true,
+				false,
+			)
+			.nice_unwrap("Unable to read installed mods");
 
 			// if not found in either        hjfod  code
 			if !matches!(found_in_index, Found::Some(_, _))
 				&& !matches!(found_in_installed, Found::Some(_, _))
 			{
-				if dep.required {
+				if offline && dep.importance() == DependencyImportance::Required {
+					fail!(
+						"Dependency '{}' version '{}' not found in the cache nor \
+						installed mods, and the index can't be checked in offline \
+						mode. Install it locally, or run the check once without \
+						--offline to cache it",
+						dep.id,
+						dep.version
+					);
+					*errors = true;
+				} else if offline {
+					warn!(
+						"{} dependency '{}' not found in the cache nor installed \
+						mods, and the index can't be checked in offline mode",
+						importance_name(dep.importance()),
+						dep.id
+					)
+				} else if dep.importance() == DependencyImportance::Required {
 					fail!(
 						"Dependency '{0}' not found in installed mods nor index! \
 						If this is a mod that hasn't been published yet, install it \
@@ -460,10 +745,11 @@ pub fn check_dependencies(
 						dep.id,
 						dep.version
 					);
-					errors = true;
+					*errors = true;
 				} else {
-					info!(
-						"Dependency '{}' not found in installed mods nor index",
+					warn!(
+						"{} dependency '{}' not found in installed mods nor index",
+						importance_name(dep.importance()),
 						dep.id
 					)
 				}
@@ -499,14 +785,22 @@ pub fn check_dependencies(
 					_ => {}
 				}
 				// skip rest
-				continue;
+				return None;
 			}
 
 			match (found_in_installed, found_in_index) {
 				(Found::Some(_, _), Found::Some(inst_path, inst_info)) => {
 					info!("Dependency '{}' found", dep.id);
 					path_to_dep_geode = inst_path;
-					_geode_info = inst_info;
+					geode_info = inst_info;
+					source = DependencySource::Index;
+				}
+
+				(Found::Some(inst_path, inst_info), _) if offline => {
+					info!("Dependency '{}' found in installed mods", dep.id);
+					path_to_dep_geode = inst_path;
+					geode_info = inst_info;
+					source = DependencySource::Installed;
 				}
 
 				(Found::Some(inst_path, inst_info), _) => {
@@ -524,11 +818,13 @@ pub fn check_dependencies(
 						dep.id, dep.version
 					);
 					path_to_dep_geode = inst_path;
-					_geode_info = inst_info;
+					geode_info = inst_info;
+					source = DependencySource::Installed;
 				}
 
 				(Found::Wrong(version), Found::Some(path, indx_info)) => {
-					if version > indx_info.version {
+					if version > indx_info.version && !self.installed_now.borrow().contains(&dep.id)
+					{
 						warn!(
 							"Dependency '{0}' found in installed mods, but as \
 							version '{1}' whereas required is '{2}'. Index has valid \
@@ -538,20 +834,16 @@ pub fn check_dependencies(
 							dependency requirements",
 							dep.id, version, dep.version, indx_info.version
 						);
-						continue;
+						return None;
 					}
 					info!(
 						"Dependency '{}' found on the index, installing \
 						(update '{}' => '{}')",
 						dep.id, version, indx_info.version
 					);
-					let geode_path = config
-						.get_current_profile()
-						.mods_dir()
-						.join(format!("{}.geode", indx_info.id));
-					std::fs::copy(path, &geode_path).nice_unwrap("Failed to install .geode");
-					path_to_dep_geode = geode_path;
-					_geode_info = indx_info;
+					path_to_dep_geode = self.install_from_index(&indx_info.id, path);
+					geode_info = indx_info;
+					source = DependencySource::Index;
 				}
 
 				(_, Found::Some(path, indx_info)) => {
@@ -559,13 +851,9 @@ pub fn check_dependencies(
 						"Dependency '{}' found on the index, installing (version '{}')",
 						dep.id, indx_info.version
 					);
-					let geode_path = config
-						.get_current_profile()
-						.mods_dir()
-						.join(format!("{}.geode", indx_info.id));
-					std::fs::copy(path, &geode_path).nice_unwrap("Failed to install .geode");
-					path_to_dep_geode = geode_path;
-					_geode_info = indx_info;
+					path_to_dep_geode = self.install_from_index(&indx_info.id, path);
+					geode_info = indx_info;
+					source = DependencySource::Index;
 				}
 
 				_ => unreachable!(),
@@ -582,82 +870,715 @@ pub fn check_dependencies(
 			// check if dependency already installed
 			// if let Found::Some(_, info) = found_in_deps {
 			// 	if info.version == geode_info.version {
-			// 		continue;
+			// 		return None;
 			// 	}
 			// }
 		}
 
+		Some(ResolvedDependency {
+			path: path_to_dep_geode,
+			info: geode_info,
+			source,
+		})
+	}
+}
+
+fn check_project_dependencies(args: CheckArgs, lock_mode: LockMode) {
+	check_dependencies(
+		std::env::current_dir().unwrap(),
+		args.install_dir.unwrap_or("build".into()),
+		&args.resolve,
+		lock_mode,
+	)
+}
+
+/// Where a resolved dependency came from
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum DependencySource {
+	Cache,
+	Index,
+	Installed,
+	External,
+}
+
+/// What was done with a dependency that a mod in the graph declares
+#[derive(Clone, PartialEq, Debug)]
+pub enum RequirementStatus {
+	/// Not needed on the platform being checked
+	OtherPlatform,
+	/// Provided by the build, with its version if one was given
+	External(Option<Version>),
+	/// Optional and not asked for
+	NotFetched,
+	/// Looked for, and in [`DependencyGraph::resolved`] if it was found
+	Fetched,
+}
+
+/// A dependency as declared by one of the mods in the dependency graph
+pub struct Requirement {
+	/// The mods that led to this requirement, starting with the project
+	pub chain: Vec<String>,
+	pub dependency: Dependency,
+	pub status: RequirementStatus,
+}
+
+impl Requirement {
+	/// The mod that declared this requirement
+	pub fn parent(&self) -> &str {
+		self.chain.last().unwrap()
+	}
+}
+
+pub struct ResolvedDependency {
+	pub path: PathBuf,
+	pub info: ModFileInfo,
+	pub source: DependencySource,
+}
+
+/// The dependencies of a project and the dependencies of those
+pub struct DependencyGraph {
+	pub root: String,
+	pub platform: PlatformName,
+	pub requirements: Vec<Requirement>,
+	pub resolved: HashMap<String, ResolvedDependency>,
+	/// Whether anything failed to resolve, which has already been reported
+	pub errors: bool,
+	/// Resolved packages may be in the cache, so it stays locked for as long
+	/// as they might be read
+	_cache: DependencyCache,
+}
+
+impl DependencyGraph {
+	/// Requirements that a mod in the graph declares
+	pub fn requirements_of<'a>(&'a self, id: &'a str) -> impl Iterator<Item = &'a Requirement> {
+		self.requirements.iter().filter(move |r| r.parent() == id)
+	}
+
+	/// How important a resolved dependency is, which is the highest
+	/// importance anything requires it with
+	pub fn importance(&self, id: &str) -> DependencyImportance {
+		self.requirements
+			.iter()
+			.filter(|r| r.dependency.id == id && r.status == RequirementStatus::Fetched)
+			.map(|r| r.dependency.importance())
+			.min()
+			.unwrap_or_default()
+	}
+}
+
+/// Get the platform to check for, making sure the mod supports it
+pub fn target_platform(mod_info: &ModFileInfo, platform: Option<PlatformName>) -> PlatformName {
+	let platform = platform.unwrap_or_else(|| {
+		PlatformName::current().nice_unwrap("Unknown platform, please specify one with --platform")
+	});
+
+	// Check if platform is supported
+	match &mod_info.gd {
+		GDVersion::Simple(_) => {}
+		GDVersion::Detailed(gd) => match platform {
+			PlatformName::Windows => {
+				if gd.win.is_none() {
+					fatal!("Geometry Dash version not specified for Windows, please specify one in mod.json");
+				}
+			}
+			PlatformName::MacOS | PlatformName::MacArm | PlatformName::MacIntel => {
+				if gd.mac.is_none() {
+					fatal!("Geometry Dash version not specified for macOS, please specify one in mod.json");
+				}
+			}
+			PlatformName::Android | PlatformName::Android32 | PlatformName::Android64 => {
+				if gd.android.is_none() {
+					fatal!("Geometry Dash version not specified for Android, please specify one in mod.json");
+				}
+			}
+			PlatformName::Ios => {
+				if gd.ios.is_none() {
+					fatal!("Geometry Dash version not specified for iOS, please specify one in mod.json");
+				}
+			}
+		},
+	}
+	platform
+}
+
+/// Forget resolved dependencies that nothing requires anymore, along with
+/// what they required, after the version of a dependency was replaced
+fn prune_unreachable(
+	root: &str,
+	requirements: &mut Vec<Requirement>,
+	resolved: &mut HashMap<String, ResolvedDependency>,
+	queue: &mut VecDeque<(Dependency, Vec<String>)>,
+) {
+	let mut reachable = HashSet::from([root.to_string()]);
+	loop {
+		let found: Vec<_> = requirements
+			.iter()
+			.filter(|r| {
+				r.status == RequirementStatus::Fetched
+					&& reachable.contains(r.parent())
+					&& resolved.contains_key(&r.dependency.id)
+					&& !reachable.contains(&r.dependency.id)
+			})
+			.map(|r| r.dependency.id.clone())
+			.collect();
+		if found.is_empty() {
+			break;
+		}
+		reachable.extend(found);
+	}
+
+	resolved.retain(|id, _| reachable.contains(id));
+	requirements.retain(|r| reachable.contains(r.parent()));
+	queue.retain(|(_, chain)| reachable.contains(chain.last().unwrap()));
+}
+
+/// Find every dependency of a mod and the dependencies of those, picking
+/// versions that satisfy all of their requirements. Dependencies that are
+/// downloaded from the index are installed into the profile if `install` is
+/// set, otherwise they're only kept in the cache
+pub fn resolve_dependencies(
+	mod_info: &ModFileInfo,
+	args: &ResolveArgs,
+	platform: PlatformName,
+	config: &Config,
+	lock: &LockFile,
+	lock_mode: LockMode,
+	install: bool,
+) -> DependencyGraph {
+	// Parse externals
+	let externals = args
+		.externals
+		.iter()
+		.map(|ext|
+			// If the external is provided as name:version get those, otherwise
+			// assume it's just the name
+			if ext.contains(':') {
+				let mut split = ext.split(':');
+				let name = split.next().unwrap().to_string();
+				let ver = split.next().unwrap();
+				(name, Some(Version::parse(ver.strip_prefix('v').unwrap_or(ver))
+					.nice_unwrap("Invalid version in external {name}")
+				))
+			}
+			else {
+				(ext.clone(), None)
+			}
+		)
+		.collect::<HashMap<_, _>>();
+
+	let cache = DependencyCache::open().nice_unwrap("Unable to open dependency cache");
+	let resolver = Resolver {
+		config,
+		cache: &cache,
+		platform,
+		lock,
+		lock_mode,
+		install,
+		installed_now: RefCell::new(HashSet::new()),
+	};
+
+	let mut errors = false;
+
+	// Dependencies left to check, along with the chain of mods that led to
+	// them, starting with this one
+	let mut queue: VecDeque<(Dependency, Vec<String>)> = mod_info
+		.dependencies
+		.into_iter()
+		.map(|dep| (dep.clone(), vec![mod_info.id.clone()]))
+		.collect();
+	let mut requirements: Vec<Requirement> = Vec::new();
+	let mut resolved: HashMap<String, ResolvedDependency> = HashMap::new();
+
+	// check all dependencies, and the dependencies of those
+	while let Some((dep, chain)) = queue.pop_front() {
+		let is_direct = chain.len() == 1;
+
+		// Skip mods that depend on this one
+		if dep.id == mod_info.id {
+			continue;
+		}
+
+		// Optional dependencies are only fetched when asked for, and the
+		// optional dependencies of dependencies never are
+		let importance = dep.importance();
+		let fetch = match importance {
+			DependencyImportance::Required => true,
+			DependencyImportance::Recommended => is_direct && args.with_recommended,
+			DependencyImportance::Suggested => is_direct && args.with_suggested,
+		};
+
+		let status = if !applies_to_platform(&dep.platforms, platform) {
+			RequirementStatus::OtherPlatform
+		}
+		// is this an external dependency?
+		else if let Some(ext) = externals.get(&dep.id) {
+			// did we get a version?
+			if let Some(version) = ext {
+				// is it valid?
+				if dep.version.matches(version) {
+					info!("Dependency '{}' found as external", dep.id);
+				}
+				// external dependency version must match regardless of whether
+				// it's optional or not as most external dependencies are other
+				// projects being built at the same time and if those have a
+				// version mismatch you've screwed something up and should fix
+				// that
+				else {
+					fail!(
+						"External dependency '{}' version '{version}' does not \
+						match required version '{}' (note: optionality is \
+						ignored when verifying external dependencies)",
+						dep.id,
+						dep.version
+					);
+					errors = true;
+				}
+			}
+			// otherwise warn that a version prolly should be provided, but let
+			// it slide this time
+			else {
+				warn!(
+					"Dependency '{}' marked as external with no version specified",
+					dep.id
+				);
+			}
+			RequirementStatus::External(ext.clone())
+		} else if !fetch {
+			RequirementStatus::NotFetched
+		} else {
+			RequirementStatus::Fetched
+		};
+		requirements.push(Requirement {
+			chain: chain.clone(),
+			dependency: dep.clone(),
+			status: status.clone(),
+		});
+		if status != RequirementStatus::Fetched {
+			continue;
+		}
+
+		let replaced = match resolved.get(&dep.id) {
+			Some(found) if dep.version.matches(&found.info.version) => continue,
+			Some(found) => Some(found.info.version.clone()),
+			None => None,
+		};
+
+		// Look for a version that satisfies everything requiring this mod
+		let reqs: Vec<_> = requirements
+			.iter()
+			.filter(|r| r.dependency.id == dep.id && r.status == RequirementStatus::Fetched)
+			.collect();
+		let mut wanted = dep.clone();
+		wanted.version = VersionReq {
+			comparators: reqs
+				.iter()
+				.flat_map(|r| r.dependency.version.comparators.clone())
+				.collect(),
+		};
+		if let Some(version) = &replaced {
+			info!(
+				"Version '{}' of dependency '{}' doesn't match '{}' required by {}, \
+				looking for one matching '{}'",
+				version,
+				dep.id,
+				dep.version,
+				chain.join(" -> "),
+				wanted.version
+			);
+		}
+		let Some(found) = resolver.resolve(&wanted, &mut errors) else {
+			if reqs.len() > 1 {
+				fail!(
+					"No version of dependency '{}' matches all of its requirements:\n{}",
+					dep.id,
+					reqs.iter()
+						.map(|r| format!(
+							"  {} requires '{}'",
+							r.chain.join(" -> "),
+							r.dependency.version
+						))
+						.collect::<Vec<_>>()
+						.join("\n")
+				);
+				errors = true;
+			}
+			continue;
+		};
+
+		resolved.insert(dep.id.clone(), found);
+		if replaced.is_some() {
+			// Whatever the previously picked version required doesn't apply
+			// anymore, nor does anything that only it led to
+			requirements.retain(|r| r.parent() != dep.id);
+			queue.retain(|(_, chain)| chain.last() != Some(&dep.id));
+			prune_unreachable(&mod_info.id, &mut requirements, &mut resolved, &mut queue);
+		}
+
+		let mut dep_chain = chain;
+		dep_chain.push(dep.id.clone());
+		for sub in &resolved[&dep.id].info.dependencies {
+			queue.push_back((sub.clone(), dep_chain.clone()));
+		}
+	}
+
+	if !index::is_available(config) {
+		let mut unverified: Vec<_> = resolved
+			.iter()
+			.filter(|(_, r)| r.source == DependencySource::Installed)
+			.map(|(id, r)| format!("{id} {}", r.info.version))
+			.collect();
+		if !unverified.is_empty() {
+			unverified.sort();
+			warn!(
+				"Offline mode: the index couldn't be checked, so it's unverified \
+				whether these dependencies taken from installed mods are published \
+				on it: {}",
+				unverified.join(", ")
+			);
+		}
+	}
+
+	DependencyGraph {
+		root: mod_info.id.clone(),
+		platform,
+		requirements,
+		resolved,
+		errors,
+		_cache: cache,
+	}
+}
+
+pub fn check_dependencies(
+	input: PathBuf,
+	output: PathBuf,
+	args: &ResolveArgs,
+	lock_mode: LockMode,
+) {
+	let config = Config::new();
+
+	let mod_info = parse_mod_info(&input);
+	let old_lock = LockFile::load(&input).nice_unwrap("Unable to load lockfile");
+	let platform = target_platform(&mod_info, args.platform);
+
+	// Entries for this platform are locked again from what resolves now
+	let mut lock = old_lock.clone();
+	lock.remove_platform(platform);
+
+	let incompatible = check_incompatibilities(&mod_info, &config, platform);
+
+	// If no dependencies, skippy wippy
+	if mod_info.dependencies.is_empty() {
+		if incompatible {
+			fatal!("Incompatible mods are installed");
+		}
+		save_lock(&input, lock, &old_lock, lock_mode);
+		return;
+	}
+
+	let dep_dir = output.join("geode-deps");
+	fs::create_dir_all(&dep_dir).nice_unwrap("Unable to create dependency directory");
+
+	let graph = resolve_dependencies(
+		&mod_info, args, platform, &config, &old_lock, lock_mode, true,
+	);
+
+	for (id, dep) in &graph.resolved {
 		// unzip the whole .geode package because there's only like a few
 		// extra files there aside from the lib, headers, and resources
-		zip::ZipArchive::new(fs::File::open(path_to_dep_geode).unwrap())
+		zip::ZipArchive::new(fs::File::open(&dep.path).unwrap())
 			.nice_unwrap("Unable to unzip")
-			.extract(dep_dir.join(&dep.id))
+			.extract(dep_dir.join(id))
 			.nice_unwrap("Unable to extract geode package");
 
-		// add a note saying if the dependencey is required or not (for cmake to
+		// add a note saying how important the dependency is (for cmake to
 		// know if to link or not)
+		let importance = graph.importance(id);
 		fs::write(
-			dep_dir.join(&dep.id).join("geode-dep-options.json"),
-			format!(r#"{{ "required": {} }}"#, dep.required),
+			dep_dir.join(id).join("geode-dep-options.json"),
+			json!({
+				"required": importance == DependencyImportance::Required,
+				"importance": importance,
+			})
+			.to_string(),
 		)
 		.nice_unwrap("Unable to save dep options");
+
+		match sha256::try_digest(&dep.path) {
+			Ok(hash) => lock.insert(LockedDependency {
+				id: id.clone(),
+				version: dep.info.version.to_string(),
+				platform: platform.to_string(),
+				// A locked index package that happens to be installed is still
+				// from the index
+				installed: dep.source == DependencySource::Installed
+					&& old_lock
+						.get(id, platform)
+						.is_none_or(|old| old.hash != hash || old.installed),
+				hash,
+			}),
+			Err(_) => warn!(
+				"Dependency '{}' at {} is not a .geode package, so it can't be locked",
+				id,
+				dep.path.display()
+			),
+		}
 	}
 
-	if errors {
+	if graph.errors || incompatible {
 		fatal!("Some dependencies were unresolved");
+	}
+	save_lock(&input, lock, &old_lock, lock_mode);
+	done!("All dependencies resolved");
+
+	for (importance, flag) in [
+		(DependencyImportance::Recommended, "--with-recommended"),
+		(DependencyImportance::Suggested, "--with-suggested"),
+	] {
+		let ids: Vec<_> = graph
+			.requirements
+			.iter()
+			.filter(|r| {
+				r.status == RequirementStatus::NotFetched
+					&& r.chain.len() == 1
+					&& r.dependency.importance() == importance
+					&& !graph.resolved.contains_key(&r.dependency.id)
+			})
+			.map(|r| r.dependency.id.as_str())
+			.collect();
+		if !ids.is_empty() {
+			info!(
+				"{} dependencies not fetched (use {flag} to fetch them): {}",
+				importance_name(importance),
+				ids.join(", ")
+			);
+		}
+	}
+}
+
+/// A dependency written the way `geode project migrate` leaves it: required
+/// dependencies without anything else in the shorthand form, suggested ones
+/// with `"required": false` and recommended ones with their importance
+fn dependency_entry(
+	version: &str,
+	importance: DependencyImportance,
+	platforms: &[PlatformName],
+) -> Value {
+	let mut entry = json!({ "version": version });
+	match importance {
+		DependencyImportance::Required => {}
+		DependencyImportance::Recommended => entry["importance"] = json!(importance),
+		DependencyImportance::Suggested => entry["required"] = json!(false),
+	}
+	if !platforms.is_empty() {
+		let mut platforms: Vec<_> = platforms.iter().map(|p| p.to_string()).collect();
+		platforms.sort();
+		platforms.dedup();
+		entry["platforms"] = json!(platforms);
+	} else if importance == DependencyImportance::Required {
+		return json!(version);
+	}
+	entry
+}
+
+fn add_dependency(
+	dir: &Path,
+	spec: &str,
+	importance: DependencyImportance,
+	platforms: Vec<PlatformName>,
+) {
+	let mod_info = parse_mod_info(dir);
+	let mut mod_json =
+		JsonEditor::open_mod_json(dir).nice_unwrap("Must be inside a project with a mod.json");
+
+	let (id, requirement) = match spec.split_once('@') {
+		Some((id, req)) => (id, Some(req)),
+		None => (spec, None),
+	};
+	if !Regex::new(MOD_ID_PATTERN).unwrap().is_match(id) {
+		fatal!(
+			"'{}' is not a valid mod ID, which are in the form developer.name",
+			id
+		);
+	}
+	if id == mod_info.id {
+		fatal!("A mod can't depend on itself");
+	}
+	if let Some(req) = requirement {
+		// Checked the same way mod.json is parsed
+		VersionReq::parse(&req.replace('v', "")).nice_unwrap("Invalid version requirement");
+	}
+	if mod_json
+		.get(&[PathSegment::Key("dependencies".into())])
+		.is_some_and(|d| d.is_array())
+	{
+		fatal!(
+			"mod.json lists dependencies in the old array format, run \
+			`geode project migrate` to update it first"
+		);
+	}
+
+	let config = Config::new();
+	// Offline there's only installed mods to look through
+	let versions = if index::is_available(&config) {
+		project_deps::index_versions(id, &config, None).unwrap_or_else(|e| {
+			warn!("Unable to look up '{}' on the index: {}", id, e);
+			Vec::new()
+		})
+	} else {
+		Vec::new()
+	};
+	let latest = if !versions.is_empty() {
+		project_deps::latest_version(&versions, None)
+	} else {
+		// Not on the index, so look through installed mods for it or for a
+		// mod with a similar ID
+		let dep = Dependency {
+			id: id.to_string(),
+			version: VersionReq::STAR,
+			importance: Some(importance),
+			required: importance == DependencyImportance::Required,
+			platforms: HashSet::new(),
+		};
+		let found = config
+			.get_profile(&config.current_profile)
+			.and_then(|p| find_dependency(&dep, &p.borrow().mods_dir(), true, false).ok())
+			.unwrap_or(Found::None);
+		match found {
+			Found::Some(_, info) => {
+				warn!(
+					"Mod '{}' is installed but not on the index - make sure it's \
+					published before you publish yours",
+					id
+				);
+				Some(info.version)
+			}
+			Found::Maybe(similar) => {
+				fatal!(
+					"Mod '{}' was not found on the index nor in installed mods - \
+					did you mean '{}'?",
+					id,
+					similar
+				);
+			}
+			_ if requirement.is_some() => {
+				warn!(
+					"Mod '{}' was not found on the index nor in installed mods",
+					id
+				);
+				None
+			}
+			_ => {
+				fatal!(
+					"Mod '{}' was not found on the index nor in installed mods. If \
+					it hasn't been published yet, specify the version with {}@<version>",
+					id,
+					id
+				);
+			}
+		}
+	};
+
+	let version = match (requirement, latest) {
+		(Some(req), _) => req.to_string(),
+		(None, Some(latest)) => format!(">={latest}"),
+		(None, None) => unreachable!(),
+	};
+	let entry = dependency_entry(&version, importance, &platforms);
+	let path = [
+		PathSegment::Key("dependencies".into()),
+		PathSegment::Key(id.into()),
+	];
+	let existed = mod_json.get(&path).is_some();
+	mod_json
+		.set(&path, &entry)
+		.nice_unwrap("Unable to add dependency");
+	mod_json.save().nice_unwrap("Unable to save mod.json");
+
+	if existed {
+		done!("Updated dependency '{}' ({})", id, version);
 	} else {
-		done!("All dependencies resolved");
+		done!("Added dependency '{}' ({})", id, version);
 	}
+	info!("Run `geode project check` to fetch it");
+}
+
+fn remove_dependency(dir: &Path, id: &str) {
+	let mut mod_json =
+		JsonEditor::open_mod_json(dir).nice_unwrap("Must be inside a project with a mod.json");
+
+	let deps_path = || PathSegment::Key("dependencies".into());
+	let (ids, path): (Vec<String>, _) = match mod_json.get(&[deps_path()]) {
+		Some(Value::Object(deps)) => (
+			deps.keys().cloned().collect(),
+			deps.contains_key(id)
+				.then(|| vec![deps_path(), PathSegment::Key(id.into())]),
+		),
+		// Can be removed after Geode hits v5
+		Some(Value::Array(deps)) => {
+			let ids: Vec<_> = deps
+				.iter()
+				.filter_map(|d| d.get("id")?.as_str().map(String::from))
+				.collect();
+			let index = ids.iter().position(|d| d == id);
+			(ids, index.map(|i| vec![deps_path(), PathSegment::Index(i)]))
+		}
+		_ => (Vec::new(), None),
+	};
+
+	let Some(path) = path else {
+		match mod_schema::closest(id, ids.iter().map(String::as_str)) {
+			Some(similar) => fatal!(
+				"'{}' is not a dependency of this mod - did you mean '{}'?",
+				id,
+				similar
+			),
+			None => fatal!("'{}' is not a dependency of this mod", id),
+		}
+	};
+
+	mod_json
+		.remove(&path)
+		.nice_unwrap("Unable to remove dependency");
+	mod_json.save().nice_unwrap("Unable to save mod.json");
+	done!("Removed dependency '{}'", id);
 }
 
 fn add_resource(dir: &Path, resource: ResourceType, files: Vec<PathBuf>) {
-	let mut mod_json: HashMap<String, Value> = serde_json::from_reader(
-		fs::File::open(dir.join("mod.json"))
-			.ok()
-			.nice_unwrap("Must be inside a project with a mod.json"),
-	)
-	.nice_unwrap("Unable to read mod.json");
+	let mut mod_json =
+		JsonEditor::open_mod_json(dir).nice_unwrap("Must be inside a project with a mod.json");
+
+	let resource_path = |name: &str| {
+		[
+			PathSegment::Key("resources".into()),
+			PathSegment::Key(name.into()),
+		]
+	};
 
 	let mut do_thing = |name: &str, othername: &str| {
-		let resource = mod_json
-			.get("resources")
-			.and_then(|x| x.get(name))
+		let path = resource_path(name);
+		let mut new_resource = mod_json
+			.get(&path)
 			.and_then(|x| x.as_array())
-			.unwrap_or(&vec![])
-			.clone();
-
-		let mut new_resource: Vec<Value> = resource
-			.into_iter()
-			.chain(files.clone().into_iter().filter_map(|x| {
-				if !x.exists() {
-					warn!("{} {} does not exist", othername, x.display());
-					None
-				} else {
-					Some(Value::String(x.as_os_str().to_str().unwrap().to_string()))
-				}
-			}))
-			.collect();
+			.cloned()
+			.unwrap_or_default();
 
-		let mut duplicates: Vec<_> = new_resource
-			.iter()
-			.filter(|x| new_resource.iter().filter(|y| y == x).count() > 1)
-			.collect();
-		duplicates.dedup();
-		duplicates
-			.into_iter()
-			.for_each(|x| warn!("Duplicate {}: {}", othername, x));
-
-		new_resource.dedup();
-
-		*mod_json
-			.entry("resources".to_string())
-			.or_insert(json!({}))
-			.as_object_mut()
-			.nice_unwrap("resources is not an object")
-			.entry(name.to_string())
-			.or_insert(json!([]))
-			.as_array_mut()
-			.nice_unwrap(format!("{} is not an array", name)) = new_resource;
+		for file in &files {
+			if !file.exists() {
+				warn!("{} {} does not exist", othername, file.display());
+				continue;
+			}
+			let file = Value::String(file.as_os_str().to_str().unwrap().to_string());
+			if new_resource.contains(&file) {
+				warn!("Duplicate {}: {}", othername, file);
+				continue;
+			}
+			new_resource.push(file);
+		}
+
+		mod_json
+			.update(&path, &Value::Array(new_resource))
+			.nice_unwrap("Unable to edit mod.json");
 	};
 
 	match resource {
@@ -665,87 +1586,154 @@ fn add_resource(dir: &Path, resource: ResourceType, files: Vec<PathBuf>) {
 		ResourceType::File => do_thing("files", "File"),
 
 		ResourceType::Font => {
-			let fonts = mod_json
-				.get("resources")
-				.and_then(|x| x.get("fonts"))
-				.and_then(|x| x.as_array())
-				.unwrap_or(&vec![])
-				.clone();
-
-			let mut new_fonts: Vec<Value> = fonts
-				.into_iter()
-				.chain(files.into_iter().filter_map(|x| {
-					if !x.exists() {
-						warn!("Font {} does not exist", x.display());
-						None
-					} else {
-						let size = ask_value("Font Size", None, true)
-							.parse::<u32>()
-							.ok()
-							.nice_unwrap("Invalid font size!");
-
-						Some(json!({
-							"path": x.as_os_str().to_str().unwrap().to_string(),
-							"size": size
-						}))
-					}
-				}))
-				.collect();
+			for file in files {
+				if !file.exists() {
+					warn!("Font {} does not exist", file.display());
+					continue;
+				}
+				// Fonts are keyed by name, which defaults to the file name
+				let name = file.file_stem().unwrap().to_string_lossy().to_string();
+				let mut path = resource_path("fonts").to_vec();
+				path.push(PathSegment::Key(name.clone()));
+				if mod_json.get(&path).is_some() {
+					warn!("Duplicate Font: {}", name);
+					continue;
+				}
 
-			let mut duplicates: Vec<_> = new_fonts
-				.iter()
-				.filter_map(|x| x.get("path"))
-				.filter(|x| {
-					new_fonts
-						.iter()
-						.filter(|y| y.get("path").map(|y| y == *x).unwrap_or(false))
-						.count() > 1
-				})
-				.collect();
-			duplicates.dedup();
-			duplicates
-				.into_iter()
-				.for_each(|x| warn!("Duplicate Font: {}", x));
-
-			new_fonts.dedup();
-
-			*mod_json
-				.entry("resources".to_string())
-				.or_insert(json!({}))
-				.as_object_mut()
-				.nice_unwrap("resources is not an object")
-				.entry("fonts".to_string())
-				.or_insert(json!([]))
-				.as_array_mut()
-				.nice_unwrap("fonts is not an array") = new_fonts;
+				let size = ask_value("Font Size", None, true)
+					.parse::<u32>()
+					.ok()
+					.nice_unwrap("Invalid font size!");
+
+				mod_json
+					.set(
+						&path,
+						&json!({
+							"path": file.as_os_str().to_str().unwrap().to_string(),
+							"size": size
+						}),
+					)
+					.nice_unwrap("Unable to edit mod.json");
+			}
 		}
 	};
 
-	fs::write(
-		dir.join("mod.json"),
-		serde_json::to_string_pretty(&mod_json).unwrap(),
-	)
-	.nice_unwrap("Failed to save mod.json");
+	mod_json.save().nice_unwrap("Failed to save mod.json");
 
 	done!("Resource added to mod.json");
 }
 
+fn write_schema(output: Option<PathBuf>) {
+	let Some(output) = output else {
+		print!("{}", mod_schema::MOD_JSON_SCHEMA);
+		return;
+	};
+	fs::write(&output, mod_schema::MOD_JSON_SCHEMA).nice_unwrap("Unable to write schema");
+	done!("Schema written to {}", output.display());
+}
+
 pub fn subcommand(cmd: Project) {
+	// Checked once here rather than every time mod.json is parsed. Migrating
+	// is how legacy files that don't pass are fixed
+	if !matches!(
+		cmd,
+		Project::New { .. }
+			| Project::Migrate { .. }
+			| Project::Schema { .. }
+			| Project::Lint { rules: true }
+	) {
+		mod_schema::check_mod_json(&std::env::current_dir().unwrap());
+	}
+
 	match cmd {
 		Project::New { path } => template::build_template(path),
 		Project::ClearCache => clear_cache(&std::env::current_dir().unwrap()),
-		Project::Check {
-			install_dir,
-			platform,
-			externals,
-		} => check_dependencies(
-			std::env::current_dir().unwrap(),
-			install_dir.unwrap_or("build".into()),
-			platform,
-			externals,
+		Project::Check { args, frozen } => check_project_dependencies(
+			args,
+			if frozen {
+				LockMode::Frozen
+			} else {
+				LockMode::Use
+			},
 		),
+		Project::Update { args } => check_project_dependencies(args, LockMode::Update),
 		Project::Add { resource, files } => {
 			add_resource(&std::env::current_dir().unwrap(), resource, files)
 		}
+		Project::AddDep {
+			dependency,
+			importance,
+			platforms,
+			optional,
+		} => add_dependency(
+			&std::env::current_dir().unwrap(),
+			&dependency,
+			match (importance, optional) {
+				(Some(importance), _) => importance,
+				(None, true) => DependencyImportance::Suggested,
+				(None, false) => DependencyImportance::Required,
+			},
+			platforms,
+		),
+		Project::RemoveDep { id } => remove_dependency(&std::env::current_dir().unwrap(), &id),
+		Project::Lint { rules: true } => project_lint::list_rules(),
+		Project::Lint { rules: false } => {
+			project_lint::lint_project(&std::env::current_dir().unwrap())
+		}
+		Project::Migrate { check } => {
+			project_migrate::migrate_project(&std::env::current_dir().unwrap(), check)
+		}
+		Project::Schema { output } => write_schema(output),
+		Project::Codegen { what } => {
+			project_codegen::subcommand(&std::env::current_dir().unwrap(), what)
+		}
+		Project::Deps { what } => project_deps::subcommand(&std::env::current_dir().unwrap(), what),
+		Project::Outdated { platform } => {
+			project_deps::outdated(&std::env::current_dir().unwrap(), platform)
+		}
+		Project::Upgrade {
+			id,
+			install_dir,
+			args,
+		} => project_deps::upgrade(&std::env::current_dir().unwrap(), id, install_dir, args),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn added_dependencies_need_no_migration() {
+		for importance in [
+			DependencyImportance::Required,
+			DependencyImportance::Recommended,
+			DependencyImportance::Suggested,
+		] {
+			for platforms in [vec![], vec![PlatformName::Windows]] {
+				let entry = dependency_entry(">=1.0.0", importance, &platforms);
+
+				let mut mod_json = JsonEditor::from_text(
+					Path::new("mod.json"),
+					"{\n\t\"id\": \"dev.mod\",\n\t\"dependencies\": {}\n}\n".into(),
+				)
+				.unwrap();
+				let path = [
+					PathSegment::Key("dependencies".into()),
+					PathSegment::Key("dev.other".into()),
+				];
+				mod_json.set(&path, &entry).unwrap();
+
+				let mut migrated = mod_json.value().clone();
+				let changes = project_migrate::migrate_mod_json(&mut migrated);
+				assert!(changes.is_empty(), "{entry} needs migrating: {changes:?}");
+
+				let read_back = match &entry {
+					Value::String(_) => DependencyImportance::Required,
+					_ => Dependency::deserialize(entry.clone()).unwrap().importance(),
+				};
+				assert_eq!(read_back, importance, "{entry}");
+			}
+		}
 	}
 }
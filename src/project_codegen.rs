@@ -0,0 +1,405 @@
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::Subcommand;
+
+use crate::mod_file::{parse_mod_info, ModFileInfo};
+use crate::mod_settings::{Setting, SettingKind};
+use crate::{done, fatal, info, NiceUnwrap};
+
+#[derive(Subcommand, Debug)]
+#[clap(rename_all = "kebab-case")]
+pub enum Codegen {
+	/// Generate a C++ header with a typed accessor for every setting in
+	/// mod.json
+	Settings {
+		/// Where to write the header. Defaults to build/generated/Settings.hpp
+		output: Option<PathBuf>,
+
+		/// Namespace to put the accessors in
+		#[clap(long, default_value = "settings")]
+		namespace: String,
+	},
+}
+
+/// C++ keywords that can't be used as identifiers, at least the ones that
+/// could plausibly come up as setting names
+const CPP_KEYWORDS: &[&str] = &[
+	"and",
+	"auto",
+	"bool",
+	"break",
+	"case",
+	"catch",
+	"char",
+	"class",
+	"const",
+	"continue",
+	"default",
+	"delete",
+	"do",
+	"double",
+	"else",
+	"enum",
+	"explicit",
+	"export",
+	"extern",
+	"false",
+	"float",
+	"for",
+	"friend",
+	"goto",
+	"if",
+	"inline",
+	"int",
+	"long",
+	"namespace",
+	"new",
+	"not",
+	"operator",
+	"or",
+	"private",
+	"protected",
+	"public",
+	"register",
+	"return",
+	"short",
+	"signed",
+	"sizeof",
+	"static",
+	"struct",
+	"switch",
+	"template",
+	"this",
+	"throw",
+	"true",
+	"try",
+	"typedef",
+	"typename",
+	"union",
+	"unsigned",
+	"using",
+	"virtual",
+	"void",
+	"volatile",
+	"while",
+];
+
+/// Turn a key like `show-fps_counter` into `showFpsCounter`, or
+/// `ShowFpsCounter` if `upper` is set
+pub fn cpp_identifier(key: &str, upper: bool) -> String {
+	let mut out = String::new();
+	let mut capitalize = upper;
+	for c in key.chars() {
+		if !c.is_ascii_alphanumeric() {
+			capitalize = !out.is_empty() || upper;
+			continue;
+		}
+		if capitalize {
+			out.push(c.to_ascii_uppercase());
+			capitalize = false;
+		} else {
+			out.push(c);
+		}
+	}
+	if out.is_empty()
+		|| out.starts_with(|c: char| c.is_ascii_digit())
+		|| CPP_KEYWORDS.contains(&out.as_str())
+	{
+		out.insert(0, '_');
+	}
+	out
+}
+
+fn is_cpp_identifier(name: &str) -> bool {
+	!name.is_empty()
+		&& !name.starts_with(|c: char| c.is_ascii_digit())
+		&& name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+		&& !CPP_KEYWORDS.contains(&name)
+}
+
+/// Quote a string as a C++ string literal. Control characters are written
+/// as octal escapes, which unlike `\x` can't swallow the characters after
+/// them
+fn cpp_string(value: &str) -> String {
+	let mut out = String::from("\"");
+	for c in value.chars() {
+		match c {
+			'"' => out += "\\\"",
+			'\\' => out += "\\\\",
+			'\n' => out += "\\n",
+			'\r' => out += "\\r",
+			'\t' => out += "\\t",
+			c if c.is_ascii_control() => out += &format!("\\{:03o}", c as u32),
+			c => out.push(c),
+		}
+	}
+	out.push('"');
+	out
+}
+
+/// Write a generated file, leaving it untouched if its content is already
+/// the same so build systems don't rebuild everything that includes it.
+/// Returns whether the file was written
+pub fn write_if_changed(path: &Path, content: &str) -> Result<bool, String> {
+	if fs::read_to_string(path).is_ok_and(|old| old == content) {
+		return Ok(false);
+	}
+	if let Some(parent) = path.parent() {
+		fs::create_dir_all(parent)
+			.map_err(|e| format!("Unable to create {}: {e}", parent.display()))?;
+	}
+	fs::write(path, content).map_err(|e| format!("Unable to write {}: {e}", path.display()))?;
+	Ok(true)
+}
+
+/// C++ type that `Mod::getSettingValue` returns for a setting
+fn cpp_type(kind: &SettingKind) -> Option<&'static str> {
+	match kind {
+		SettingKind::Bool => Some("bool"),
+		SettingKind::Int { .. } => Some("int64_t"),
+		SettingKind::Float { .. } => Some("double"),
+		SettingKind::String { .. } => Some("std::string"),
+		SettingKind::File | SettingKind::Folder => Some("std::filesystem::path"),
+		SettingKind::Color => Some("cocos2d::ccColor3B"),
+		SettingKind::Rgba => Some("cocos2d::ccColor4B"),
+		SettingKind::Title | SettingKind::Custom(_) => None,
+	}
+}
+
+fn doc_comment(setting: &Setting, indent: &str) -> String {
+	let mut out = String::new();
+	for line in setting
+		.name
+		.iter()
+		.chain(setting.description.iter())
+		.flat_map(|text| text.lines())
+		.filter(|line| !line.trim().is_empty())
+	{
+		out += &format!("{indent}/// {}\n", line.trim());
+	}
+	out
+}
+
+/// Accessor for a string setting restricted to a set of choices, returning
+/// an enum instead of the string
+fn enum_accessor(setting: &Setting, choices: &[String], name: &str) -> String {
+	let ty = cpp_identifier(&setting.key, true);
+	let variants: Vec<_> = choices.iter().map(|c| cpp_identifier(c, true)).collect();
+
+	let mut out = format!("\tenum class {ty} {{\n");
+	for variant in &variants {
+		out += &format!("\t\t{variant},\n");
+	}
+	out += "\t};\n\n";
+
+	out += &doc_comment(setting, "\t");
+	out += &format!(
+		"\tinline {ty} {name}() {{\n\
+		\t\tauto value = geode::Mod::get()->getSettingValue<std::string>({key});\n",
+		key = cpp_string(&setting.key)
+	);
+	// Values that aren't one of the choices can only come from a broken save
+	// file, which Geode resets to the default
+	let fallback = setting
+		.default
+		.as_ref()
+		.and_then(|d| d.as_str())
+		.and_then(|d| choices.iter().position(|c| c == d))
+		.unwrap_or(0);
+	for (i, (choice, variant)) in choices.iter().zip(&variants).enumerate() {
+		if i != fallback {
+			out += &format!(
+				"\t\tif (value == {}) return {ty}::{variant};\n",
+				cpp_string(choice)
+			);
+		}
+	}
+	out += &format!("\t\treturn {ty}::{};\n\t}}\n", variants[fallback]);
+	out
+}
+
+/// Generate a header with one accessor per setting
+pub fn settings_header(settings: &[Setting], namespace: &str) -> Result<String, String> {
+	let mut out = String::from(
+		"// Generated by `geode project codegen settings` from mod.json, do not edit\n\
+		#pragma once\n\
+		\n\
+		#include <Geode/loader/Mod.hpp>\n\
+		#include <cstdint>\n\
+		#include <filesystem>\n\
+		#include <string>\n\
+		\n",
+	);
+	out += &format!("namespace {namespace} {{\n");
+
+	let mut names = HashSet::new();
+	for setting in settings {
+		let Some(ty) = cpp_type(&setting.kind) else {
+			continue;
+		};
+		let name = cpp_identifier(&setting.key, false);
+		if !names.insert(name.clone()) {
+			return Err(format!(
+				"Setting '{}' has the same C++ name as another setting, '{name}'",
+				setting.key
+			));
+		}
+
+		out += "\n";
+		match &setting.kind {
+			SettingKind::String {
+				one_of: Some(choices),
+			} if !choices.is_empty() => {
+				let variants: HashSet<_> =
+					choices.iter().map(|c| cpp_identifier(c, true)).collect();
+				if variants.len() != choices.len() {
+					return Err(format!(
+						"Choices of setting '{}' don't all have different C++ names",
+						setting.key
+					));
+				}
+				out += &enum_accessor(setting, choices, &name);
+			}
+			_ => {
+				out += &doc_comment(setting, "\t");
+				out += &format!(
+					"\tinline {ty} {name}() {{\n\
+					\t\treturn geode::Mod::get()->getSettingValue<{ty}>({key});\n\
+					\t}}\n",
+					key = cpp_string(&setting.key)
+				);
+			}
+		}
+	}
+
+	out += "}\n";
+	Ok(out)
+}
+
+/// Give every name a unique identifier, going through the names in sorted
+/// order so the identifiers don't change between runs
+fn name_constants(
+	names: BTreeSet<String>,
+	ident: impl Fn(&str) -> String,
+) -> Vec<(String, String)> {
+	let mut taken = HashSet::new();
+	let mut out = Vec::new();
+	for name in names {
+		let base = ident(&name);
+		let mut id = base.clone();
+		let mut n = 2;
+		while !taken.insert(id.clone()) {
+			id = format!("{base}_{n}");
+			n += 1;
+		}
+		out.push((id, name));
+	}
+	out.sort();
+	out
+}
+
+fn file_stem(path: &Path) -> String {
+	path.file_stem().unwrap().to_string_lossy().to_string()
+}
+
+/// Generate a header with a constant for the name of every sprite,
+/// spritesheet frame, font and file that `geode package resources` creates
+pub fn resources_header(mod_info: &ModFileInfo) -> String {
+	let id = &mod_info.id;
+	let res = &mod_info.resources;
+	let groups = [
+		(
+			"sprites",
+			res.sprites
+				.iter()
+				.map(|s| format!("{id}/{}.png", file_stem(s)))
+				.collect::<BTreeSet<_>>(),
+		),
+		(
+			"frames",
+			res.spritesheets
+				.values()
+				.flat_map(|sheet| &sheet.files)
+				.map(|f| {
+					let stem = file_stem(f);
+					let name = stem
+						.strip_suffix("-uhd")
+						.or_else(|| stem.strip_suffix("-hd"))
+						.unwrap_or(&stem);
+					format!("{id}/{name}.png")
+				})
+				.collect(),
+		),
+		(
+			"fonts",
+			res.fonts
+				.values()
+				.map(|f| format!("{id}/{}.fnt", f.name))
+				.collect(),
+		),
+		(
+			"files",
+			res.files
+				.iter()
+				.map(|f| format!("{id}/{}", f.file_name().unwrap().to_string_lossy()))
+				.collect(),
+		),
+	];
+
+	let mut out = String::from(
+		"// Generated by `geode package resources` from mod.json, do not edit\n\
+		#pragma once\n\
+		\n\
+		namespace resources {\n",
+	);
+	for (namespace, names) in groups {
+		if names.is_empty() {
+			continue;
+		}
+		// Sprites and frames are always .png, so the extension is left out
+		let with_extension = namespace == "files";
+		let constants = name_constants(names, |name| {
+			let file = name.split_once('/').map(|(_, f)| f).unwrap_or(name);
+			if with_extension {
+				cpp_identifier(file, false)
+			} else {
+				cpp_identifier(file_stem(Path::new(file)).as_str(), false)
+			}
+		});
+
+		out += &format!("\tnamespace {namespace} {{\n");
+		for (ident, name) in constants {
+			out += &format!("\t\tconstexpr auto {ident} = {};\n", cpp_string(&name));
+		}
+		out += "\t}\n";
+	}
+	out += "}\n";
+	out
+}
+
+fn codegen_settings(root: &Path, output: Option<PathBuf>, namespace: &str) {
+	let mod_info = parse_mod_info(root);
+	let output =
+		output.unwrap_or_else(|| root.join("build").join("generated").join("Settings.hpp"));
+
+	let header = settings_header(&mod_info.settings, namespace)
+		.nice_unwrap("Unable to generate settings header");
+	if write_if_changed(&output, &header).nice_unwrap("Unable to write settings header") {
+		done!("Generated {}", output.display());
+	} else {
+		info!("{} is up to date", output.display());
+	}
+}
+
+pub fn subcommand(root: &Path, cmd: Codegen) {
+	match cmd {
+		Codegen::Settings { output, namespace } => {
+			if !namespace.split("::").all(is_cpp_identifier) {
+				fatal!("'{}' is not a valid C++ namespace", namespace);
+			}
+			codegen_settings(root, output, &namespace)
+		}
+	}
+}
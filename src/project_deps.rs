@@ -0,0 +1,554 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use clap::{Subcommand, ValueEnum};
+use colored::Colorize;
+use regex::Regex;
+use semver::Version;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::config::Config;
+use crate::json_edit::JsonEditor;
+use crate::json_span::PathSegment;
+use crate::logging;
+use crate::mod_file::{parse_mod_info, DependencyImportance, GDVersion, PlatformName};
+use crate::project::{
+	check_dependencies, importance_name, resolve_dependencies, target_platform, DependencyGraph,
+	DependencySource, Requirement, RequirementStatus, ResolveArgs,
+};
+use crate::project_lock::{LockFile, LockMode};
+use crate::server::ServerModVersionInfo;
+use crate::util::logging::print_table;
+use crate::{done, fatal, index, index_search, info, warn, NiceUnwrap};
+
+#[derive(Subcommand, Debug)]
+#[clap(rename_all = "kebab-case")]
+pub enum Deps {
+	/// Print the resolved dependencies of the project as a tree, including
+	/// the dependencies of dependencies
+	Tree {
+		#[clap(flatten)]
+		args: ResolveArgs,
+
+		/// Print the tree as JSON
+		#[clap(long)]
+		json: bool,
+
+		/// Only show the paths that lead to this dependency
+		#[clap(long, value_name = "MOD_ID")]
+		why: Option<String>,
+	},
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum NodeStatus {
+	Resolved,
+	External,
+	/// Optional, and not asked to be fetched
+	NotFetched,
+	/// Not needed on the platform the tree is for
+	OtherPlatform,
+	Unresolved,
+}
+
+/// A dependency in the tree, as required by its parent
+#[derive(Serialize, Debug)]
+pub struct TreeNode {
+	pub id: String,
+	/// The version its parent requires
+	pub requirement: String,
+	/// The version that was picked, if any
+	pub version: Option<String>,
+	pub source: Option<DependencySource>,
+	pub status: NodeStatus,
+	pub importance: DependencyImportance,
+	pub required: bool,
+	pub platforms: Vec<String>,
+	pub dependencies: Vec<TreeNode>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DependencyTree {
+	pub id: String,
+	pub version: String,
+	pub platform: String,
+	pub dependencies: Vec<TreeNode>,
+}
+
+fn sorted_requirements<'a>(graph: &'a DependencyGraph, id: &'a str) -> Vec<&'a Requirement> {
+	let mut reqs: Vec<_> = graph.requirements_of(id).collect();
+	reqs.sort_by(|a, b| a.dependency.id.cmp(&b.dependency.id));
+	reqs
+}
+
+/// Build the node for a requirement. `path` holds the mods above it, so
+/// dependency cycles aren't followed forever
+fn tree_node(graph: &DependencyGraph, req: &Requirement, path: &mut Vec<String>) -> TreeNode {
+	let id = &req.dependency.id;
+	let resolved = graph.resolved.get(id);
+	let (status, version, source) = match &req.status {
+		RequirementStatus::OtherPlatform => (NodeStatus::OtherPlatform, None, None),
+		RequirementStatus::NotFetched => (NodeStatus::NotFetched, None, None),
+		RequirementStatus::External(version) => (
+			NodeStatus::External,
+			version.as_ref().map(|v| v.to_string()),
+			Some(DependencySource::External),
+		),
+		RequirementStatus::Fetched => match resolved {
+			Some(dep) => (
+				NodeStatus::Resolved,
+				Some(dep.info.version.to_string()),
+				Some(dep.source),
+			),
+			None => (NodeStatus::Unresolved, None, None),
+		},
+	};
+
+	let mut dependencies = Vec::new();
+	if status == NodeStatus::Resolved && !path.contains(id) {
+		path.push(id.clone());
+		dependencies = sorted_requirements(graph, id)
+			.into_iter()
+			.map(|r| tree_node(graph, r, path))
+			.collect();
+		path.pop();
+	}
+
+	let mut platforms: Vec<_> = req
+		.dependency
+		.platforms
+		.iter()
+		.map(|p| p.to_string())
+		.collect();
+	platforms.sort();
+
+	let importance = req.dependency.importance();
+	TreeNode {
+		id: id.clone(),
+		requirement: req.dependency.version.to_string(),
+		version,
+		source,
+		status,
+		importance,
+		required: importance == DependencyImportance::Required,
+		platforms,
+		dependencies,
+	}
+}
+
+pub fn dependency_tree(graph: &DependencyGraph, version: String) -> DependencyTree {
+	let mut path = vec![graph.root.clone()];
+	DependencyTree {
+		id: graph.root.clone(),
+		version,
+		platform: graph.platform.to_string(),
+		dependencies: sorted_requirements(graph, &graph.root)
+			.into_iter()
+			.map(|r| tree_node(graph, r, &mut path))
+			.collect(),
+	}
+}
+
+/// Drop every branch that doesn't lead to `id`, returning whether anything
+/// is left
+fn keep_paths_to(nodes: &mut Vec<TreeNode>, id: &str) -> bool {
+	nodes.retain_mut(|node| {
+		if node.id == id {
+			node.dependencies.clear();
+			true
+		} else {
+			keep_paths_to(&mut node.dependencies, id)
+		}
+	});
+	!nodes.is_empty()
+}
+
+fn node_label(node: &TreeNode, platform: &str) -> String {
+	let importance = importance_name(node.importance).to_lowercase();
+	let version = node
+		.version
+		.as_ref()
+		.map(|v| format!(" -> v{v}"))
+		.unwrap_or_default();
+	let details = match node.status {
+		NodeStatus::Resolved => format!(
+			"{}, {importance}",
+			serde_json::to_value(node.source).unwrap().as_str().unwrap()
+		),
+		NodeStatus::External if node.version.is_none() => {
+			format!("external, no version given, {importance}")
+		}
+		NodeStatus::External => format!("external, {importance}"),
+		NodeStatus::NotFetched => format!("not fetched, {importance}"),
+		NodeStatus::OtherPlatform => format!("not on {platform}, {importance}"),
+		NodeStatus::Unresolved => format!("unresolved, {importance}"),
+	};
+	let platforms = if node.platforms.len() == PlatformName::value_variants().len() {
+		String::new()
+	} else {
+		format!(" [{}]", node.platforms.join(", "))
+	};
+
+	let label = format!(
+		"{} {}{version} ({details}){platforms}",
+		node.id, node.requirement
+	);
+	match node.status {
+		NodeStatus::Unresolved => label.red().to_string(),
+		NodeStatus::NotFetched | NodeStatus::OtherPlatform => label.dimmed().to_string(),
+		_ => label,
+	}
+}
+
+/// Print the nodes below a parent. Mods whose dependencies have already
+/// been printed once are marked with (*) instead of printing them again
+fn print_nodes(nodes: &[TreeNode], prefix: &str, platform: &str, expanded: &mut HashSet<String>) {
+	for (i, node) in nodes.iter().enumerate() {
+		let last = i + 1 == nodes.len();
+		let repeated = !node.dependencies.is_empty() && !expanded.insert(node.id.clone());
+		println!(
+			"{prefix}{}{}{}",
+			if last { "└── " } else { "├── " },
+			node_label(node, platform),
+			if repeated { " (*)" } else { "" }
+		);
+		if !repeated {
+			let prefix = format!("{prefix}{}", if last { "    " } else { "│   " });
+			print_nodes(&node.dependencies, &prefix, platform, expanded);
+		}
+	}
+}
+
+fn print_tree(root: &Path, args: ResolveArgs, json: bool, why: Option<String>) {
+	let config = Config::new();
+	let mod_info = parse_mod_info(root);
+	let lock = LockFile::load(root).nice_unwrap("Unable to load lockfile");
+	let platform = target_platform(&mod_info, args.platform);
+
+	// The tree is the output, not the log of how it was resolved
+	logging::set_quiet(true);
+	// Only looking, so nothing gets installed into the profile
+	let graph = resolve_dependencies(
+		&mod_info,
+		&args,
+		platform,
+		&config,
+		&lock,
+		LockMode::Use,
+		false,
+	);
+	let mut tree = dependency_tree(&graph, mod_info.version.to_string());
+
+	if let Some(id) = &why {
+		if !keep_paths_to(&mut tree.dependencies, id) {
+			fatal!(
+				"'{}' is not a dependency of {} on {}",
+				id,
+				mod_info.id,
+				platform
+			);
+		}
+	}
+
+	if json {
+		println!("{}", serde_json::to_string_pretty(&tree).unwrap());
+	} else {
+		println!("{} v{} ({})", tree.id, tree.version, tree.platform);
+		print_nodes(&tree.dependencies, "", &tree.platform, &mut HashSet::new());
+	}
+
+	if graph.errors {
+		fatal!("Some dependencies were unresolved");
+	}
+}
+
+/// Where the version requirement of every dependency is written in mod.json
+fn requirement_paths(mod_json: &Value) -> Vec<(String, Vec<PathSegment>)> {
+	let key = |k: &str| PathSegment::Key(k.to_string());
+	match mod_json.get("dependencies") {
+		Some(Value::Object(deps)) => deps
+			.iter()
+			.map(|(id, dep)| {
+				let mut path = vec![key("dependencies"), key(id)];
+				// Longhand is "[mod.id]": { "version": ... }
+				if dep.is_object() {
+					path.push(key("version"));
+				}
+				(id.clone(), path)
+			})
+			.collect(),
+		// Can be removed after Geode hits v5
+		Some(Value::Array(deps)) => deps
+			.iter()
+			.enumerate()
+			.filter_map(|(i, dep)| {
+				let id = dep.get("id")?.as_str()?.to_string();
+				Some((
+					id,
+					vec![key("dependencies"), PathSegment::Index(i), key("version")],
+				))
+			})
+			.collect(),
+		_ => Vec::new(),
+	}
+}
+
+/// Split a requirement like `>=v1.2.0` into the text before the version, the
+/// version and the text after it. Only requirements of a single minimum or
+/// exact version can be split, since those are the only ones that can be
+/// raised without changing what they mean
+fn split_requirement(req: &str) -> Option<(&str, Version, &str)> {
+	let re = Regex::new(r"^(\s*(?:>=|=)?\s*v?)(\d+\.\d+\.\d+(?:-[0-9A-Za-z.-]+)?)(\s*)$").unwrap();
+	let caps = re.captures(req)?;
+	Some((
+		caps.get(1).unwrap().as_str(),
+		Version::parse(&caps[2]).ok()?,
+		caps.get(3).unwrap().as_str(),
+	))
+}
+
+/// What the versions of a dependency have to support for a project to be
+/// able to use them
+pub(crate) struct Target<'a> {
+	pub platform: PlatformName,
+	pub gd: &'a GDVersion,
+	pub geode: &'a Version,
+}
+
+impl Target<'_> {
+	/// Whether a version on the index is for the same platform, GD version
+	/// and major Geode version as the project, and no newer Geode version
+	fn supports(&self, version: &ServerModVersionInfo) -> bool {
+		let gd = match (self.gd, self.platform) {
+			(GDVersion::Simple(gd), _) => Some(gd),
+			(GDVersion::Detailed(gd), PlatformName::Windows) => gd.win.as_ref(),
+			(
+				GDVersion::Detailed(gd),
+				PlatformName::MacOS | PlatformName::MacArm | PlatformName::MacIntel,
+			) => gd.mac.as_ref(),
+			(
+				GDVersion::Detailed(gd),
+				PlatformName::Android | PlatformName::Android32 | PlatformName::Android64,
+			) => gd.android.as_ref(),
+			(GDVersion::Detailed(gd), PlatformName::Ios) => gd.ios.as_ref(),
+		};
+		let supports_gd = index_search::index_platforms(self.platform)
+			.iter()
+			.any(|p| {
+				version
+					.gd
+					.get(*p)
+					.and_then(|v| v.as_deref())
+					.is_some_and(|v| gd.is_none_or(|gd| v == gd || v == "*" || gd == "*"))
+			});
+		let supports_geode = Version::parse(version.geode.trim_start_matches('v'))
+			.is_ok_and(|v| v.major == self.geode.major && v <= *self.geode);
+		supports_gd && supports_geode
+	}
+}
+
+/// Every version of a mod on the index, newest first, limited to those a
+/// project can use if there's a target
+pub(crate) fn index_versions(
+	id: &str,
+	config: &Config,
+	target: Option<&Target>,
+) -> Result<Vec<Version>, String> {
+	let Some(mod_info) = index_search::get_mod(id, config).map_err(|e| e.to_string())? else {
+		return Ok(Vec::new());
+	};
+	let mut versions: Vec<_> = mod_info
+		.versions
+		.iter()
+		.filter(|v| target.is_none_or(|t| t.supports(v)))
+		.filter_map(|v| Version::parse(v.version.trim_start_matches('v')).ok())
+		.collect();
+	versions.sort_by(|a, b| b.cmp(a));
+	Ok(versions)
+}
+
+/// The newest version, which is only a prerelease if `current` is one too
+/// or there are no releases at all
+pub(crate) fn latest_version(versions: &[Version], current: Option<&Version>) -> Option<Version> {
+	let wants_pre = current.is_some_and(|v| !v.pre.is_empty());
+	versions
+		.iter()
+		.find(|v| wants_pre || v.pre.is_empty())
+		.or(versions.first())
+		.cloned()
+}
+
+/// Print the latest versions of the dependencies on the index
+pub fn outdated(root: &Path, platform: Option<PlatformName>) {
+	let config = Config::new();
+	index::require_available(&config, "Checking for newer dependency versions");
+	let mod_info = parse_mod_info(root);
+	let editor = JsonEditor::open_mod_json(root).nice_unwrap("Unable to open mod.json");
+	let lock = LockFile::load(root).nice_unwrap("Unable to load lockfile");
+	let platform = platform.or_else(PlatformName::current);
+	let target = platform.map(|platform| Target {
+		platform,
+		gd: &mod_info.gd,
+		geode: &mod_info.geode,
+	});
+
+	let headers = [
+		"Dependency",
+		"Requirement",
+		"Locked",
+		"Compatible",
+		"Latest",
+	];
+	let mut rows = Vec::new();
+	let mut outdated = false;
+
+	let mut deps = requirement_paths(editor.value());
+	deps.sort_by(|a, b| a.0.cmp(&b.0));
+	for (id, path) in deps {
+		let Some(dep) = mod_info.dependencies.into_iter().find(|d| d.id == id) else {
+			continue;
+		};
+		let raw = editor
+			.get(&path)
+			.and_then(Value::as_str)
+			.unwrap_or_default();
+		let versions = match index_versions(&id, &config, target.as_ref()) {
+			Ok(versions) => versions,
+			Err(e) => {
+				warn!("Unable to get versions of '{}' from the index: {}", id, e);
+				continue;
+			}
+		};
+
+		let base = split_requirement(raw).map(|(_, version, _)| version);
+		let locked = platform
+			.and_then(|p| lock.get(&id, p))
+			.and_then(|l| Version::parse(&l.version).ok());
+		let compatible = versions.iter().find(|v| dep.version.matches(v)).cloned();
+		let latest = latest_version(&versions, base.as_ref());
+
+		// Newer versions are highlighted: a compatible one newer than the
+		// locked one, and a latest one newer than what mod.json asks for
+		let newer_compatible = locked.is_some() && compatible > locked;
+		let newer_latest = base.is_some() && latest.is_some() && latest > base;
+		outdated |= newer_compatible || newer_latest;
+
+		let show = |v: &Option<Version>| v.as_ref().map(|v| v.to_string()).unwrap_or("-".into());
+		rows.push((
+			[
+				id.clone(),
+				raw.to_string(),
+				show(&locked),
+				show(&compatible),
+				show(&latest),
+			],
+			[false, false, false, newer_compatible, newer_latest],
+		));
+	}
+
+	if rows.is_empty() {
+		info!("No dependencies to check");
+		return;
+	}
+
+	print_table(
+		&headers,
+		&rows
+			.iter()
+			.map(|(cells, _)| cells.to_vec())
+			.collect::<Vec<_>>(),
+		|row, col| rows[row].1[col],
+	);
+
+	if outdated {
+		info!("Run `geode project upgrade` to raise the requirements in mod.json to the latest versions");
+	} else {
+		done!("All dependencies are up to date");
+	}
+}
+
+/// Raise the requirements in mod.json to the latest versions on the index
+/// and resolve the dependencies again
+pub fn upgrade(root: &Path, id: Option<String>, install_dir: Option<PathBuf>, args: ResolveArgs) {
+	let config = Config::new();
+	index::require_available(&config, "Upgrading dependencies");
+	let mut editor = JsonEditor::open_mod_json(root).nice_unwrap("Unable to open mod.json");
+	let mod_info = parse_mod_info(root);
+	// Only versions the project can use on the platform it's checked for
+	let target = Target {
+		platform: target_platform(&mod_info, args.platform),
+		gd: &mod_info.gd,
+		geode: &mod_info.geode,
+	};
+
+	let mut deps = requirement_paths(editor.value());
+	if let Some(id) = &id {
+		deps.retain(|(dep, _)| dep == id);
+		if deps.is_empty() {
+			fatal!("'{}' is not a dependency of this mod", id);
+		}
+	}
+	deps.sort_by(|a, b| a.0.cmp(&b.0));
+
+	let mut upgraded = false;
+	for (id, path) in deps {
+		let raw = editor
+			.get(&path)
+			.and_then(Value::as_str)
+			.unwrap_or_default()
+			.to_string();
+		let Some((prefix, current, suffix)) = split_requirement(&raw) else {
+			warn!(
+				"Not upgrading '{}', its requirement '{}' isn't a single minimum (>=) or exact (=) version",
+				id, raw
+			);
+			continue;
+		};
+		let versions = match index_versions(&id, &config, Some(&target)) {
+			Ok(versions) => versions,
+			Err(e) => {
+				warn!("Unable to get versions of '{}' from the index: {}", id, e);
+				continue;
+			}
+		};
+		let Some(latest) = latest_version(&versions, Some(&current)) else {
+			warn!(
+				"'{}' has no versions on the index for {} and this mod's GD and Geode versions",
+				id, target.platform
+			);
+			continue;
+		};
+		if latest <= current {
+			info!("'{}' already requires the latest version ({})", id, current);
+			continue;
+		}
+
+		let new = format!("{prefix}{latest}{suffix}");
+		editor
+			.set(&path, &Value::String(new.clone()))
+			.nice_unwrap("Unable to update mod.json");
+		info!("Upgraded '{}' from '{}' to '{}'", id, raw, new);
+		upgraded = true;
+	}
+
+	if !upgraded {
+		done!("Nothing to upgrade");
+		return;
+	}
+	editor.save().nice_unwrap("Unable to save mod.json");
+
+	check_dependencies(
+		root.to_path_buf(),
+		install_dir.unwrap_or("build".into()),
+		&args,
+		LockMode::Use,
+	);
+}
+
+pub fn subcommand(root: &Path, cmd: Deps) {
+	match cmd {
+		Deps::Tree { args, json, why } => print_tree(root, args, json, why),
+	}
+}
@@ -0,0 +1,317 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::config::Config;
+use crate::dep_cache::find_cached;
+use crate::mod_file::{
+	parse_mod_info, try_parse_mod_info, GDVersion, ModFileInfo, PlatformName, MOD_ID_PATTERN,
+};
+use crate::mod_schema::{self, Severity};
+use crate::{done, fail, fatal, info, sdk, warn};
+
+pub struct LintRule {
+	pub id: &'static str,
+	pub severity: Severity,
+	pub description: &'static str,
+}
+
+/// Every rule checked by `geode project lint`. Rules can be turned off by
+/// listing their IDs under `lint.ignore` in mod.json
+pub const RULES: &[LintRule] = &[
+	LintRule {
+		id: "id-format",
+		severity: Severity::Error,
+		description: "Mod IDs must be in the form developer.name, in lowercase",
+	},
+	LintRule {
+		id: "geode-newer-than-sdk",
+		severity: Severity::Error,
+		description: "The targeted Geode version must not be newer than the installed SDK",
+	},
+	LintRule {
+		id: "missing-gd-platform",
+		severity: Severity::Error,
+		description: "Every platform a binary is shipped for needs an entry in `gd`",
+	},
+	LintRule {
+		id: "dependency-platforms",
+		severity: Severity::Warning,
+		description: "Dependencies that only exist on some platforms should be restricted to those",
+	},
+	LintRule {
+		id: "missing-logo",
+		severity: Severity::Warning,
+		description: "Mods should have a logo.png",
+	},
+	LintRule {
+		id: "missing-about",
+		severity: Severity::Warning,
+		description: "Mods should have an about.md",
+	},
+	LintRule {
+		id: "missing-changelog",
+		severity: Severity::Warning,
+		description: "Mods should have a changelog.md",
+	},
+];
+
+struct Finding {
+	rule: &'static LintRule,
+	message: String,
+}
+
+struct Linter<'a> {
+	root: &'a Path,
+	mod_info: &'a ModFileInfo,
+	findings: Vec<Finding>,
+}
+
+/// Key of a platform in the `gd` object of mod.json
+fn gd_key(platform: PlatformName) -> &'static str {
+	match platform {
+		PlatformName::Windows => "win",
+		PlatformName::MacOS | PlatformName::MacIntel | PlatformName::MacArm => "mac",
+		PlatformName::Android | PlatformName::Android32 | PlatformName::Android64 => "android",
+		PlatformName::Ios => "ios",
+	}
+}
+
+/// Platforms (as `gd` keys) a mod declares a Geometry Dash version for
+fn gd_platforms(gd: &GDVersion) -> BTreeSet<&'static str> {
+	match gd {
+		GDVersion::Simple(_) => BTreeSet::from(["win", "mac", "android", "ios"]),
+		GDVersion::Detailed(gd) => [
+			("win", &gd.win),
+			("mac", &gd.mac),
+			("android", &gd.android),
+			("ios", &gd.ios),
+		]
+		.into_iter()
+		.filter(|(_, v)| v.is_some())
+		.map(|(k, _)| k)
+		.collect(),
+	}
+}
+
+impl Linter<'_> {
+	fn report(&mut self, id: &str, message: String) {
+		let rule = RULES.iter().find(|r| r.id == id).unwrap();
+		self.findings.push(Finding { rule, message });
+	}
+
+	fn check_id(&mut self) {
+		let id = &self.mod_info.id;
+		if !Regex::new(MOD_ID_PATTERN).unwrap().is_match(id) {
+			self.report(
+				"id-format",
+				format!(
+					"Mod ID '{id}' should be in the form developer.name, using only \
+					lowercase letters, numbers, - and _"
+				),
+			);
+		}
+	}
+
+	fn check_sdk_version(&mut self) {
+		if Config::try_sdk_path().is_err() {
+			info!("Geode SDK not found, skipping geode-newer-than-sdk");
+			return;
+		}
+		let sdk_version = sdk::get_version();
+		if self.mod_info.geode > sdk_version {
+			self.report(
+				"geode-newer-than-sdk",
+				format!(
+					"mod.json targets Geode {} but the installed SDK is {}",
+					self.mod_info.geode, sdk_version
+				),
+			);
+		}
+	}
+
+	/// Platforms (as `gd` keys) the project has binaries built for, looked
+	/// up the same way `geode package new` finds them
+	fn shipped_platforms(&self) -> Vec<(String, &'static str)> {
+		let prefix = format!("{}.", self.mod_info.id);
+		let mut found = Vec::new();
+		for dir in [self.root.to_path_buf(), self.root.join("build")] {
+			let Ok(entries) = dir.read_dir() else {
+				continue;
+			};
+			for entry in entries.flatten() {
+				let name = entry.file_name().to_string_lossy().to_string();
+				let Some(ext) = name.strip_prefix(&prefix) else {
+					continue;
+				};
+				let platform = match ext {
+					"dll" => "win",
+					"dylib" => "mac",
+					"ios.dylib" => "ios",
+					"so" | "android32.so" | "android64.so" => "android",
+					_ => continue,
+				};
+				found.push((name, platform));
+			}
+		}
+		found
+	}
+
+	fn check_gd_platforms(&mut self) {
+		let declared = gd_platforms(&self.mod_info.gd);
+		let mut reported = BTreeSet::new();
+		for (binary, platform) in self.shipped_platforms() {
+			if !declared.contains(platform) && reported.insert(platform) {
+				self.report(
+					"missing-gd-platform",
+					format!(
+						"Binary '{binary}' is shipped, but `gd` has no entry \
+						for '{platform}'"
+					),
+				);
+			}
+		}
+	}
+
+	fn check_dependency_platforms(&mut self) {
+		let ours = gd_platforms(&self.mod_info.gd);
+		let build_deps = self.root.join("build").join("geode-deps");
+
+		for dep in &self.mod_info.dependencies {
+			// Only dependencies that have already been fetched by
+			// `geode project check` can be looked at
+			let dep_info = Some(build_deps.join(&dep.id))
+				.filter(|p| p.exists())
+				.into_iter()
+				.chain(find_cached(&dep.id, &dep.version))
+				.find_map(|p| try_parse_mod_info(&p).ok());
+			let Some(dep_info) = dep_info else {
+				info!(
+					"Dependency '{}' has not been fetched yet, skipping its \
+					platform checks (run `geode project check` first)",
+					dep.id
+				);
+				continue;
+			};
+
+			let theirs = gd_platforms(&dep_info.gd);
+			let declared_for: BTreeSet<_> = dep.platforms.iter().map(|p| gd_key(*p)).collect();
+			let missing: Vec<_> = ours
+				.iter()
+				.filter(|p| declared_for.contains(*p) && !theirs.contains(*p))
+				.copied()
+				.collect();
+			if !missing.is_empty() {
+				let available: Vec<_> = ours.intersection(&theirs).copied().collect();
+				self.report(
+					"dependency-platforms",
+					format!(
+						"Dependency '{}' is not available on {}, restrict it with \
+						\"platforms\": {:?}",
+						dep.id,
+						missing.join(", "),
+						available
+					),
+				);
+			}
+		}
+	}
+
+	fn check_files(&mut self) {
+		for (file, rule) in [
+			("logo.png", "missing-logo"),
+			("about.md", "missing-about"),
+			("changelog.md", "missing-changelog"),
+		] {
+			if !self.root.join(file).exists() {
+				self.report(rule, format!("Missing {file}"));
+			}
+		}
+	}
+}
+
+/// Rule IDs listed under `lint.ignore` in mod.json
+fn ignored_rules(root: &Path) -> Vec<String> {
+	let Some(mod_json) = fs::read_to_string(root.join("mod.json"))
+		.ok()
+		.and_then(|s| serde_json::from_str::<Value>(&s).ok())
+	else {
+		return Vec::new();
+	};
+
+	let ignored: Vec<String> = mod_json
+		.pointer("/lint/ignore")
+		.and_then(|i| i.as_array())
+		.map(|i| {
+			i.iter()
+				.filter_map(|r| r.as_str().map(str::to_string))
+				.collect()
+		})
+		.unwrap_or_default();
+
+	for id in &ignored {
+		if RULES.iter().any(|r| r.id == id) {
+			continue;
+		}
+		match mod_schema::closest(id, RULES.iter().map(|r| r.id)) {
+			Some(close) => warn!("Unknown lint rule '{}', did you mean '{}'?", id, close),
+			None => warn!("Unknown lint rule '{}'", id),
+		}
+	}
+	ignored
+}
+
+pub fn list_rules() {
+	for rule in RULES {
+		let severity = match rule.severity {
+			Severity::Error => "error",
+			Severity::Warning => "warning",
+		};
+		println!("{} ({severity}): {}", rule.id, rule.description);
+	}
+}
+
+pub fn lint_project(root: &Path) {
+	let mod_info = parse_mod_info(root);
+	let ignored = ignored_rules(root);
+
+	let mut linter = Linter {
+		root,
+		mod_info: &mod_info,
+		findings: Vec::new(),
+	};
+	linter.check_id();
+	linter.check_sdk_version();
+	linter.check_gd_platforms();
+	linter.check_dependency_platforms();
+	linter.check_files();
+
+	let findings: Vec<_> = linter
+		.findings
+		.into_iter()
+		.filter(|f| !ignored.iter().any(|i| i == f.rule.id))
+		.collect();
+
+	for finding in &findings {
+		match finding.rule.severity {
+			Severity::Error => fail!("[{}] {}", finding.rule.id, finding.message),
+			Severity::Warning => warn!("[{}] {}", finding.rule.id, finding.message),
+		}
+	}
+
+	let errors = findings
+		.iter()
+		.filter(|f| f.rule.severity == Severity::Error)
+		.count();
+	let warnings = findings.len() - errors;
+	if errors > 0 {
+		fatal!("Lint found {} errors and {} warnings", errors, warnings);
+	} else if warnings > 0 {
+		done!("Lint found {} warnings", warnings);
+	} else {
+		done!("No issues found");
+	}
+}
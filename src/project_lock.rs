@@ -0,0 +1,115 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::mod_file::PlatformName;
+use crate::project_codegen::write_if_changed;
+
+/// Name of the lockfile, placed next to mod.json
+pub const LOCK_FILE: &str = "geode.lock";
+
+const LOCK_VERSION: u32 = 1;
+
+/// How `geode project check` treats geode.lock
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LockMode {
+	/// Reuse locked versions, locking any new dependencies
+	Use,
+	/// Ignore locked versions and lock whatever resolves now
+	Update,
+	/// Reuse locked versions, failing if the lock would have to change
+	Frozen,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct LockedDependency {
+	pub id: String,
+	pub version: String,
+	pub platform: String,
+	/// SHA-256 of the .geode package
+	pub hash: String,
+	/// Whether the package was taken from installed mods because it isn't on
+	/// the index, in which case it can't be downloaded on other machines
+	#[serde(default, skip_serializing_if = "std::ops::Not::not")]
+	pub installed: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct LockFile {
+	pub version: u32,
+	pub dependencies: Vec<LockedDependency>,
+}
+
+impl Default for LockFile {
+	fn default() -> Self {
+		Self {
+			version: LOCK_VERSION,
+			dependencies: Vec::new(),
+		}
+	}
+}
+
+pub fn lock_path(root: &Path) -> PathBuf {
+	root.join(LOCK_FILE)
+}
+
+impl LockFile {
+	/// Load the lockfile of a project, which is empty if there isn't one yet
+	pub fn load(root: &Path) -> Result<LockFile, String> {
+		let path = lock_path(root);
+		if !path.exists() {
+			return Ok(LockFile::default());
+		}
+		let text = std::fs::read_to_string(&path)
+			.map_err(|e| format!("Unable to read {}: {e}", path.display()))?;
+		let lock: LockFile = serde_json::from_str(&text)
+			.map_err(|e| format!("Unable to parse {}: {e}", path.display()))?;
+		if lock.version > LOCK_VERSION {
+			return Err(format!(
+				"{} was written by a newer version of the CLI, please update",
+				path.display()
+			));
+		}
+		Ok(lock)
+	}
+
+	/// Write the lockfile with its entries sorted, so it only changes when
+	/// the resolved dependencies do. Returns whether the file was written
+	pub fn save(&mut self, root: &Path) -> Result<bool, String> {
+		self.dependencies
+			.sort_by(|a, b| (&a.id, &a.platform).cmp(&(&b.id, &b.platform)));
+		write_if_changed(
+			&lock_path(root),
+			&(serde_json::to_string_pretty(self).unwrap() + "\n"),
+		)
+	}
+
+	pub fn get(&self, id: &str, platform: PlatformName) -> Option<&LockedDependency> {
+		let platform = platform.to_string();
+		self.dependencies
+			.iter()
+			.find(|d| d.id == id && d.platform == platform)
+	}
+
+	/// Add an entry, replacing the one for the same dependency and platform
+	pub fn insert(&mut self, entry: LockedDependency) {
+		self.dependencies
+			.retain(|d| d.id != entry.id || d.platform != entry.platform);
+		self.dependencies.push(entry);
+	}
+
+	/// Drop all entries for a platform, so they can be locked again
+	pub fn remove_platform(&mut self, platform: PlatformName) {
+		let platform = platform.to_string();
+		self.dependencies.retain(|d| d.platform != platform);
+	}
+
+	/// Whether two lockfiles lock the same things, regardless of order
+	pub fn same_as(&self, other: &LockFile) -> bool {
+		self.dependencies.len() == other.dependencies.len()
+			&& self
+				.dependencies
+				.iter()
+				.all(|d| other.dependencies.contains(d))
+	}
+}
@@ -0,0 +1,253 @@
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+use crate::json_edit::JsonEditor;
+use crate::{done, fatal, info, NiceUnwrap};
+
+/// Convert a legacy array of `{ "id": ..., ... }` objects into the keyed form
+fn migrate_array(key: &str, value: &mut Value, changes: &mut Vec<String>) {
+	let Value::Array(items) = value else {
+		return;
+	};
+
+	let mut keyed = Map::new();
+	for item in items.drain(..) {
+		let Value::Object(mut item) = item else {
+			fatal!("Entries in \"{}\" must be objects", key);
+		};
+		let Some(Value::String(id)) = item.shift_remove("id") else {
+			fatal!("Entry in \"{}\" is missing an id", key);
+		};
+		keyed.insert(id, Value::Object(item));
+	}
+	*value = Value::Object(keyed);
+	changes.push(format!(
+		"Converted \"{key}\" from the legacy array format to an object"
+	));
+}
+
+/// Drop `importance` from required dependencies since that's the default,
+/// collapsing dependencies that are left with only a version into the
+/// shorthand form, and turn it into `"required": false` for suggested ones.
+/// Recommended dependencies have no other way to be written, so they keep it
+fn migrate_dependencies(deps: &mut Value, changes: &mut Vec<String>) {
+	let Value::Object(deps) = deps else {
+		return;
+	};
+	for (id, dep) in deps.iter_mut() {
+		let Value::Object(obj) = dep else {
+			continue;
+		};
+		match obj.get("importance").and_then(|i| i.as_str()) {
+			Some("required") => {
+				obj.shift_remove("importance");
+				obj.shift_remove("required");
+				if obj.len() == 1 && obj.contains_key("version") {
+					*dep = obj.shift_remove("version").unwrap();
+				}
+				changes.push(format!(
+					"Removed \"importance\": \"required\" from dependency '{id}', as \
+					dependencies are required by default"
+				));
+			}
+			Some("suggested") => {
+				obj.shift_remove("importance");
+				obj.insert("required".into(), Value::Bool(false));
+				changes.push(format!(
+					"Replaced \"importance\": \"suggested\" of dependency '{id}' with \
+					\"required\": false"
+				));
+			}
+			_ => {}
+		}
+	}
+}
+
+/// Version requirements are written as an operator followed by a version
+/// without a prefix, like `>=1.2.0`
+fn migrate_requirements(key: &str, list: &mut Value, changes: &mut Vec<String>) {
+	let Value::Object(list) = list else {
+		return;
+	};
+	for (id, entry) in list.iter_mut() {
+		let version = match entry {
+			Value::Object(obj) => match obj.get_mut("version") {
+				Some(version) => version,
+				None => continue,
+			},
+			other => other,
+		};
+		let Value::String(req) = version else {
+			continue;
+		};
+		let trimmed = req.trim();
+		let bare = trimmed.trim_start_matches(['=', '<', '>', '^', '~']);
+		let op = &trimmed[..trimmed.len() - bare.len()];
+		let normalized = format!("{op}{}", bare.trim().trim_start_matches('v'));
+		if *req != normalized {
+			changes.push(format!(
+				"Normalized the version of '{id}' in \"{key}\" from \"{req}\" to \"{normalized}\""
+			));
+			*req = normalized;
+		}
+	}
+}
+
+/// Geode versions are written without a prefix, mod versions with a "v"
+fn migrate_version(key: &str, value: &mut Value, prefixed: bool, changes: &mut Vec<String>) {
+	let Value::String(version) = value else {
+		return;
+	};
+	let bare = version.trim().trim_start_matches('v');
+	let normalized = if prefixed {
+		format!("v{bare}")
+	} else {
+		bare.to_string()
+	};
+	if *version != normalized {
+		changes.push(format!(
+			"Normalized \"{key}\" from \"{version}\" to \"{normalized}\""
+		));
+		*version = normalized;
+	}
+}
+
+/// Migrate a mod.json to the current format, returning the descriptions of
+/// every change made
+pub fn migrate_mod_json(mod_json: &mut Value) -> Vec<String> {
+	let mut changes = Vec::new();
+	let Value::Object(root) = mod_json else {
+		fatal!("mod.json must be an object");
+	};
+
+	if let Some(geode) = root.get_mut("geode") {
+		migrate_version("geode", geode, false, &mut changes);
+	}
+	if let Some(version) = root.get_mut("version") {
+		migrate_version("version", version, true, &mut changes);
+	}
+	if let Some(deps) = root.get_mut("dependencies") {
+		migrate_array("dependencies", deps, &mut changes);
+		migrate_dependencies(deps, &mut changes);
+		migrate_requirements("dependencies", deps, &mut changes);
+	}
+	if let Some(incompats) = root.get_mut("incompatibilities") {
+		migrate_array("incompatibilities", incompats, &mut changes);
+		migrate_requirements("incompatibilities", incompats, &mut changes);
+	}
+
+	changes
+}
+
+pub fn migrate_project(root: &Path, check: bool) {
+	let mut mod_json = JsonEditor::open_mod_json(root).nice_unwrap("Unable to read mod.json");
+
+	let mut migrated = mod_json.value().clone();
+	let changes = migrate_mod_json(&mut migrated);
+	if changes.is_empty() {
+		done!("mod.json is up to date");
+		return;
+	}
+
+	for change in &changes {
+		info!("{}", change);
+	}
+	if check {
+		fatal!("mod.json needs to be migrated, run `geode project migrate`");
+	}
+
+	mod_json
+		.update(&[], &migrated)
+		.nice_unwrap("Unable to migrate mod.json");
+	mod_json.save().nice_unwrap("Unable to write mod.json");
+	done!("Migrated mod.json");
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	fn migrated(mut mod_json: Value) -> (Value, usize) {
+		let changes = migrate_mod_json(&mut mod_json);
+		(mod_json, changes.len())
+	}
+
+	#[test]
+	fn converts_legacy_arrays_to_objects() {
+		let (value, changes) = migrated(json!({
+			"dependencies": [
+				{ "id": "geode.node-ids", "version": ">=1.0.0" },
+				{ "id": "dev.other", "version": "1.2.0", "platforms": ["win"] },
+			],
+			"incompatibilities": [{ "id": "dev.bad", "version": "*" }],
+		}));
+		assert_eq!(
+			value,
+			json!({
+				"dependencies": {
+					"geode.node-ids": { "version": ">=1.0.0" },
+					"dev.other": { "version": "1.2.0", "platforms": ["win"] },
+				},
+				"incompatibilities": { "dev.bad": { "version": "*" } },
+			})
+		);
+		assert_eq!(changes, 2);
+	}
+
+	#[test]
+	fn migrates_importance_without_losing_it() {
+		let (value, changes) = migrated(json!({
+			"dependencies": [
+				{ "id": "dev.required", "version": ">=1.0.0", "importance": "required" },
+				{ "id": "dev.platform", "version": ">=1.0.0", "importance": "required", "platforms": ["win"] },
+				{ "id": "dev.recommended", "version": ">=1.0.0", "importance": "recommended" },
+				{ "id": "dev.suggested", "version": ">=1.0.0", "importance": "suggested" },
+			],
+		}));
+		assert_eq!(
+			value,
+			json!({
+				"dependencies": {
+					"dev.required": ">=1.0.0",
+					"dev.platform": { "version": ">=1.0.0", "platforms": ["win"] },
+					"dev.recommended": { "version": ">=1.0.0", "importance": "recommended" },
+					"dev.suggested": { "version": ">=1.0.0", "required": false },
+				},
+			})
+		);
+		assert_eq!(changes, 4);
+
+		// Already migrated files are left alone
+		assert_eq!(migrated(value.clone()), (value, 0));
+	}
+
+	#[test]
+	fn normalizes_version_prefixes() {
+		let (value, changes) = migrated(json!({
+			"geode": "v4.0.0",
+			"version": "1.0.0",
+			"dependencies": {
+				"dev.a": ">= v1.0.0",
+				"dev.b": { "version": "v2.0.0", "required": false },
+				"dev.c": "*",
+			},
+			"incompatibilities": { "dev.d": "<=v1.0.0" },
+		}));
+		assert_eq!(
+			value,
+			json!({
+				"geode": "4.0.0",
+				"version": "v1.0.0",
+				"dependencies": {
+					"dev.a": ">=1.0.0",
+					"dev.b": { "version": "2.0.0", "required": false },
+					"dev.c": "*",
+				},
+				"incompatibilities": { "dev.d": "<=1.0.0" },
+			})
+		);
+		assert_eq!(changes, 5);
+	}
+}
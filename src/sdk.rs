@@ -1,4 +1,4 @@
-use crate::config::Config;
+use crate::config::{self, Config};
 use crate::util::logging::ask_confirm;
 use clap::Subcommand;
 use colored::Colorize;
@@ -348,6 +348,7 @@ fn clone_repo(url: &str, into: &Path) -> Result<Repository, git2::Error> {
 }
 
 fn install(config: &mut Config, path: PathBuf, force: bool) {
+	config::require_online("Installing the SDK");
 	let path = path.absolutize().nice_unwrap("Failed to get absolute path");
 	let parent = path.parent().unwrap();
 
@@ -441,6 +442,7 @@ fn fetch_repo_info(repo: &git2::Repository) -> git2::MergeAnalysis {
 }
 
 fn update(config: &mut Config, branch: Option<String>) {
+	config::require_online("Updating the SDK");
 	// Switch branch if necessary
 	match branch.as_deref().unwrap_or(if config.sdk_nightly {
 		"nightly"
@@ -570,6 +572,7 @@ fn switch_to_tag(config: &mut Config, repo: &Repository) {
 }
 
 fn install_binaries(config: &mut Config, platform: Option<String>, version: Option<String>) {
+	config::require_online("Installing SDK binaries");
 	let release_tag: String;
 	let target_dir: PathBuf;
 	if config.sdk_nightly {
@@ -811,6 +814,7 @@ fn install_linux(
 	force_download_xwin: bool,
 	force_update_winsdk: bool,
 ) {
+	config::require_online("Installing the Linux cross-compilation tools");
 	let arch = arch.unwrap_or_else(|| "x86_64".to_owned());
 	let path = path.unwrap_or_else(Config::cross_tools_path);
 
@@ -1,13 +1,64 @@
-use serde::Deserialize;
+use crate::index_dev::ModDeveloper;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 pub struct ApiResponse<T> {
 	pub error: String,
 	pub payload: T,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 pub struct PaginatedData<T> {
 	pub data: Vec<T>,
 	pub count: i32,
 }
+
+/// A mod as the index lists it
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ServerMod {
+	pub id: String,
+	#[serde(default)]
+	pub featured: bool,
+	#[serde(default)]
+	pub download_count: i32,
+	#[serde(default)]
+	pub developers: Vec<ModDeveloper>,
+	#[serde(default)]
+	pub versions: Vec<ServerModVersionInfo>,
+	#[serde(default)]
+	pub tags: Vec<String>,
+	pub about: Option<String>,
+	pub repository: Option<String>,
+}
+
+/// A version of a mod as the index lists it. Dependencies and
+/// incompatibilities may be left out when listing every version of a mod
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ServerModVersionInfo {
+	#[serde(default)]
+	pub mod_id: String,
+	pub name: String,
+	pub version: String,
+	pub description: Option<String>,
+	#[serde(default)]
+	pub download_count: i32,
+	#[serde(default)]
+	pub geode: String,
+	#[serde(default)]
+	pub early_load: bool,
+	#[serde(default)]
+	pub api: bool,
+	/// GD version for each platform the version is available for
+	#[serde(default)]
+	pub gd: BTreeMap<String, Option<String>>,
+	pub dependencies: Option<Vec<ServerDependency>>,
+	pub incompatibilities: Option<Vec<ServerDependency>>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ServerDependency {
+	pub mod_id: String,
+	pub version: String,
+	pub importance: String,
+}
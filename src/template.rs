@@ -1,4 +1,4 @@
-use crate::config::Config;
+use crate::config::{self, Config};
 use crate::sdk::get_version;
 use crate::util::logging::{ask_confirm, ask_value};
 use crate::{done, info, warn, NiceUnwrap};
@@ -9,7 +9,7 @@ use regex::Regex;
 use serde::Serialize;
 use serde_json::json;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 struct CreateTemplate {
 	pub template: String,
@@ -23,6 +23,21 @@ struct CreateTemplate {
 }
 
 fn create_template(template: CreateTemplate) {
+	let (used_template, branch) = if template.template.contains('@') {
+		template.template.split_once('@').unwrap()
+	} else if template.template.contains('/') {
+		(template.template.as_str(), "main")
+	} else if template.template.is_empty() {
+		("https://github.com/geode-sdk/example-mod", "main")
+	} else {
+		(template.template.as_str(), "main")
+	};
+
+	// Only templates cloned from a local repository work offline
+	if !Path::new(used_template).exists() {
+		config::require_online(&format!("Cloning template {used_template}"));
+	}
+
 	if template.project_location.exists() {
 		warn!("The provided location already exists.");
 		if !ask_confirm("Are you sure you want to proceed?", false) {
@@ -34,16 +49,6 @@ fn create_template(template: CreateTemplate) {
 			.nice_unwrap("Unable to create project directory");
 	}
 
-	let (used_template, branch) = if template.template.contains('@') {
-		template.template.split_once('@').unwrap()
-	} else if template.template.contains('/') {
-		(template.template.as_str(), "main")
-	} else if template.template.is_empty() {
-		("https://github.com/geode-sdk/example-mod", "main")
-	} else {
-		(template.template.as_str(), "main")
-	};
-
 	// Remove this if you dont think its needed
 	info!("Cloning branch {} of repository {}", branch, used_template);
 
@@ -95,17 +100,25 @@ fn create_template(template: CreateTemplate) {
 
 	// Add cross-platform action
 	// Download the action from https://raw.githubusercontent.com/geode-sdk/build-geode-mod/main/examples/multi-platform.yml
-	let action_path = template
-		.project_location
-		.join(".github/workflows/multi-platform.yml");
-	fs::create_dir_all(action_path.parent().unwrap())
-		.nice_unwrap("Unable to create .github/workflows directory");
-	let action = reqwest::blocking::get("https://raw.githubusercontent.com/geode-sdk/build-geode-mod/main/examples/multi-platform.yml").nice_unwrap("Unable to download action");
-	fs::write(
-		action_path,
-		action.text().nice_unwrap("Unable to write action"),
-	)
-	.nice_unwrap("Unable to write action");
+	if config::is_offline() {
+		warn!(
+			"Skipping the GitHub Actions workflow, as it can't be downloaded in \
+			offline mode. Add it later from \
+			https://github.com/geode-sdk/build-geode-mod/blob/main/examples/multi-platform.yml"
+		);
+	} else {
+		let action_path = template
+			.project_location
+			.join(".github/workflows/multi-platform.yml");
+		fs::create_dir_all(action_path.parent().unwrap())
+			.nice_unwrap("Unable to create .github/workflows directory");
+		let action = reqwest::blocking::get("https://raw.githubusercontent.com/geode-sdk/build-geode-mod/main/examples/multi-platform.yml").nice_unwrap("Unable to download action");
+		fs::write(
+			action_path,
+			action.text().nice_unwrap("Unable to write action"),
+		)
+		.nice_unwrap("Unable to write action");
+	}
 
 	let mod_json_path = template.project_location.join("mod.json");
 
@@ -1,5 +1,7 @@
 use crate::cache::CacheBundle;
-use crate::mod_file::BitmapFont;
+use crate::fnt::{FntChar, FntFile};
+use crate::mod_file::{BitmapFont, Color, FontShadow};
+use crate::spritesheet;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
@@ -8,8 +10,9 @@ use texture_packer::texture::Texture;
 use texture_packer::TexturePacker;
 use texture_packer::TexturePackerConfig;
 
-use crate::{done, info, NiceUnwrap};
-use image::{Rgba, RgbaImage};
+use crate::error::{Error, WithPath};
+use crate::{done, info, warn};
+use image::{imageops, GrayImage, Luma, Rgba, RgbaImage};
 
 use super::mod_file::ModFileInfo;
 
@@ -72,7 +75,72 @@ fn gen_outline<T: DistanceStorage>(sdf: SignedDistanceField<T>, size: f32) -> im
 	img
 }*/
 
-fn generate_char(font: &BitmapFont, metrics: fontdue::Metrics, data: Vec<u8>) -> Option<RgbaImage> {
+/// Extra space around every glyph image, needed to fit effects like shadows
+#[derive(Default, Clone, Copy)]
+struct GlyphPadding {
+	top: u32,
+	right: u32,
+	bottom: u32,
+	left: u32,
+}
+
+/// Font shadow with its offset and blur scaled down to the tier being built
+struct ScaledShadow<'a> {
+	x: i32,
+	y: i32,
+	blur: f32,
+	color: &'a Color,
+}
+
+impl<'a> ScaledShadow<'a> {
+	fn new(shadow: &'a FontShadow, factor: u32) -> Self {
+		let scale = |v: i32| (v as f32 / factor as f32).round() as i32;
+		Self {
+			x: scale(shadow.offset[0]),
+			y: scale(shadow.offset[1]),
+			blur: shadow.blur as f32 / factor as f32,
+			color: &shadow.color,
+		}
+	}
+
+	fn padding(&self) -> GlyphPadding {
+		let spread = self.blur.ceil() as i32;
+		GlyphPadding {
+			top: (spread - self.y).max(0) as u32,
+			right: (spread + self.x).max(0) as u32,
+			bottom: (spread + self.y).max(0) as u32,
+			left: (spread - self.x).max(0) as u32,
+		}
+	}
+}
+
+/// Composite `src` over `dst` (straight alpha)
+fn blend_over(dst: &mut Rgba<u8>, src: Rgba<u8>) {
+	if dst.0[3] == 0 {
+		*dst = src;
+		return;
+	}
+	let sa = src.0[3] as f32 / 255.0;
+	let da = dst.0[3] as f32 / 255.0;
+	let out_a = sa + da * (1.0 - sa);
+	if out_a <= 0.0 {
+		return;
+	}
+	for i in 0..3 {
+		let c = (src.0[i] as f32 * sa + dst.0[i] as f32 * da * (1.0 - sa)) / out_a;
+		dst.0[i] = c.round().clamp(0.0, 255.0) as u8;
+	}
+	dst.0[3] = (out_a * 255.0).round().clamp(0.0, 255.0) as u8;
+}
+
+fn generate_char(
+	font: &BitmapFont,
+	shadow: Option<&ScaledShadow>,
+	padding: GlyphPadding,
+	line_metrics: &fontdue::LineMetrics,
+	metrics: fontdue::Metrics,
+	data: Vec<u8>,
+) -> Option<RgbaImage> {
 	if data.is_empty() {
 		return None;
 	}
@@ -99,68 +167,109 @@ fn generate_char(font: &BitmapFont, metrics: fontdue::Metrics, data: Vec<u8>) ->
 	let width = metrics.width as u32;
 	let height = metrics.height as u32;
 
-	Some(RgbaImage::from_fn(width, height, |x, y| {
-		Rgba::<u8>([
-			font.color.red,
-			font.color.green,
-			font.color.blue,
-			data[(x + width * y) as usize],
-		])
-	}))
-}
+	let mut img = RgbaImage::new(
+		width + padding.left + padding.right,
+		height + padding.top + padding.bottom,
+	);
 
-fn initialize_font_bundle(
-	bundle: &FontBundle,
-	font: &BitmapFont,
-	factor: u32,
-	_mod_info: &ModFileInfo,
-) -> PathBuf {
-	// Get all characters from the charset format
-	let chars: Vec<char> = font
-		.charset
-		.as_deref()
-		.unwrap_or("32-126,8226")
-		.split(',')
-		.map(|x| {
-			x.split('-')
-				.map(|x| x.parse().unwrap())
-				.collect::<Vec<u32>>()
-		})
-		.flat_map(|x| {
-			assert!(
-				x.len() <= 2,
-				"Invalid charset '{}'",
-				font.charset.as_ref().unwrap()
+	if let Some(shadow) = shadow {
+		let mut mask = GrayImage::new(img.width(), img.height());
+		let origin_x = (padding.left as i32 + shadow.x) as u32;
+		let origin_y = (padding.top as i32 + shadow.y) as u32;
+		for y in 0..height {
+			for x in 0..width {
+				mask.put_pixel(
+					origin_x + x,
+					origin_y + y,
+					Luma([data[(x + width * y) as usize]]),
+				);
+			}
+		}
+		if shadow.blur > 0.0 {
+			// The padding covers the blur radius, which is about 3 sigmas
+			mask = imageproc::filter::gaussian_blur_f32(&mask, shadow.blur / 3.0);
+		}
+		for (x, y, pixel) in mask.enumerate_pixels() {
+			img.put_pixel(
+				x,
+				y,
+				Rgba([
+					shadow.color.red,
+					shadow.color.green,
+					shadow.color.blue,
+					pixel.0[0],
+				]),
 			);
-			*x.first().unwrap()..*x.last().unwrap() + 1
-		})
-		.map(|c| char::from_u32(c).unwrap())
-		.collect();
-
-	// Scaled font size
-	let scaled_size = font.size / factor;
+		}
+	}
 
-	// Read & parse source .ttf file
-	let ttf_font = fontdue::Font::from_bytes(
-		fs::read(&font.path).unwrap(),
-		fontdue::FontSettings::default(),
-	)
-	.unwrap();
+	// Gradients are relative to the line box rather than the glyph itself so
+	// that every character gets the same color at the same height
+	let line_height = line_metrics.ascent - line_metrics.descent;
+	let glyph_top = metrics.ymin as f32 + metrics.height as f32;
+
+	for y in 0..height {
+		let fill = match &font.gradient {
+			Some(gradient) => {
+				let above_baseline = glyph_top - y as f32 - 0.5;
+				let t = ((line_metrics.ascent - above_baseline) / line_height).clamp(0.0, 1.0);
+				gradient.top.lerp(&gradient.bottom, t)
+			}
+			None => font.color.clone(),
+		};
+		for x in 0..width {
+			blend_over(
+				img.get_pixel_mut(x + padding.left, y + padding.top),
+				Rgba([
+					fill.red,
+					fill.green,
+					fill.blue,
+					data[(x + width * y) as usize],
+				]),
+			);
+		}
+	}
 
-	// Rasterize characters from charset using the source font
-	let rasterized_chars: Vec<_> = chars
-		.iter()
-		.filter_map(|c| {
-			let (metrics, data) = ttf_font.rasterize(*c, scaled_size as f32);
+	Some(img)
+}
 
-			generate_char(font, metrics, data).map(|img| RenderedChar { id: *c, img })
-		})
-		.collect();
+/// Parse the charset of a font, which is a list of code points and code point
+/// ranges, i.e. "32-126,8226"
+fn font_charset(font: &BitmapFont) -> Result<Vec<char>, Error> {
+	let charset = font.charset.as_deref().unwrap_or("32-126,8226");
+	let invalid = || Error::Resource(format!("Invalid charset '{charset}' in font {}", font.name));
+
+	let mut chars = Vec::new();
+	for range in charset.split(',') {
+		let bounds = range
+			.split('-')
+			.map(|x| x.trim().parse::<u32>())
+			.collect::<Result<Vec<_>, _>>()
+			.map_err(|_| invalid())?;
+		if bounds.len() > 2 {
+			return Err(invalid());
+		}
+		for c in bounds[0]..bounds[bounds.len() - 1] + 1 {
+			chars.push(char::from_u32(c).ok_or_else(invalid)?);
+		}
+	}
+	Ok(chars)
+}
 
+/// Pack rendered characters into a single texture
+fn pack_chars<'a>(
+	font: &BitmapFont,
+	rasterized_chars: &'a [RenderedChar],
+) -> Result<TexturePacker<'a, RgbaImage, char>, Error> {
 	// Determine bounds to create the most efficient packing
 	let char_widths = rasterized_chars.iter().map(|c| c.img.width());
 
-	let widest_char: u32 = char_widths.clone().max().unwrap();
+	let Some(widest_char) = char_widths.clone().max() else {
+		return Err(Error::Resource(format!(
+			"Font {} contains no characters",
+			font.name
+		)));
+	};
 	let width_sum: u32 = char_widths.sum();
 	let mean_height: f64 = (rasterized_chars.iter().map(|c| c.img.height()).sum::<u32>() as f64)
 		/ rasterized_chars.len() as f64;
@@ -183,14 +292,78 @@ fn initialize_font_bundle(
 	};
 	let mut packer = TexturePacker::new_skyline(config);
 
-	rasterized_chars
+	for c in rasterized_chars {
+		packer
+			.pack_ref(c.id, &c.img)
+			.map_err(|e| Error::Resource(format!("Unable to pack character {:?}: {e:?}", c.id)))?;
+	}
+
+	Ok(packer)
+}
+
+/// Write the texture of packed characters to the .png of a bundle
+fn write_packed_png(
+	packer: &TexturePacker<'_, RgbaImage, char>,
+	bundle: &FontBundle,
+) -> Result<(), Error> {
+	let exporter = ImageExporter::export(packer, None).map_err(Error::Resource)?;
+	let mut f = fs::File::create(&bundle.png).with_path(&bundle.png)?;
+	exporter
+		.write_to(&mut f, image::ImageFormat::Png)
+		.with_path(&bundle.png)
+}
+
+fn initialize_font_bundle(
+	bundle: &FontBundle,
+	font: &BitmapFont,
+	factor: u32,
+	_mod_info: &ModFileInfo,
+) -> Result<PathBuf, Error> {
+	if font.is_prebuilt() {
+		return import_font_bundle(bundle, font, factor);
+	}
+
+	// Get all characters from the charset format
+	let chars = font_charset(font)?;
+
+	// Scaled font size
+	let scaled_size = font.size / factor;
+
+	// Read & parse source .ttf file
+	let ttf_font = fontdue::Font::from_bytes(
+		fs::read(&font.path).with_path(&font.path)?,
+		fontdue::FontSettings::default(),
+	)
+	.map_err(|e| Error::Resource(format!("Unable to parse font {}: {e}", font.path.display())))?;
+
+	let line_metrics = ttf_font
+		.horizontal_line_metrics(scaled_size as f32)
+		.ok_or_else(|| {
+			Error::Resource(format!(
+				"Font {} has no horizontal metrics",
+				font.path.display()
+			))
+		})?;
+
+	// Effects that draw outside the glyph need extra room around it
+	let shadow = font.shadow.as_ref().map(|s| ScaledShadow::new(s, factor));
+	let padding = shadow.as_ref().map(|s| s.padding()).unwrap_or_default();
+
+	// Rasterize characters from charset using the source font
+	let rasterized_chars: Vec<_> = chars
 		.iter()
-		.for_each(|x| packer.pack_ref(x.id, &x.img).unwrap());
+		.filter_map(|c| {
+			let (metrics, data) = ttf_font.rasterize(*c, scaled_size as f32);
+
+			generate_char(font, shadow.as_ref(), padding, &line_metrics, metrics, data)
+				.map(|img| RenderedChar { id: *c, img })
+		})
+		.collect();
+
+	let packer = pack_chars(font, &rasterized_chars)?;
 
 	// Create .png file
-	let exporter = ImageExporter::export(&packer, None).unwrap();
-	let mut f = fs::File::create(&bundle.png).nice_unwrap("Unable to write font .png file");
-	exporter.write_to(&mut f, image::ImageFormat::Png).unwrap();
+	write_packed_png(&packer, bundle)?;
 
 	// Get all characters and their metrics (positions in the png)
 	// Add space explicitly because it's empty and not in the frames
@@ -209,8 +382,8 @@ fn initialize_font_bundle(
 			frame.frame.y as i32,
 			frame.frame.w as i32,
 			frame.frame.h as i32,
-			metrics.xmin,
-			scaled_size as i32 - metrics.height as i32 - metrics.ymin,
+			metrics.xmin - padding.left as i32,
+			scaled_size as i32 - metrics.height as i32 - metrics.ymin - padding.top as i32,
 			metrics.advance_width as i32
 		));
 	}
@@ -228,7 +401,7 @@ fn initialize_font_bundle(
 					.map(|kern| {
 						format!(
 							"kerning first={} second={} amount={}",
-							left.id, right.id, kern as i32
+							left.id as u32, right.id as u32, kern as i32
 						)
 					})
 			})
@@ -239,12 +412,10 @@ fn initialize_font_bundle(
 	all_kerning_pairs.sort();
 
 	// Create .fnt file
-	let line_metrics = ttf_font
-		.horizontal_line_metrics(scaled_size as f32)
-		.unwrap();
 	let fnt_data = format!(
 		"info face=\"{font_name}\" size={font_size} bold=0 italic=0 \
-		charset=\"\" unicode=1 stretchH=100 smooth=1 aa=1 padding=0,0,0,0 spacing=1,1\n\
+		charset=\"\" unicode=1 stretchH=100 smooth=1 aa=1 \
+		padding={pad_top},{pad_right},{pad_bottom},{pad_left} spacing=1,1\n\
 		common lineHeight={common_line_height} base={font_base} \
 		scaleW={scale_w} scaleH={scale_h} pages=1 packed=0\n\
 		page id=0 file=\"{sprite_file_name}.png\"\n\
@@ -254,6 +425,10 @@ fn initialize_font_bundle(
 		{all_kernings}\n",
 		font_name = font.path.file_name().unwrap().to_str().unwrap(),
 		font_size = scaled_size,
+		pad_top = padding.top,
+		pad_right = padding.right,
+		pad_bottom = padding.bottom,
+		pad_left = padding.left,
 		common_line_height = line_metrics.new_line_size,
 		font_base = (-line_metrics.descent + line_metrics.line_gap) as i32,
 		scale_w = packer.width(),
@@ -264,9 +439,144 @@ fn initialize_font_bundle(
 		kerning_count = all_kerning_pairs.len(),
 		all_kernings = all_kerning_pairs.join("\n"),
 	);
-	fs::write(&bundle.fnt, fnt_data).nice_unwrap("Unable to write font .fnt file");
+	fs::write(&bundle.fnt, fnt_data).with_path(&bundle.fnt)?;
+
+	Ok(PathBuf::from(font.name.to_owned() + ".png"))
+}
+
+/// Build a font bundle out of a prebuilt BMFont .fnt file. The source atlas is
+/// treated as the UHD tier and every glyph is downscaled for the other tiers
+fn import_font_bundle(
+	bundle: &FontBundle,
+	font: &BitmapFont,
+	factor: u32,
+) -> Result<PathBuf, Error> {
+	let fnt = FntFile::load(&font.path).map_err(Error::Resource)?;
+	let pages: Vec<RgbaImage> = fnt
+		.page_paths(&font.path)
+		.iter()
+		.map(|p| spritesheet::read_to_image(p))
+		.collect::<Result<_, _>>()?;
+
+	// Only keep the requested characters if a charset was explicitly given
+	let charset = font
+		.charset
+		.as_ref()
+		.map(|_| font_charset(font))
+		.transpose()?;
+	let chars: Vec<(char, &FntChar)> = fnt
+		.chars
+		.iter()
+		.filter_map(|c| char::from_u32(c.id).map(|ch| (ch, c)))
+		.filter(|(ch, _)| charset.as_ref().is_none_or(|set| set.contains(ch)))
+		.collect();
+
+	let scale = |v: i32| (v as f32 / factor as f32).round() as i32;
+
+	// Cut every glyph out of its page and downscale it on its own so
+	// neighbouring glyphs can't bleed into each other
+	let rasterized_chars: Vec<_> = chars
+		.iter()
+		.filter(|(_, c)| c.width > 0 && c.height > 0)
+		.map(|(id, c)| {
+			let page = pages.get(c.page as usize).ok_or_else(|| {
+				Error::Resource(format!(
+					"Character {} refers to missing page {}",
+					c.id, c.page
+				))
+			})?;
+			// Pad the glyph so its size divides evenly by the scale factor
+			let mut img = RgbaImage::new(
+				c.width.div_ceil(factor) * factor,
+				c.height.div_ceil(factor) * factor,
+			);
+			imageops::replace(
+				&mut img,
+				&*imageops::crop_imm(page, c.x, c.y, c.width, c.height),
+				0,
+				0,
+			);
+			spritesheet::downscale(&mut img, factor);
+			Ok(RenderedChar { id: *id, img })
+		})
+		.collect::<Result<_, Error>>()?;
+
+	let packer = pack_chars(font, &rasterized_chars)?;
+
+	// Create .png file
+	write_packed_png(&packer, bundle)?;
+
+	let frames = packer.get_frames();
+	let mut all_chars = chars
+		.iter()
+		.map(|(id, c)| {
+			let (x, y, w, h) = frames
+				.get(id)
+				.map(|f| (f.frame.x, f.frame.y, f.frame.w, f.frame.h))
+				.unwrap_or_default();
+			format!(
+				"char id={} x={} y={} width={} height={} xoffset={} yoffset={} xadvance={} page=0 chnl=0",
+				c.id,
+				x,
+				y,
+				w,
+				h,
+				scale(c.xoffset),
+				scale(c.yoffset),
+				scale(c.xadvance)
+			)
+		})
+		.collect::<Vec<_>>();
+	// Make sure all packings for the same input produce identical output by
+	// sorting
+	all_chars.sort();
+
+	let mut all_kerning_pairs = fnt
+		.kernings
+		.iter()
+		.filter(|k| {
+			chars.iter().any(|(_, c)| c.id == k.first)
+				&& chars.iter().any(|(_, c)| c.id == k.second)
+		})
+		.filter(|k| scale(k.amount) != 0)
+		.map(|k| {
+			format!(
+				"kerning first={} second={} amount={}",
+				k.first,
+				k.second,
+				scale(k.amount)
+			)
+		})
+		.collect::<Vec<_>>();
+	all_kerning_pairs.sort();
+
+	let [pad_top, pad_right, pad_bottom, pad_left] = fnt.padding.map(scale);
+	let fnt_data = format!(
+		"info face=\"{font_name}\" size={font_size} bold=0 italic=0 \
+		charset=\"\" unicode=1 stretchH=100 smooth=1 aa=1 \
+		padding={pad_top},{pad_right},{pad_bottom},{pad_left} spacing=1,1\n\
+		common lineHeight={common_line_height} base={font_base} \
+		scaleW={scale_w} scaleH={scale_h} pages=1 packed=0\n\
+		page id=0 file=\"{sprite_file_name}.png\"\n\
+		chars count={char_count}\n\
+		{all_chars}\n\
+		kernings count={kerning_count}\n\
+		{all_kernings}\n",
+		font_name = fnt.face,
+		font_size = scale(fnt.size),
+		common_line_height = scale(fnt.line_height),
+		font_base = scale(fnt.base),
+		scale_w = packer.width(),
+		scale_h = packer.height(),
+		sprite_file_name = font.name,
+		char_count = all_chars.len(),
+		all_chars = all_chars.join("\n"),
+		kerning_count = all_kerning_pairs.len(),
+		all_kernings = all_kerning_pairs.join("\n"),
+	);
+	fs::write(&bundle.fnt, fnt_data).with_path(&bundle.fnt)?;
 
-	PathBuf::from(font.name.to_owned() + ".png")
+	Ok(PathBuf::from(font.name.to_owned() + ".png"))
 }
 
 pub struct FontBundle {
@@ -322,9 +632,9 @@ fn extract_from_cache(
 	if !shut_up {
 		info!("Extracting '{}' from cache", path_name);
 	}
-	cache_bundle.try_extract_cached_into(
-		path_name,
-		&working_dir.join(path.file_name().unwrap().to_str().unwrap()),
+	cache_bundle.try_extract_cached_into(
+		path_name,
+		&working_dir.join(path.file_name().unwrap().to_str().unwrap()),
 	)
 }
 
@@ -334,7 +644,7 @@ pub fn get_font_bundles(
 	cache: &mut Option<CacheBundle>,
 	mod_info: &ModFileInfo,
 	shut_up: bool,
-) -> FontBundles {
+) -> Result<FontBundles, Error> {
 	// todo: we really should add a global verbosity option and logging levels for that
 
 	if !shut_up {
@@ -360,7 +670,7 @@ pub fn get_font_bundles(
 				}
 
 				done!("Fetched {} from cache", font.name.bright_yellow());
-				return bundles;
+				return Ok(bundles);
 			} else {
 				info!("Failed to extract cached files");
 			}
@@ -372,17 +682,31 @@ pub fn get_font_bundles(
 	}
 	let bundles = FontBundles::new(working_dir.join(font.name.to_string() + ".png"));
 
+	if font.is_prebuilt() {
+		if font.gradient.is_some() || font.shadow.is_some() {
+			warn!(
+				"Effects are not supported for prebuilt font {}, ignoring them",
+				font.name
+			);
+		}
+	} else if font.size == 0 {
+		return Err(Error::Resource(format!(
+			"Font {} is missing a size",
+			font.name
+		)));
+	}
+
 	// Create new font
 
 	info!("Creating normal font");
-	initialize_font_bundle(&bundles.sd, font, 4, mod_info);
+	initialize_font_bundle(&bundles.sd, font, 4, mod_info)?;
 
 	info!("Creating HD font");
-	initialize_font_bundle(&bundles.hd, font, 2, mod_info);
+	initialize_font_bundle(&bundles.hd, font, 2, mod_info)?;
 
 	info!("Creating UHD font");
-	initialize_font_bundle(&bundles.uhd, font, 1, mod_info);
+	initialize_font_bundle(&bundles.uhd, font, 1, mod_info)?;
 
 	done!("Built font {}", font.name.bright_yellow());
-	bundles
+	Ok(bundles)
 }
@@ -5,11 +5,13 @@ use std::fs::File;
 use std::io::Read;
 use std::path::{Path, PathBuf};
 
+use crate::error::{Error, WithPath};
+use crate::fnt::FntFile;
 use crate::mod_file::BitmapFont;
 use crate::spritesheet::SpriteSheet;
-use crate::{warn, NiceUnwrap};
+use crate::warn;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Default, Serialize, Deserialize)]
 pub struct ResourceCache {
 	pub spritesheets: HashMap<String, PathBuf>,
 	pub fonts: HashMap<String, PathBuf>,
@@ -63,19 +65,42 @@ fn hash_sheet(sheet: &SpriteSheet) -> String {
 	let mut hashes: Vec<String> = sheet
 		.files
 		.iter()
-		.map(|x| sha256::try_digest(x).unwrap())
+		// Unreadable files are reported when the sheet is built
+		.map(|x| sha256::try_digest(x).unwrap_or_default())
 		.collect();
 	hashes.sort();
 	sha256::digest(hashes.into_iter().collect::<String>())
 }
 
+/// Version of the generated font files, bumped whenever they change so that
+/// fonts cached by older versions get built again
+const FONT_OUTPUT_VERSION: u32 = 2;
+
 fn hash_font(font: &BitmapFont) -> String {
+	// Prebuilt fonts also depend on the atlas pages next to the .fnt
+	let pages = if font.is_prebuilt() {
+		FntFile::load(&font.path)
+			.map(|fnt| {
+				fnt.page_paths(&font.path)
+					.iter()
+					.filter_map(|p| sha256::try_digest(p).ok())
+					.collect::<String>()
+			})
+			.unwrap_or_default()
+	} else {
+		String::new()
+	};
 	sha256::digest(format!(
-		"{}|{}|{}|{}",
+		"{}|{}|{}|{}|{:?}|{:?}|{:?}|{}{}",
+		FONT_OUTPUT_VERSION,
 		font.size,
 		font.outline,
 		font.charset.clone().unwrap_or_default(),
-		sha256::try_digest(font.path.clone()).unwrap()
+		font.color,
+		font.gradient,
+		font.shadow,
+		sha256::try_digest(font.path.clone()).unwrap_or_default(),
+		pages
 	))
 }
 
@@ -83,9 +108,9 @@ pub fn get_cache_bundle_from_dir(path: &Path) -> Option<CacheBundle> {
 	path.join(".geode_cache")
 		.exists()
 		.then(|| {
-			let cache = ResourceCache::load(
-				fs::read_to_string(path.join(".geode_cache")).nice_unwrap("Unable to read cache"),
-			);
+			let cache = fs::read_to_string(path.join(".geode_cache"))
+				.ok()
+				.and_then(ResourceCache::load)?;
 			Some(CacheBundle {
 				cache,
 				src: CacheBundleSource::Directory(path.to_path_buf()),
@@ -96,37 +121,36 @@ pub fn get_cache_bundle_from_dir(path: &Path) -> Option<CacheBundle> {
 
 pub fn get_cache_bundle(path: &Path) -> Option<CacheBundle> {
 	path.exists()
-		.then(|| {
-			match zip::ZipArchive::new(
-				File::open(path).nice_unwrap("Unable to open cached package"),
-			) {
-				Ok(mut archive) => {
-					let cache: ResourceCache = if archive.by_name(".geode_cache").is_ok() {
-						let mut cache_data = String::new();
-						if archive
-							.by_name(".geode_cache")
-							.unwrap()
-							.read_to_string(&mut cache_data)
-							.is_err()
-						{
-							return None;
-						}
-
-						ResourceCache::load(cache_data)
-					} else {
-						ResourceCache::new()
-					};
-
-					Some(CacheBundle {
-						cache,
-						src: CacheBundleSource::Archive(archive),
-					})
-				}
+		.then(|| match zip::ZipArchive::new(File::open(path).ok()?) {
+			Ok(mut archive) => {
+				let cache: ResourceCache = if archive.by_name(".geode_cache").is_ok() {
+					let mut cache_data = String::new();
+					if archive
+						.by_name(".geode_cache")
+						.unwrap()
+						.read_to_string(&mut cache_data)
+						.is_err()
+					{
+						return None;
+					}
+
+					ResourceCache::load(cache_data)?
+				} else {
+					ResourceCache::new()
+				};
 
-				Err(e) => {
-					warn!("Error reading cache from previous build: {}. Disabling cache for this build", e);
-					None
-				}
+				Some(CacheBundle {
+					cache,
+					src: CacheBundleSource::Archive(archive),
+				})
+			}
+
+			Err(e) => {
+				warn!(
+					"Error reading cache from previous build: {}. Disabling cache for this build",
+					e
+				);
+				None
 			}
 		})
 		.flatten()
@@ -134,22 +158,26 @@ pub fn get_cache_bundle(path: &Path) -> Option<CacheBundle> {
 
 impl ResourceCache {
 	pub fn new() -> ResourceCache {
-		ResourceCache {
-			spritesheets: HashMap::new(),
-			fonts: HashMap::new(),
-		}
+		ResourceCache::default()
 	}
 
-	pub fn load(cache_data: String) -> ResourceCache {
-		serde_json::from_str::<ResourceCache>(&cache_data).nice_unwrap("Unable to parse cache file")
+	/// Parse a cache file, ignoring it if it's malformed
+	pub fn load(cache_data: String) -> Option<ResourceCache> {
+		match serde_json::from_str::<ResourceCache>(&cache_data) {
+			Ok(cache) => Some(cache),
+			Err(e) => {
+				warn!(
+					"Unable to parse cache file: {}. Disabling cache for this build",
+					e
+				);
+				None
+			}
+		}
 	}
 
-	pub fn save(&self, path: &Path) {
-		std::fs::write(
-			path.join(".geode_cache"),
-			serde_json::to_string(self).unwrap(),
-		)
-		.unwrap()
+	pub fn save(&self, path: &Path) -> Result<(), Error> {
+		let path = path.join(".geode_cache");
+		std::fs::write(&path, serde_json::to_string(self).unwrap()).with_path(&path)
 	}
 
 	pub fn add_sheet(&mut self, sheet: &SpriteSheet, path: PathBuf) {
@@ -4,6 +4,8 @@ use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
 
 use crate::{done, fail, fatal, warn, NiceUnwrap};
 
@@ -31,6 +33,9 @@ pub struct Config {
 	pub index_token: Option<String>,
 	#[serde(default = "default_index_url")]
 	pub index_url: String,
+	/// Never access the network, see [`is_offline`]
+	#[serde(default)]
+	pub offline: bool,
 	#[serde(flatten)]
 	other: HashMap<String, Value>,
 }
@@ -39,6 +44,31 @@ fn default_index_url() -> String {
 	"https://api.geode-sdk.org".to_string()
 }
 
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Turn on offline mode for the rest of the process, like `--offline` does
+pub fn set_offline(offline: bool) {
+	OFFLINE.store(offline, Ordering::Relaxed);
+}
+
+/// Whether the network is off limits, either because of `--offline` or the
+/// `offline` setting. Anything that would access the network should fail
+/// or fall back to local caches and installed mods instead
+pub fn is_offline() -> bool {
+	static SETTING: OnceLock<bool> = OnceLock::new();
+	OFFLINE.load(Ordering::Relaxed) || *SETTING.get_or_init(|| Config::new().offline)
+}
+
+/// Exit with an error saying what can't be done without the network
+pub fn require_online(what: &str) {
+	if is_offline() {
+		fatal!(
+			"{what} requires network access, which is disabled in offline mode \
+			(--offline or the 'offline' setting)"
+		);
+	}
+}
+
 pub fn profile_platform_default() -> String {
 	if cfg!(target_os = "windows") {
 		"win".to_owned()
@@ -201,9 +231,13 @@ impl Config {
 			other: HashMap::<String, Value>::new(),
 			index_token: None,
 			index_url: "https://api.geode-sdk.org".to_string(),
+			offline: false,
 		}
 	}
 
+	// Loading the config reads and migrates files on disk, which a Default
+	// impl shouldn't do
+	#[allow(clippy::new_without_default)]
 	pub fn new() -> Config {
 		if !geode_root().exists() {
 			return Config::default_fallback();
@@ -0,0 +1,164 @@
+use std::fs::{self, File, TryLockError};
+use std::path::{Path, PathBuf};
+
+use semver::{Version, VersionReq};
+
+use crate::info;
+use crate::mod_file::PlatformName;
+
+/// Cache of downloaded dependencies, laid out as
+/// `{id}/{version}/{platform}/{id}.geode`. Every package has its SHA-256
+/// hash from the index next to it in `{id}.geode.sha256`, so packages
+/// that were only partially written or tampered with are never used
+pub struct DependencyCache {
+	root: PathBuf,
+	/// Held for as long as the cache is open, so concurrent `geode project
+	/// check` runs don't write the same packages at once
+	_lock: File,
+}
+
+/// Where the cache is, if the system has a cache directory at all
+pub fn cache_root() -> Option<PathBuf> {
+	Some(dirs::cache_dir()?.join("geode").join("dependencies"))
+}
+
+fn hash_path(package: &Path) -> PathBuf {
+	let mut name = package.as_os_str().to_owned();
+	name.push(".sha256");
+	PathBuf::from(name)
+}
+
+/// Whether a cached package matches the hash it was stored with
+fn is_intact(package: &Path) -> bool {
+	let Ok(expected) = fs::read_to_string(hash_path(package)) else {
+		return false;
+	};
+	sha256::try_digest(package).is_ok_and(|hash| hash == expected.trim())
+}
+
+/// Cached packages of a mod for a platform that are intact, along with
+/// their versions
+fn cached_versions(
+	root: &Path,
+	id: &str,
+	platform: Option<PlatformName>,
+) -> Vec<(Version, PathBuf)> {
+	let Ok(versions) = fs::read_dir(root.join(id)) else {
+		return Vec::new();
+	};
+	let mut found = Vec::new();
+	for dir in versions.flatten() {
+		let Some(version) = dir
+			.file_name()
+			.to_str()
+			.and_then(|v| Version::parse(v).ok())
+		else {
+			continue;
+		};
+		let platforms: Vec<PathBuf> = match platform {
+			Some(platform) => vec![dir.path().join(platform.to_string())],
+			None => fs::read_dir(dir.path())
+				.into_iter()
+				.flatten()
+				.flatten()
+				.map(|p| p.path())
+				.collect(),
+		};
+		for platform_dir in platforms {
+			let package = platform_dir.join(format!("{id}.geode"));
+			if is_intact(&package) {
+				found.push((version.clone(), package));
+				break;
+			}
+		}
+	}
+	found.sort_by(|a, b| b.0.cmp(&a.0));
+	found
+}
+
+/// Find the newest cached package of a mod that matches a version
+/// requirement on any platform, without locking the cache
+pub fn find_cached(id: &str, version: &VersionReq) -> Option<PathBuf> {
+	cached_versions(&cache_root()?, id, None)
+		.into_iter()
+		.find(|(v, _)| version.matches(v))
+		.map(|(_, path)| path)
+}
+
+impl DependencyCache {
+	/// Open the cache, waiting for any other process that has it open
+	pub fn open() -> Result<DependencyCache, String> {
+		let root = cache_root().ok_or("Unable to find a cache directory on this system")?;
+		fs::create_dir_all(&root)
+			.map_err(|e| format!("Unable to create {}: {e}", root.display()))?;
+
+		let lock_path = root.join(".lock");
+		let lock = File::create(&lock_path)
+			.map_err(|e| format!("Unable to create {}: {e}", lock_path.display()))?;
+		match lock.try_lock() {
+			Ok(()) => {}
+			Err(TryLockError::WouldBlock) => {
+				info!("Waiting for another process to finish using the dependency cache");
+				lock.lock()
+					.map_err(|e| format!("Unable to lock the dependency cache: {e}"))?;
+			}
+			Err(TryLockError::Error(e)) => {
+				return Err(format!("Unable to lock the dependency cache: {e}"));
+			}
+		}
+
+		Ok(DependencyCache { root, _lock: lock })
+	}
+
+	/// Where the package of a mod version for a platform is cached
+	pub fn package_path(&self, id: &str, version: &str, platform: PlatformName) -> PathBuf {
+		self.root
+			.join(id)
+			.join(version.trim_start_matches('v'))
+			.join(platform.to_string())
+			.join(format!("{id}.geode"))
+	}
+
+	/// The cached package of a mod version, if it's there and has the
+	/// expected hash
+	pub fn get(
+		&self,
+		id: &str,
+		version: &str,
+		platform: PlatformName,
+		hash: &str,
+	) -> Option<PathBuf> {
+		let package = self.package_path(id, version, platform);
+		(is_intact(&package)
+			&& fs::read_to_string(hash_path(&package)).is_ok_and(|h| h.trim() == hash))
+		.then_some(package)
+	}
+
+	/// Cached versions of a mod for a platform, newest first
+	pub fn versions(&self, id: &str, platform: PlatformName) -> Vec<(Version, PathBuf)> {
+		cached_versions(&self.root, id, Some(platform))
+	}
+
+	/// Store a downloaded package whose hash has already been checked
+	pub fn store(
+		&self,
+		id: &str,
+		version: &str,
+		platform: PlatformName,
+		data: &[u8],
+		hash: &str,
+	) -> Result<PathBuf, String> {
+		let package = self.package_path(id, version, platform);
+		let dir = package.parent().unwrap();
+		fs::create_dir_all(dir).map_err(|e| format!("Unable to create {}: {e}", dir.display()))?;
+
+		// The hash is written last, so an interrupted write leaves behind a
+		// package that's simply not used
+		let _ = fs::remove_file(hash_path(&package));
+		fs::write(&package, data)
+			.map_err(|e| format!("Unable to write {}: {e}", package.display()))?;
+		fs::write(hash_path(&package), hash)
+			.map_err(|e| format!("Unable to write {}: {e}", package.display()))?;
+		Ok(package)
+	}
+}
@@ -0,0 +1,94 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+/// Error returned by the library API of the CLI
+#[derive(Debug)]
+pub enum Error {
+	/// Reading or writing a file failed
+	Io {
+		path: PathBuf,
+		source: std::io::Error,
+	},
+	/// mod.json is missing, malformed or doesn't describe a valid mod
+	ModJson(String),
+	/// An image couldn't be read, decoded or written
+	Image {
+		path: PathBuf,
+		source: image::ImageError,
+	},
+	/// A .geode package couldn't be read or written
+	Zip(zip::result::ZipError),
+	/// A request couldn't be sent, or its response couldn't be read
+	Network(reqwest::Error),
+	/// The index responded with an error or something unexpected
+	Index(String),
+	/// Something that requires network access was attempted in offline mode
+	Offline(String),
+	/// Resources like fonts or spritesheets couldn't be generated
+	Resource(String),
+}
+
+impl Display for Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Error::Io { path, source } => write!(f, "{}: {source}", path.display()),
+			Error::ModJson(e) => write!(f, "Invalid mod.json: {e}"),
+			Error::Image { path, source } => write!(f, "{}: {source}", path.display()),
+			Error::Zip(e) => write!(f, "Invalid package: {e}"),
+			Error::Network(e) => write!(f, "Request failed: {e}"),
+			Error::Index(e) => write!(f, "Index error: {e}"),
+			Error::Offline(what) => write!(
+				f,
+				"{what} requires network access, which is disabled in offline mode"
+			),
+			Error::Resource(e) => f.write_str(e),
+		}
+	}
+}
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Error::Io { source, .. } => Some(source),
+			Error::Image { source, .. } => Some(source),
+			Error::Zip(e) => Some(e),
+			Error::Network(e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+impl From<zip::result::ZipError> for Error {
+	fn from(e: zip::result::ZipError) -> Self {
+		Error::Zip(e)
+	}
+}
+
+impl From<reqwest::Error> for Error {
+	fn from(e: reqwest::Error) -> Self {
+		Error::Network(e)
+	}
+}
+
+/// Attach the path being worked on to IO and image errors
+pub trait WithPath<T> {
+	fn with_path(self, path: &Path) -> Result<T, Error>;
+}
+
+impl<T> WithPath<T> for std::io::Result<T> {
+	fn with_path(self, path: &Path) -> Result<T, Error> {
+		self.map_err(|source| Error::Io {
+			path: path.to_path_buf(),
+			source,
+		})
+	}
+}
+
+impl<T> WithPath<T> for image::ImageResult<T> {
+	fn with_path(self, path: &Path) -> Result<T, Error> {
+		self.map_err(|source| Error::Image {
+			path: path.to_path_buf(),
+			source,
+		})
+	}
+}
@@ -0,0 +1,502 @@
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Contents of an AngelCode BMFont descriptor (.fnt) file
+pub struct FntFile {
+	pub face: String,
+	pub size: i32,
+	pub line_height: i32,
+	pub base: i32,
+	pub scale_w: u32,
+	pub scale_h: u32,
+	pub padding: [i32; 4],
+	pub pages: Vec<String>,
+	pub chars: Vec<FntChar>,
+	pub kernings: Vec<FntKerning>,
+}
+
+#[derive(Clone)]
+pub struct FntChar {
+	pub id: u32,
+	pub x: u32,
+	pub y: u32,
+	pub width: u32,
+	pub height: u32,
+	pub xoffset: i32,
+	pub yoffset: i32,
+	pub xadvance: i32,
+	pub page: u32,
+}
+
+#[derive(Clone)]
+pub struct FntKerning {
+	pub first: u32,
+	pub second: u32,
+	pub amount: i32,
+}
+
+impl FntFile {
+	fn empty() -> Self {
+		FntFile {
+			face: String::new(),
+			size: 0,
+			line_height: 0,
+			base: 0,
+			scale_w: 0,
+			scale_h: 0,
+			padding: [0; 4],
+			pages: Vec::new(),
+			chars: Vec::new(),
+			kernings: Vec::new(),
+		}
+	}
+
+	/// Read a .fnt file in either the text, XML or binary format
+	pub fn load(path: &Path) -> Result<FntFile, String> {
+		let data = fs::read(path).map_err(|e| format!("Unable to read {}: {e}", path.display()))?;
+		Self::parse(&data).map_err(|e| format!("Unable to parse {}: {e}", path.display()))
+	}
+
+	pub fn parse(data: &[u8]) -> Result<FntFile, String> {
+		if data.starts_with(b"BMF") {
+			return Self::parse_binary(data);
+		}
+		let text = std::str::from_utf8(data).map_err(|_| "File is not valid UTF-8")?;
+		let text = text.trim_start_matches('\u{feff}').trim_start();
+		if text.starts_with('<') {
+			Self::parse_xml(text)
+		} else {
+			Self::parse_text(text)
+		}
+	}
+
+	/// Paths of the atlas pages, relative to the directory of the .fnt file.
+	/// Like cocos2d, pages of -hd and -uhd fonts are looked up with the same
+	/// suffix first, since generated fonts reference the plain page name
+	pub fn page_paths(&self, fnt_path: &Path) -> Vec<PathBuf> {
+		let dir = fnt_path.parent().unwrap_or(Path::new(""));
+		let stem = fnt_path.file_stem().unwrap_or_default().to_string_lossy();
+		let suffix = ["-uhd", "-hd"].into_iter().find(|s| stem.ends_with(s));
+
+		self.pages
+			.iter()
+			.map(|page| {
+				let path = dir.join(page);
+				let Some(suffix) = suffix else {
+					return path;
+				};
+				let page_stem = path.file_stem().unwrap_or_default().to_string_lossy();
+				if page_stem.ends_with(suffix) {
+					return path;
+				}
+				let suffixed = path.with_file_name(format!(
+					"{page_stem}{suffix}.{}",
+					path.extension().unwrap_or_default().to_string_lossy()
+				));
+				if suffixed.exists() {
+					suffixed
+				} else {
+					path
+				}
+			})
+			.collect()
+	}
+
+	pub fn get_char(&self, id: u32) -> Option<&FntChar> {
+		self.chars.iter().find(|c| c.id == id)
+	}
+
+	/// Kerning amount between two characters, or 0 if the pair has none
+	pub fn kerning(&self, first: u32, second: u32) -> i32 {
+		self.kernings
+			.iter()
+			.find(|k| k.first == first && k.second == second)
+			.map(|k| k.amount)
+			.unwrap_or(0)
+	}
+
+	fn parse_text(text: &str) -> Result<FntFile, String> {
+		let attr_regex = Regex::new(r#"(\w+)=("[^"]*"|\S*)"#).unwrap();
+
+		let mut tags = Vec::new();
+		for line in text.lines() {
+			let line = line.trim();
+			let Some(tag) = line.split_whitespace().next() else {
+				continue;
+			};
+			tags.push((tag.to_string(), parse_attrs(&attr_regex, line)));
+		}
+		Self::from_tags(tags)
+	}
+
+	fn parse_xml(text: &str) -> Result<FntFile, String> {
+		let tag_regex = Regex::new(r"<(info|common|page|char|kerning)\s([^>]*?)/?>").unwrap();
+		let attr_regex = Regex::new(r#"(\w+)\s*=\s*("[^"]*"|'[^']*')"#).unwrap();
+
+		let tags = tag_regex
+			.captures_iter(text)
+			.map(|c| (c[1].to_string(), parse_attrs(&attr_regex, &c[2])))
+			.collect();
+		Self::from_tags(tags)
+	}
+
+	fn from_tags(tags: Vec<(String, HashMap<String, String>)>) -> Result<FntFile, String> {
+		let mut fnt = FntFile::empty();
+		let mut pages = Vec::new();
+
+		for (tag, attrs) in tags {
+			// Some generators (including older versions of this CLI) write
+			// fractional metrics, so round those instead of failing
+			let int = |key: &str| -> Result<i32, String> {
+				attrs
+					.get(key)
+					.map(|v| {
+						v.parse::<f64>()
+							.map(|v| v.round() as i32)
+							.map_err(|_| format!("Invalid value '{v}' for {tag}.{key}"))
+					})
+					.unwrap_or(Ok(0))
+			};
+			match tag.as_str() {
+				"info" => {
+					fnt.face = attrs.get("face").cloned().unwrap_or_default();
+					fnt.size = int("size")?.abs();
+					if let Some(padding) = attrs.get("padding") {
+						for (i, v) in padding.split(',').take(4).enumerate() {
+							fnt.padding[i] = v.trim().parse().unwrap_or(0);
+						}
+					}
+				}
+				"common" => {
+					fnt.line_height = int("lineHeight")?;
+					fnt.base = int("base")?;
+					fnt.scale_w = int("scaleW")? as u32;
+					fnt.scale_h = int("scaleH")? as u32;
+				}
+				"page" => {
+					let file = attrs
+						.get("file")
+						.ok_or("Page is missing a file name")?
+						.clone();
+					pages.push((int("id")?, file));
+				}
+				"char" => fnt.chars.push(FntChar {
+					id: int("id")? as u32,
+					x: int("x")? as u32,
+					y: int("y")? as u32,
+					width: int("width")? as u32,
+					height: int("height")? as u32,
+					xoffset: int("xoffset")?,
+					yoffset: int("yoffset")?,
+					xadvance: int("xadvance")?,
+					page: int("page")? as u32,
+				}),
+				"kerning" => fnt.kernings.push(FntKerning {
+					first: int("first")? as u32,
+					second: int("second")? as u32,
+					amount: int("amount")?,
+				}),
+				_ => {}
+			}
+		}
+
+		pages.sort_by_key(|(id, _)| *id);
+		fnt.pages = pages.into_iter().map(|(_, file)| file).collect();
+
+		if fnt.pages.is_empty() {
+			return Err("Font has no pages".into());
+		}
+		Ok(fnt)
+	}
+
+	fn parse_binary(data: &[u8]) -> Result<FntFile, String> {
+		let mut reader = BinaryReader { data, pos: 3 };
+		let version = reader.u8()?;
+		if version != 3 {
+			return Err(format!("Unsupported binary .fnt version {version}"));
+		}
+
+		let mut fnt = FntFile::empty();
+		while reader.pos < data.len() {
+			let block_type = reader.u8()?;
+			let block_size = reader.u32()? as usize;
+			let block_end = reader.pos + block_size;
+			if block_end > data.len() {
+				return Err("Unexpected end of file".into());
+			}
+			match block_type {
+				1 => {
+					fnt.size = (reader.u16()? as i16 as i32).abs();
+					// bitField, charSet, stretchH, aa
+					reader.skip(5)?;
+					for p in &mut fnt.padding {
+						*p = reader.u8()? as i32;
+					}
+					// spacing, outline
+					reader.skip(3)?;
+					fnt.face = reader.cstr(block_end)?;
+				}
+				2 => {
+					fnt.line_height = reader.u16()? as i32;
+					fnt.base = reader.u16()? as i32;
+					fnt.scale_w = reader.u16()? as u32;
+					fnt.scale_h = reader.u16()? as u32;
+				}
+				3 => {
+					while reader.pos < block_end {
+						fnt.pages.push(reader.cstr(block_end)?);
+					}
+				}
+				4 => {
+					while reader.pos + 20 <= block_end {
+						fnt.chars.push(FntChar {
+							id: reader.u32()?,
+							x: reader.u16()? as u32,
+							y: reader.u16()? as u32,
+							width: reader.u16()? as u32,
+							height: reader.u16()? as u32,
+							xoffset: reader.u16()? as i16 as i32,
+							yoffset: reader.u16()? as i16 as i32,
+							xadvance: reader.u16()? as i16 as i32,
+							page: reader.u8()? as u32,
+						});
+						// chnl
+						reader.skip(1)?;
+					}
+				}
+				5 => {
+					while reader.pos + 10 <= block_end {
+						fnt.kernings.push(FntKerning {
+							first: reader.u32()?,
+							second: reader.u32()?,
+							amount: reader.u16()? as i16 as i32,
+						});
+					}
+				}
+				_ => {}
+			}
+			reader.pos = block_end;
+		}
+
+		if fnt.pages.is_empty() {
+			return Err("Font has no pages".into());
+		}
+		Ok(fnt)
+	}
+}
+
+fn parse_attrs(regex: &Regex, text: &str) -> HashMap<String, String> {
+	regex
+		.captures_iter(text)
+		.map(|c| {
+			let value = c[2].trim_matches(|c| c == '"' || c == '\'').to_string();
+			(c[1].to_string(), value)
+		})
+		.collect()
+}
+
+struct BinaryReader<'a> {
+	data: &'a [u8],
+	pos: usize,
+}
+
+impl BinaryReader<'_> {
+	fn take(&mut self, len: usize) -> Result<&[u8], String> {
+		let bytes = self
+			.data
+			.get(self.pos..self.pos + len)
+			.ok_or("Unexpected end of file")?;
+		self.pos += len;
+		Ok(bytes)
+	}
+
+	fn skip(&mut self, len: usize) -> Result<(), String> {
+		self.take(len).map(|_| ())
+	}
+
+	fn u8(&mut self) -> Result<u8, String> {
+		Ok(self.take(1)?[0])
+	}
+
+	fn u16(&mut self) -> Result<u16, String> {
+		Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+	}
+
+	fn u32(&mut self) -> Result<u32, String> {
+		Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+	}
+
+	fn cstr(&mut self, end: usize) -> Result<String, String> {
+		let rest = self
+			.data
+			.get(self.pos..end)
+			.ok_or("Unexpected end of file")?;
+		let len = rest.iter().position(|b| *b == 0).unwrap_or(rest.len());
+		let str = String::from_utf8_lossy(&rest[..len]).to_string();
+		self.pos += (len + 1).min(rest.len());
+		Ok(str)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn check_sample(fnt: &FntFile) {
+		assert_eq!(fnt.face, "Pusab");
+		assert_eq!(fnt.size, 32);
+		assert_eq!(fnt.padding, [1, 2, 3, 4]);
+		assert_eq!((fnt.line_height, fnt.base), (36, 29));
+		assert_eq!((fnt.scale_w, fnt.scale_h), (256, 128));
+		assert_eq!(fnt.pages, ["font_0.png", "font_1.png"]);
+
+		assert_eq!(fnt.chars.len(), 1);
+		let a = &fnt.chars[0];
+		assert_eq!((a.id, a.x, a.y, a.width, a.height), (65, 1, 2, 20, 24));
+		assert_eq!((a.xoffset, a.yoffset, a.xadvance, a.page), (-1, 4, 18, 1));
+
+		assert_eq!(fnt.kernings.len(), 1);
+		let kerning = &fnt.kernings[0];
+		assert_eq!(
+			(kerning.first, kerning.second, kerning.amount),
+			(65, 86, -2)
+		);
+	}
+
+	#[test]
+	fn parses_text() {
+		let text = concat!(
+			"info face=\"Pusab\" size=-32 bold=0 padding=1,2,3,4 spacing=1,1\n",
+			"common lineHeight=36 base=28.6 scaleW=256 scaleH=128 pages=2\n",
+			"page id=1 file=\"font_1.png\"\n",
+			"page id=0 file=\"font_0.png\"\n",
+			"\n",
+			"chars count=1\n",
+			"char id=65   x=1    y=2    width=20   height=24   xoffset=-1   yoffset=4    xadvance=18   page=1  chnl=15\n",
+			"kernings count=1\n",
+			"kerning first=65 second=86 amount=-2\n",
+		);
+		check_sample(&FntFile::parse(text.as_bytes()).unwrap());
+	}
+
+	#[test]
+	fn parses_xml() {
+		let xml = r#"<?xml version="1.0"?>
+<font>
+	<info face="Pusab" size="-32" padding="1,2,3,4"/>
+	<common lineHeight="36" base="28.6" scaleW='256' scaleH='128' pages="2"/>
+	<pages>
+		<page id="0" file="font_0.png" />
+		<page id="1" file="font_1.png" />
+	</pages>
+	<chars count="1">
+		<char id="65" x="1" y="2" width="20" height="24" xoffset="-1" yoffset="4" xadvance="18" page="1" chnl="15" />
+	</chars>
+	<kernings count="1">
+		<kerning first="65" second="86" amount="-2" />
+	</kernings>
+</font>
+"#;
+		// Some generators write a byte order mark
+		let xml = format!("\u{feff}{xml}");
+		check_sample(&FntFile::parse(xml.as_bytes()).unwrap());
+	}
+
+	#[test]
+	fn parses_binary() {
+		fn block(data: &mut Vec<u8>, kind: u8, payload: &[u8]) {
+			data.push(kind);
+			data.extend((payload.len() as u32).to_le_bytes());
+			data.extend(payload);
+		}
+
+		let mut data = b"BMF\x03".to_vec();
+		let mut info = (-32i16).to_le_bytes().to_vec();
+		info.extend([0, 0, 100, 0, 1, 1, 2, 3, 4, 1, 1, 0]);
+		info.extend(b"Pusab\0");
+		block(&mut data, 1, &info);
+
+		let mut common = Vec::new();
+		for v in [36u16, 29, 256, 128, 2] {
+			common.extend(v.to_le_bytes());
+		}
+		common.extend([0, 0, 0, 0, 0]);
+		block(&mut data, 2, &common);
+
+		block(&mut data, 3, b"font_0.png\0font_1.png\0");
+
+		let mut chars = 65u32.to_le_bytes().to_vec();
+		for v in [1i16, 2, 20, 24, -1, 4, 18] {
+			chars.extend(v.to_le_bytes());
+		}
+		chars.extend([1, 15]);
+		block(&mut data, 4, &chars);
+
+		let mut kernings = 65u32.to_le_bytes().to_vec();
+		kernings.extend(86u32.to_le_bytes());
+		kernings.extend((-2i16).to_le_bytes());
+		block(&mut data, 5, &kernings);
+
+		check_sample(&FntFile::parse(&data).unwrap());
+
+		// Cut off in the middle of the kerning block
+		assert!(FntFile::parse(&data[..data.len() - 3]).is_err());
+		assert!(FntFile::parse(b"BMF\x02").is_err());
+	}
+
+	#[test]
+	fn rejects_invalid_fonts() {
+		assert!(FntFile::parse(b"info face=\"Pusab\" size=32\n").is_err());
+		assert!(FntFile::parse(b"page id=0 file=\"a.png\"\nchar id=x\n").is_err());
+		assert!(FntFile::parse(b"page id=0\n").is_err());
+		assert!(FntFile::parse(b"\xff\xfe").is_err());
+	}
+
+	#[test]
+	fn page_paths_are_relative_to_the_fnt() {
+		let fnt =
+			FntFile::parse(b"page id=0 file=\"font_0.png\"\npage id=1 file=\"sub/font_1.png\"\n")
+				.unwrap();
+		assert_eq!(
+			fnt.page_paths(Path::new("fonts/font.fnt")),
+			[
+				PathBuf::from("fonts/font_0.png"),
+				PathBuf::from("fonts/sub/font_1.png")
+			]
+		);
+	}
+
+	#[test]
+	fn looks_up_chars_and_kerning() {
+		let fnt = FntFile::parse(
+			b"page id=0 file=\"a.png\"\nchar id=65 x=1\nkerning first=65 second=86 amount=-2\n",
+		)
+		.unwrap();
+		assert_eq!(fnt.get_char(65).map(|c| c.x), Some(1));
+		assert!(fnt.get_char(66).is_none());
+		assert_eq!(fnt.kerning(65, 86), -2);
+		assert_eq!(fnt.kerning(86, 65), 0);
+	}
+
+	#[test]
+	fn page_paths_prefer_matching_quality() {
+		let dir = std::env::temp_dir().join(format!("geode-fnt-test-{}", std::process::id()));
+		fs::create_dir_all(&dir).unwrap();
+		fs::write(dir.join("font_0-hd.png"), []).unwrap();
+
+		let fnt = FntFile::parse(b"page id=0 file=\"font_0.png\"\npage id=1 file=\"font_1.png\"\n")
+			.unwrap();
+		assert_eq!(
+			fnt.page_paths(&dir.join("font-hd.fnt")),
+			[dir.join("font_0-hd.png"), dir.join("font_1.png")]
+		);
+		assert_eq!(
+			fnt.page_paths(&dir.join("font.fnt")),
+			[dir.join("font_0.png"), dir.join("font_1.png")]
+		);
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+}
@@ -0,0 +1,352 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::json_span::{display_path, pointer, JsonSpans, PathSegment, Span};
+
+/// Editor for JSON files like mod.json that changes only the parts of the
+/// text that are edited, so key order, indentation, line endings and the
+/// trailing newline of everything else stay exactly as they were
+pub struct JsonEditor {
+	path: PathBuf,
+	text: String,
+	value: Value,
+	spans: JsonSpans,
+	/// One level of indentation, as used by the file
+	indent: String,
+	newline: &'static str,
+}
+
+fn key_path(path: &[PathSegment], key: &str) -> Vec<PathSegment> {
+	let mut path = path.to_vec();
+	path.push(PathSegment::Key(key.to_string()));
+	path
+}
+
+impl JsonEditor {
+	pub fn open(path: &Path) -> Result<JsonEditor, String> {
+		let text = fs::read_to_string(path)
+			.map_err(|e| format!("Unable to read {}: {e}", path.display()))?;
+		Self::from_text(path, text)
+	}
+
+	/// Open the mod.json of a project
+	pub fn open_mod_json(root_path: &Path) -> Result<JsonEditor, String> {
+		Self::open(&root_path.join("mod.json"))
+	}
+
+	pub fn from_text(path: &Path, text: String) -> Result<JsonEditor, String> {
+		let indent = text
+			.lines()
+			.map(|l| &l[..l.len() - l.trim_start().len()])
+			.find(|ws| !ws.is_empty())
+			.map(|ws| {
+				if ws.starts_with('\t') {
+					"\t".to_string()
+				} else {
+					ws.to_string()
+				}
+			})
+			.unwrap_or("\t".into());
+		let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+
+		let value = serde_json::from_str(&text).map_err(|e| format!("Invalid JSON: {e}"))?;
+		let spans = JsonSpans::parse(&text)?;
+		Ok(JsonEditor {
+			path: path.to_path_buf(),
+			text,
+			value,
+			spans,
+			indent,
+			newline,
+		})
+	}
+
+	pub fn value(&self) -> &Value {
+		&self.value
+	}
+
+	pub fn get(&self, path: &[PathSegment]) -> Option<&Value> {
+		self.value.pointer(&pointer(path))
+	}
+
+	pub fn save(&self) -> Result<(), String> {
+		fs::write(&self.path, &self.text)
+			.map_err(|e| format!("Unable to write {}: {e}", self.path.display()))
+	}
+
+	fn splice(&mut self, start: usize, end: usize, with: &str) -> Result<(), String> {
+		self.text.replace_range(start..end, with);
+		self.value = serde_json::from_str(&self.text)
+			.map_err(|e| format!("Edit produced invalid JSON: {e}"))?;
+		self.spans = JsonSpans::parse(&self.text)?;
+		Ok(())
+	}
+
+	fn span(&self, path: &[PathSegment]) -> Result<Span, String> {
+		self.spans
+			.get(path)
+			.copied()
+			.ok_or_else(|| format!("{} does not exist", display_path(path)))
+	}
+
+	/// Leading whitespace of the line that contains an offset
+	fn line_indent(&self, offset: usize) -> String {
+		let line_start = self.text[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
+		self.text[line_start..]
+			.chars()
+			.take_while(|c| *c == ' ' || *c == '\t')
+			.collect()
+	}
+
+	/// Pretty-print a value with the file's own indentation, for placing on
+	/// a line indented by `base`
+	fn format(&self, value: &Value, base: &str) -> String {
+		let formatter = serde_json::ser::PrettyFormatter::with_indent(self.indent.as_bytes());
+		let mut ser = serde_json::Serializer::with_formatter(Vec::new(), formatter);
+		value.serialize(&mut ser).unwrap();
+		String::from_utf8(ser.into_inner())
+			.unwrap()
+			.replace('\n', &format!("{}{base}", self.newline))
+	}
+
+	/// Add a member to an object or an item to an array, after its last one
+	fn append(
+		&mut self,
+		container: &[PathSegment],
+		key: Option<&str>,
+		value: &Value,
+	) -> Result<(), String> {
+		let span = self.span(container)?;
+		let prefix = key
+			.map(|k| format!("{}: ", serde_json::to_string(k).unwrap()))
+			.unwrap_or_default();
+		let children = self.spans.children(container);
+
+		let Some(last) = children.last() else {
+			// Empty containers are expanded onto multiple lines
+			let base = self.line_indent(span.key_start.unwrap_or(span.start));
+			let inner = format!("{base}{}", self.indent);
+			let text = format!(
+				"{open}{nl}{inner}{prefix}{value}{nl}{base}{close}",
+				open = &self.text[span.start..span.start + 1],
+				close = &self.text[span.end - 1..span.end],
+				nl = self.newline,
+				value = self.format(value, &inner),
+			);
+			return self.splice(span.start, span.end, &text);
+		};
+
+		let first = children[0];
+		let first_start = first.key_start.unwrap_or(first.start);
+		let insert_at = last.end;
+		let text = if self.text[span.start..first_start].contains('\n') {
+			let inner = self.line_indent(first_start);
+			format!(
+				",{}{inner}{prefix}{}",
+				self.newline,
+				self.format(value, &inner)
+			)
+		} else {
+			// Keep containers that are written on one line that way
+			format!(", {prefix}{}", serde_json::to_string(value).unwrap())
+		};
+		self.splice(insert_at, insert_at, &text)
+	}
+
+	/// Set the value at a path, creating it (and any missing parent objects)
+	/// if it doesn't exist yet
+	pub fn set(&mut self, path: &[PathSegment], value: &Value) -> Result<(), String> {
+		if let Some(span) = self.spans.get(path).copied() {
+			let base = self.line_indent(span.key_start.unwrap_or(span.start));
+			let text = self.format(value, &base);
+			return self.splice(span.start, span.end, &text);
+		}
+
+		let Some((last, parent)) = path.split_last() else {
+			unreachable!("the root value always exists");
+		};
+		match (last, self.get(parent)) {
+			(PathSegment::Key(key), Some(Value::Object(_))) => {
+				self.append(parent, Some(key), value)
+			}
+			(PathSegment::Key(key), None) => {
+				let mut wrapped = json!({});
+				wrapped[key] = value.clone();
+				self.set(parent, &wrapped)
+			}
+			(PathSegment::Index(_), Some(Value::Array(_))) => self.append(parent, None, value),
+			_ => Err(format!(
+				"Can't set {}, its parent has the wrong type",
+				display_path(path)
+			)),
+		}
+	}
+
+	/// Remove an object member or array item along with its separator
+	pub fn remove(&mut self, path: &[PathSegment]) -> Result<(), String> {
+		let span = self.span(path)?;
+		let Some((_, parent)) = path.split_last() else {
+			return Err("Can't remove the root value".into());
+		};
+		let parent_span = self.span(parent)?;
+		let siblings: Vec<Span> = self.spans.children(parent).into_iter().copied().collect();
+		let index = siblings.iter().position(|s| s.start == span.start).unwrap();
+
+		if siblings.len() == 1 {
+			self.splice(parent_span.start + 1, parent_span.end - 1, "")
+		} else if index + 1 == siblings.len() {
+			self.splice(siblings[index - 1].end, span.end, "")
+		} else {
+			let next = siblings[index + 1];
+			self.splice(
+				span.key_start.unwrap_or(span.start),
+				next.key_start.unwrap_or(next.start),
+				"",
+			)
+		}
+	}
+
+	/// Make the value at a path equal to `value` with as small of an edit as
+	/// possible: objects are updated member by member, and arrays that only
+	/// grew get the new items appended
+	pub fn update(&mut self, path: &[PathSegment], value: &Value) -> Result<(), String> {
+		let Some(current) = self.get(path).cloned() else {
+			return self.set(path, value);
+		};
+		if current == *value {
+			return Ok(());
+		}
+
+		match (&current, value) {
+			(Value::Object(old), Value::Object(new)) if !old.is_empty() => {
+				for key in old.keys().filter(|k| !new.contains_key(*k)) {
+					self.remove(&key_path(path, key))?;
+				}
+				for (key, member) in new {
+					self.update(&key_path(path, key), member)?;
+				}
+				Ok(())
+			}
+			(Value::Array(old), Value::Array(new))
+				if new.len() > old.len() && new.starts_with(old) =>
+			{
+				for item in &new[old.len()..] {
+					self.append(path, None, item)?;
+				}
+				Ok(())
+			}
+			_ => self.set(path, value),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const MOD_JSON: &str = "{\n\t\"id\": \"a.b\",\n\t\"version\":   \"v1.0.0\",\n\t\"tags\": [\"x\"],\n\t\"dependencies\": {}\n}\n";
+
+	fn key(k: &str) -> PathSegment {
+		PathSegment::Key(k.into())
+	}
+
+	fn editor(text: &str) -> JsonEditor {
+		JsonEditor::from_text(Path::new("mod.json"), text.into()).unwrap()
+	}
+
+	#[test]
+	fn unchanged_values_keep_the_text() {
+		let mut edit = editor(MOD_JSON);
+		let value = edit.value().clone();
+		edit.update(&[], &value).unwrap();
+		assert_eq!(edit.text, MOD_JSON);
+	}
+
+	#[test]
+	fn update_keeps_key_order_and_whitespace() {
+		let mut edit = editor(MOD_JSON);
+		edit.update(
+			&[],
+			&json!({
+				"dependencies": {},
+				"tags": ["x", "y"],
+				"version": "v1.1.0",
+				"id": "a.b",
+			}),
+		)
+		.unwrap();
+		assert_eq!(
+			edit.text,
+			"{\n\t\"id\": \"a.b\",\n\t\"version\":   \"v1.1.0\",\n\t\"tags\": [\"x\", \"y\"],\n\t\"dependencies\": {}\n}\n"
+		);
+	}
+
+	#[test]
+	fn set_appends_with_the_file_indentation() {
+		let mut edit = editor(MOD_JSON);
+		edit.set(&[key("name")], &json!("Mod")).unwrap();
+		edit.set(
+			&[key("dependencies"), key("geode.node-ids")],
+			&json!(">=1.0.0"),
+		)
+		.unwrap();
+		edit.set(&[key("resources"), key("files")], &json!(["a.png"]))
+			.unwrap();
+		assert_eq!(
+			edit.text,
+			concat!(
+				"{\n",
+				"\t\"id\": \"a.b\",\n",
+				"\t\"version\":   \"v1.0.0\",\n",
+				"\t\"tags\": [\"x\"],\n",
+				"\t\"dependencies\": {\n",
+				"\t\t\"geode.node-ids\": \">=1.0.0\"\n",
+				"\t},\n",
+				"\t\"name\": \"Mod\",\n",
+				"\t\"resources\": {\n",
+				"\t\t\"files\": [\n",
+				"\t\t\t\"a.png\"\n",
+				"\t\t]\n",
+				"\t}\n",
+				"}\n",
+			)
+		);
+	}
+
+	#[test]
+	fn set_keeps_crlf_line_endings() {
+		let mut edit = editor("{\r\n  \"id\": \"a.b\"\r\n}\r\n");
+		edit.set(&[key("api")], &json!({ "include": ["*.hpp"] }))
+			.unwrap();
+		assert_eq!(
+			edit.text,
+			"{\r\n  \"id\": \"a.b\",\r\n  \"api\": {\r\n    \"include\": [\r\n      \"*.hpp\"\r\n    ]\r\n  }\r\n}\r\n"
+		);
+	}
+
+	#[test]
+	fn set_rejects_parents_of_the_wrong_type() {
+		let mut edit = editor(MOD_JSON);
+		assert!(edit.set(&[key("id"), key("x")], &json!(1)).is_err());
+		assert_eq!(edit.text, MOD_JSON);
+	}
+
+	#[test]
+	fn remove_takes_the_separator_with_it() {
+		let mut edit = editor(MOD_JSON);
+		edit.remove(&[key("version")]).unwrap();
+		assert_eq!(
+			edit.text,
+			"{\n\t\"id\": \"a.b\",\n\t\"tags\": [\"x\"],\n\t\"dependencies\": {}\n}\n"
+		);
+		edit.remove(&[key("dependencies")]).unwrap();
+		assert_eq!(edit.text, "{\n\t\"id\": \"a.b\",\n\t\"tags\": [\"x\"]\n}\n");
+		edit.remove(&[key("tags"), PathSegment::Index(0)]).unwrap();
+		assert_eq!(edit.text, "{\n\t\"id\": \"a.b\",\n\t\"tags\": []\n}\n");
+		assert!(edit.remove(&[key("missing")]).is_err());
+		assert!(edit.remove(&[]).is_err());
+	}
+}
@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+
+/// A step in the path to a JSON value
+#[derive(Clone, Debug, PartialEq)]
+pub enum PathSegment {
+	Key(String),
+	Index(usize),
+}
+
+/// Format a path the way JSONPath does, i.e. `$.resources.fonts["my-font"].size`
+pub fn display_path(path: &[PathSegment]) -> String {
+	let mut out = String::from("$");
+	for seg in path {
+		match seg {
+			PathSegment::Key(key)
+				if !key.is_empty()
+					&& key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+					&& !key.starts_with(|c: char| c.is_ascii_digit()) =>
+			{
+				out.push('.');
+				out.push_str(key);
+			}
+			PathSegment::Key(key) => out.push_str(&format!("[{key:?}]")),
+			PathSegment::Index(i) => out.push_str(&format!("[{i}]")),
+		}
+	}
+	out
+}
+
+/// Format a path as a JSON pointer (RFC 6901)
+pub fn pointer(path: &[PathSegment]) -> String {
+	path.iter()
+		.map(|seg| match seg {
+			PathSegment::Key(key) => format!("/{}", key.replace('~', "~0").replace('/', "~1")),
+			PathSegment::Index(i) => format!("/{i}"),
+		})
+		.collect()
+}
+
+/// Byte offsets of a value in the source text
+#[derive(Clone, Copy, Debug)]
+pub struct Span {
+	/// Start of the key string (including its quote), if the value is an
+	/// object member
+	pub key_start: Option<usize>,
+	pub start: usize,
+	pub end: usize,
+}
+
+/// Source locations of every value in a JSON document, keyed by JSON pointer
+pub struct JsonSpans {
+	spans: HashMap<String, Span>,
+	children: HashMap<String, Vec<String>>,
+}
+
+impl JsonSpans {
+	pub fn parse(text: &str) -> Result<JsonSpans, String> {
+		let mut scanner = Scanner {
+			text: text.as_bytes(),
+			pos: 0,
+			spans: HashMap::new(),
+			children: HashMap::new(),
+		};
+		scanner.skip_ws();
+		scanner.value(String::new(), None)?;
+		scanner.skip_ws();
+		if scanner.pos != text.len() {
+			return Err(format!("Trailing characters at offset {}", scanner.pos));
+		}
+		Ok(JsonSpans {
+			spans: scanner.spans,
+			children: scanner.children,
+		})
+	}
+
+	pub fn get(&self, path: &[PathSegment]) -> Option<&Span> {
+		self.spans.get(&pointer(path))
+	}
+
+	/// Spans of the members of an object or the items of an array, in the
+	/// order they appear in
+	pub fn children(&self, path: &[PathSegment]) -> Vec<&Span> {
+		self.children
+			.get(&pointer(path))
+			.map(|c| c.iter().filter_map(|p| self.spans.get(p)).collect())
+			.unwrap_or_default()
+	}
+
+	/// Find the span of a path, falling back to its closest existing parent
+	/// (for example when a required key is missing)
+	pub fn closest(&self, path: &[PathSegment]) -> Option<&Span> {
+		(0..=path.len())
+			.rev()
+			.find_map(|len| self.get(&path[..len]))
+	}
+}
+
+/// 1-based line and column of a byte offset
+pub fn line_col(text: &str, offset: usize) -> (usize, usize) {
+	let before = &text[..offset.min(text.len())];
+	let line = before.matches('\n').count() + 1;
+	let col = before
+		.rsplit('\n')
+		.next()
+		.map(|l| l.chars().count())
+		.unwrap_or(0)
+		+ 1;
+	(line, col)
+}
+
+struct Scanner<'a> {
+	text: &'a [u8],
+	pos: usize,
+	spans: HashMap<String, Span>,
+	children: HashMap<String, Vec<String>>,
+}
+
+impl Scanner<'_> {
+	fn skip_ws(&mut self) {
+		while self
+			.text
+			.get(self.pos)
+			.is_some_and(|c| c.is_ascii_whitespace())
+		{
+			self.pos += 1;
+		}
+	}
+
+	fn expect(&mut self, c: u8) -> Result<(), String> {
+		if self.text.get(self.pos) == Some(&c) {
+			self.pos += 1;
+			Ok(())
+		} else {
+			Err(format!("Expected '{}' at offset {}", c as char, self.pos))
+		}
+	}
+
+	fn value(&mut self, pointer: String, key_start: Option<usize>) -> Result<(), String> {
+		let start = self.pos;
+		let mut children = Vec::new();
+		match self.text.get(self.pos) {
+			Some(b'{') => {
+				self.pos += 1;
+				self.skip_ws();
+				if self.text.get(self.pos) == Some(&b'}') {
+					self.pos += 1;
+				} else {
+					loop {
+						self.skip_ws();
+						let key_start = self.pos;
+						let key = self.string()?;
+						self.skip_ws();
+						self.expect(b':')?;
+						self.skip_ws();
+						let child =
+							format!("{pointer}/{}", key.replace('~', "~0").replace('/', "~1"));
+						children.push(child.clone());
+						self.value(child, Some(key_start))?;
+						self.skip_ws();
+						match self.text.get(self.pos) {
+							Some(b',') => self.pos += 1,
+							Some(b'}') => {
+								self.pos += 1;
+								break;
+							}
+							_ => {
+								return Err(format!("Expected ',' or '}}' at offset {}", self.pos))
+							}
+						}
+					}
+				}
+			}
+			Some(b'[') => {
+				self.pos += 1;
+				self.skip_ws();
+				if self.text.get(self.pos) == Some(&b']') {
+					self.pos += 1;
+				} else {
+					let mut index = 0;
+					loop {
+						self.skip_ws();
+						let child = format!("{pointer}/{index}");
+						children.push(child.clone());
+						self.value(child, None)?;
+						index += 1;
+						self.skip_ws();
+						match self.text.get(self.pos) {
+							Some(b',') => self.pos += 1,
+							Some(b']') => {
+								self.pos += 1;
+								break;
+							}
+							_ => return Err(format!("Expected ',' or ']' at offset {}", self.pos)),
+						}
+					}
+				}
+			}
+			Some(b'"') => {
+				self.string()?;
+			}
+			Some(_) => {
+				// Numbers and literals; their exact contents don't matter here
+				while self
+					.text
+					.get(self.pos)
+					.is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, b'-' | b'+' | b'.'))
+				{
+					self.pos += 1;
+				}
+				if self.pos == start {
+					return Err(format!("Unexpected character at offset {}", self.pos));
+				}
+			}
+			None => return Err("Unexpected end of input".into()),
+		}
+		if !children.is_empty() {
+			self.children.insert(pointer.clone(), children);
+		}
+		self.spans.insert(
+			pointer,
+			Span {
+				key_start,
+				start,
+				end: self.pos,
+			},
+		);
+		Ok(())
+	}
+
+	fn string(&mut self) -> Result<String, String> {
+		let start = self.pos;
+		self.expect(b'"')?;
+		loop {
+			match self.text.get(self.pos) {
+				Some(b'"') => {
+					self.pos += 1;
+					break;
+				}
+				Some(b'\\') => self.pos += 2,
+				Some(_) => self.pos += 1,
+				None => return Err("Unterminated string".into()),
+			}
+		}
+		let raw = std::str::from_utf8(&self.text[start..self.pos]).map_err(|e| e.to_string())?;
+		serde_json::from_str(raw).map_err(|e| e.to_string())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn key(k: &str) -> PathSegment {
+		PathSegment::Key(k.into())
+	}
+
+	fn source<'t>(text: &'t str, span: &Span) -> &'t str {
+		&text[span.start..span.end]
+	}
+
+	#[test]
+	fn spans_cover_values_and_keys() {
+		let text = "{\n\t\"id\": \"a.b\",\n\t\"tags\": [1, true, null],\n\t\"x/y\": {\"q\\\"\": -1.5e3}\n}";
+		let spans = JsonSpans::parse(text).unwrap();
+
+		let id = spans.get(&[key("id")]).unwrap();
+		assert_eq!(source(text, id), "\"a.b\"");
+		assert_eq!(&text[id.key_start.unwrap()..id.start], "\"id\": ");
+
+		let tags = spans.get(&[key("tags")]).unwrap();
+		assert_eq!(source(text, tags), "[1, true, null]");
+		let item = spans.get(&[key("tags"), PathSegment::Index(2)]).unwrap();
+		assert_eq!(source(text, item), "null");
+		assert_eq!(item.key_start, None);
+
+		let escaped = spans.get(&[key("x/y"), key("q\"")]).unwrap();
+		assert_eq!(source(text, escaped), "-1.5e3");
+
+		assert_eq!(source(text, spans.get(&[]).unwrap()), text);
+	}
+
+	#[test]
+	fn children_are_in_source_order() {
+		let text = r#"{"b": 1, "a": 2, "c": {}}"#;
+		let spans = JsonSpans::parse(text).unwrap();
+		let children: Vec<_> = spans
+			.children(&[])
+			.into_iter()
+			.map(|s| source(text, s))
+			.collect();
+		assert_eq!(children, ["1", "2", "{}"]);
+		assert!(spans.children(&[key("c")]).is_empty());
+	}
+
+	#[test]
+	fn closest_falls_back_to_parent() {
+		let text = r#"{"resources": {"fonts": {}}}"#;
+		let spans = JsonSpans::parse(text).unwrap();
+		let path = [key("resources"), key("fonts"), key("missing"), key("size")];
+		assert_eq!(source(text, spans.closest(&path).unwrap()), "{}");
+	}
+
+	#[test]
+	fn invalid_json_is_rejected() {
+		assert!(JsonSpans::parse(r#"{"a": 1} x"#).is_err());
+		assert!(JsonSpans::parse(r#"{"a" 1}"#).is_err());
+		assert!(JsonSpans::parse(r#"[1, 2"#).is_err());
+		assert!(JsonSpans::parse(r#"{"a": "unterminated}"#).is_err());
+	}
+
+	#[test]
+	fn paths_are_formatted() {
+		let path = [
+			key("resources"),
+			key("fonts"),
+			key("my-font"),
+			PathSegment::Index(0),
+		];
+		assert_eq!(display_path(&path), r#"$.resources.fonts["my-font"][0]"#);
+		assert_eq!(pointer(&path), "/resources/fonts/my-font/0");
+		assert_eq!(pointer(&[key("a/b~c")]), "/a~1b~0c");
+		assert_eq!(display_path(&[]), "$");
+	}
+
+	#[test]
+	fn line_col_is_one_based() {
+		let text = "{\n\t\"id\": 1\n}";
+		assert_eq!(line_col(text, 0), (1, 1));
+		assert_eq!(line_col(text, text.find("\"id\"").unwrap()), (2, 2));
+		assert_eq!(line_col(text, text.len()), (3, 2));
+	}
+}
@@ -1,5 +1,6 @@
 use std::fmt::Display;
 use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use rustyline::Editor;
 
@@ -7,7 +8,9 @@ use rustyline::Editor;
 macro_rules! info {
     ($x:expr $(, $more:expr)*) => {{
     	use ::colored::Colorize;
-        println!("{}{}", "| Info | ".bright_cyan(), format!($x, $($more),*));
+        if !$crate::logging::is_quiet() {
+            println!("{}{}", "| Info | ".bright_cyan(), format!($x, $($more),*));
+        }
     }}
 }
 
@@ -40,7 +43,9 @@ macro_rules! warn {
 macro_rules! done {
     ($x:expr $(, $more:expr)*) => {{
     	use ::colored::Colorize;
-        println!("{}{}", "| Done | ".bright_green(), format!($x, $($more),*));
+        if !$crate::logging::is_quiet() {
+            println!("{}{}", "| Done | ".bright_green(), format!($x, $($more),*));
+        }
     }}
 }
 
@@ -51,6 +56,18 @@ macro_rules! confirm {
     };
 }
 
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Stop `info!` and `done!` from printing anything, for commands whose
+/// output is meant to be read as-is. Warnings and errors still go to stderr
+pub fn set_quiet(quiet: bool) {
+	QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+	QUIET.load(Ordering::Relaxed)
+}
+
 pub fn clear_terminal() {
 	print!("{esc}c", esc = 27 as char);
 }
@@ -99,6 +116,56 @@ pub fn ask_confirm(text: &str, default: bool) -> bool {
 	}
 }
 
+/// Print rows of cells in aligned columns under bold headers, highlighting
+/// the cells that `highlight` picks by row and column
+pub fn print_table(
+	headers: &[&str],
+	rows: &[Vec<String>],
+	highlight: impl Fn(usize, usize) -> bool,
+) {
+	use colored::Colorize;
+	let widths: Vec<_> = (0..headers.len())
+		.map(|i| {
+			rows.iter()
+				.map(|cells| cells[i].len())
+				.chain([headers[i].len()])
+				.max()
+				.unwrap()
+		})
+		.collect();
+	let line = |cells: Vec<String>| cells.join("  ").trim_end().to_string();
+	println!(
+		"{}",
+		line(
+			headers
+				.iter()
+				.zip(&widths)
+				.map(|(h, w)| format!("{h:w$}").bold().to_string())
+				.collect()
+		)
+	);
+	for (row, cells) in rows.iter().enumerate() {
+		println!(
+			"{}",
+			line(
+				cells
+					.iter()
+					.zip(&widths)
+					.enumerate()
+					.map(|(col, (c, w))| {
+						let cell = format!("{c:w$}");
+						if highlight(row, col) {
+							cell.yellow().to_string()
+						} else {
+							cell
+						}
+					})
+					.collect()
+			)
+		);
+	}
+}
+
 pub trait NiceUnwrap<T> {
 	fn nice_unwrap<S: Display>(self, text: S) -> T;
 }
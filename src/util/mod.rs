@@ -1,8 +1,15 @@
 pub mod bmfont;
 pub mod cache;
 pub mod config;
+pub mod dep_cache;
+pub mod error;
+pub mod fnt;
+pub mod json_edit;
+pub mod json_span;
 pub mod logging;
 pub mod mod_file;
+pub mod mod_schema;
+pub mod mod_settings;
 pub mod spritesheet;
 
 pub use logging::NiceUnwrap;
@@ -1,8 +1,11 @@
+use crate::error::{Error, WithPath};
+use crate::mod_settings::{self, Setting};
 use crate::spritesheet::SpriteSheet;
 use crate::NiceUnwrap;
 use clap::ValueEnum;
+use path_absolutize::Absolutize;
 use semver::{Version, VersionReq};
-use serde::{de::Error, Deserialize, Deserializer};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
 use std::fs;
@@ -10,66 +13,36 @@ use std::io::Read;
 use std::path::{Path, PathBuf};
 use vec1::Vec1;
 
-trait Glob {
-	fn glob(self) -> Self;
-}
-
-impl Glob for Vec<PathBuf> {
-	fn glob(self) -> Self {
-		self.into_iter()
-			.flat_map(|src| {
-				glob::glob(
-					std::env::current_dir()
-						.unwrap()
-						.join(&src)
-						.to_str()
-						.unwrap(),
-				)
-				.nice_unwrap(format!("Invalid glob pattern {}", src.to_str().unwrap()))
-				.map(|g| g.unwrap())
-			})
-			.collect()
+/// Expand glob patterns relative to `root` into the paths they match
+fn expand_globs(root: &Path, patterns: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+	let mut out = Vec::new();
+	for src in patterns {
+		let paths = glob::glob(root.join(src).to_str().unwrap())
+			.map_err(|e| format!("Invalid glob pattern {}: {e}", src.display()))?;
+		for path in paths {
+			out.push(path.map_err(|e| e.to_string())?);
+		}
 	}
-}
-
-fn parse_glob<'de, D>(deserializer: D) -> Result<Vec<PathBuf>, D::Error>
-where
-	D: Deserializer<'de>,
-{
-	Ok(Vec::<PathBuf>::deserialize(deserializer)?.glob())
-}
-
-fn parse_glob_rel<'de, D>(deserializer: D) -> Result<Vec<PathBuf>, D::Error>
-where
-	D: Deserializer<'de>,
-{
-	Ok(Vec::<PathBuf>::deserialize(deserializer)?
-		.glob()
-		.into_iter()
-		.map(|p| {
-			p.strip_prefix(std::env::current_dir().unwrap())
-				.unwrap_or(&p)
-				.to_path_buf()
-		})
-		.collect())
+	Ok(out)
 }
 
 fn parse_spritesheets<'de, D>(deserializer: D) -> Result<HashMap<String, SpriteSheet>, D::Error>
 where
 	D: Deserializer<'de>,
 {
-	Ok(HashMap::<String, Vec<PathBuf>>::deserialize(deserializer)?
+	HashMap::<String, Vec<PathBuf>>::deserialize(deserializer)?
 		.into_iter()
 		.map(|(name, srcs)| {
-			(
+			Ok((
 				name.clone(),
 				SpriteSheet {
 					name,
-					files: srcs.glob(),
+					// Globs are expanded by ModFileInfo::resolve_paths
+					files: srcs,
 				},
-			)
+			))
 		})
-		.collect())
+		.collect()
 }
 
 fn parse_version<'de, D>(deserializer: D) -> Result<Version, D::Error>
@@ -123,7 +96,6 @@ where
 		.into_iter()
 		.map(|(name, mut font)| {
 			font.name.clone_from(&name);
-			font.path = std::env::current_dir().unwrap().join(font.path);
 			(name, font)
 		})
 		.collect())
@@ -183,6 +155,49 @@ impl Color {
 			blue: 255,
 		}
 	}
+
+	pub fn black() -> Self {
+		Self {
+			red: 0,
+			green: 0,
+			blue: 0,
+		}
+	}
+
+	/// Linearly interpolate between two colors, `t` being in range 0..=1
+	pub fn lerp(&self, other: &Color, t: f32) -> Self {
+		let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+		Self {
+			red: mix(self.red, other.red),
+			green: mix(self.green, other.green),
+			blue: mix(self.blue, other.blue),
+		}
+	}
+}
+
+#[derive(Deserialize, PartialEq, Debug)]
+pub struct FontGradient {
+	#[serde(deserialize_with = "parse_color")]
+	pub top: Color,
+	#[serde(deserialize_with = "parse_color")]
+	pub bottom: Color,
+}
+
+fn default_shadow_offset() -> [i32; 2] {
+	[2, 2]
+}
+
+/// Drop shadow rendered behind each glyph. Offset and blur are in UHD pixels
+/// (like `size`) and get scaled down for the HD and SD tiers; positive y
+/// moves the shadow down
+#[derive(Deserialize, PartialEq, Debug)]
+pub struct FontShadow {
+	#[serde(default = "default_shadow_offset")]
+	pub offset: [i32; 2],
+	#[serde(default = "Color::black", deserialize_with = "parse_color")]
+	pub color: Color,
+	#[serde(default)]
+	pub blur: u32,
 }
 
 #[derive(Deserialize, PartialEq)]
@@ -191,25 +206,39 @@ pub struct BitmapFont {
 	pub name: String,
 	pub path: PathBuf,
 	pub charset: Option<String>,
+	/// Not needed for prebuilt .fnt fonts
+	#[serde(default)]
 	pub size: u32,
 	#[serde(default)]
 	pub outline: u32,
 	#[serde(default = "Color::white", deserialize_with = "parse_color")]
 	pub color: Color,
+	pub gradient: Option<FontGradient>,
+	pub shadow: Option<FontShadow>,
+}
+
+impl BitmapFont {
+	/// Whether this font is imported from an existing BMFont .fnt file rather
+	/// than rasterized from a TrueType font
+	pub fn is_prebuilt(&self) -> bool {
+		self.path
+			.extension()
+			.is_some_and(|ext| ext.eq_ignore_ascii_case("fnt"))
+	}
 }
 
 #[derive(Default, Deserialize, PartialEq)]
 pub struct ModResources {
-	#[serde(deserialize_with = "parse_glob", default = "Vec::new")]
+	#[serde(default)]
 	pub libraries: Vec<PathBuf>,
 
-	#[serde(deserialize_with = "parse_glob", default = "Vec::new")]
+	#[serde(default)]
 	pub files: Vec<PathBuf>,
 
 	#[serde(deserialize_with = "parse_spritesheets", default = "HashMap::new")]
 	pub spritesheets: HashMap<String, SpriteSheet>,
 
-	#[serde(deserialize_with = "parse_glob", default = "Vec::new")]
+	#[serde(default)]
 	pub sprites: Vec<PathBuf>,
 
 	#[serde(deserialize_with = "parse_fonts", default = "HashMap::new")]
@@ -287,7 +316,10 @@ fn default_required() -> bool {
 	true
 }
 
-#[derive(Default, Deserialize, PartialEq, Clone)]
+/// Ordered from most to least important
+#[derive(
+	Default, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, ValueEnum,
+)]
 #[serde(rename_all = "camelCase")]
 pub enum DependencyImportance {
 	#[default]
@@ -296,7 +328,7 @@ pub enum DependencyImportance {
 	Suggested,
 }
 
-#[derive(Deserialize, PartialEq)]
+#[derive(Deserialize, PartialEq, Clone)]
 pub struct Dependency {
 	#[serde(skip)]
 	pub id: String,
@@ -309,6 +341,18 @@ pub struct Dependency {
 	pub platforms: HashSet<PlatformName>,
 }
 
+impl Dependency {
+	/// How important the dependency is, falling back to the legacy
+	/// `required` field, where optional meant suggested
+	pub fn importance(&self) -> DependencyImportance {
+		self.importance.unwrap_or(if self.required {
+			DependencyImportance::Required
+		} else {
+			DependencyImportance::Suggested
+		})
+	}
+}
+
 #[derive(Deserialize, PartialEq)]
 pub struct LegacyDependency {
 	pub id: String,
@@ -395,7 +439,7 @@ where
 							Dependency {
 								id: dep.id,
 								version: dep.version,
-								importance: Some(dep.importance.clone()),
+								importance: Some(dep.importance),
 								required: dep.importance == DependencyImportance::Required,
 								platforms: dep.platforms,
 							},
@@ -409,12 +453,134 @@ where
 	}
 }
 
+fn parse_settings<'de, D>(deserializer: D) -> Result<Vec<Setting>, D::Error>
+where
+	D: Deserializer<'de>,
+{
+	// Issues are reported when validating mod.json, so packages with broken
+	// settings can still be used as dependencies
+	Ok(mod_settings::parse_settings(&serde_json::Value::deserialize(deserializer)?).0)
+}
+
+#[derive(Default, Deserialize, PartialEq, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum IncompatibilityImportance {
+	/// Geode refuses to load both mods at once
+	#[default]
+	Breaking,
+	/// Both mods can be loaded, but they might not work well together
+	Conflicting,
+	/// This mod replaces the other one
+	Superseded,
+}
+
+#[derive(Deserialize, PartialEq)]
+pub struct Incompatibility {
+	#[serde(default)]
+	pub id: String,
+	#[serde(deserialize_with = "parse_comparable_version")]
+	pub version: VersionReq,
+	#[serde(default)]
+	pub importance: IncompatibilityImportance,
+	#[serde(default = "all_platforms")]
+	pub platforms: HashSet<PlatformName>,
+}
+
+#[derive(PartialEq, Default)]
+pub struct Incompatibilities(HashMap<String, Incompatibility>);
+
+impl Incompatibilities {
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+
+	pub fn get(&self, id: &str) -> Option<&Incompatibility> {
+		self.0.get(id)
+	}
+}
+
+impl<'a> IntoIterator for &'a Incompatibilities {
+	type IntoIter = std::collections::hash_map::Values<'a, String, Incompatibility>;
+	type Item = &'a Incompatibility;
+	fn into_iter(self) -> Self::IntoIter {
+		self.0.values()
+	}
+}
+
+fn parse_incompatibilities<'de, D>(deserializer: D) -> Result<Incompatibilities, D::Error>
+where
+	D: Deserializer<'de>,
+{
+	let value = serde_json::Value::deserialize(deserializer)?;
+
+	// Legacy format is an array of objects with IDs
+	if value.is_array() {
+		return Ok(Incompatibilities(
+			<Vec<Incompatibility>>::deserialize(value)
+				.map_err(D::Error::custom)?
+				.into_iter()
+				.map(|i| (i.id.clone(), i))
+				.collect(),
+		));
+	}
+
+	<HashMap<String, serde_json::Value>>::deserialize(value)
+		.map_err(D::Error::custom)?
+		.into_iter()
+		.map(|(id, json)| {
+			// Shorthand is just "[mod.id]": "[version]"
+			let incompat = match parse_comparable_version(json.clone()) {
+				Ok(version) => Incompatibility {
+					id: id.clone(),
+					version,
+					importance: IncompatibilityImportance::default(),
+					platforms: all_platforms(),
+				},
+				Err(_) => {
+					let mut incompat =
+						Incompatibility::deserialize(json).map_err(D::Error::custom)?;
+					incompat.id.clone_from(&id);
+					incompat
+				}
+			};
+			Ok((id, incompat))
+		})
+		.collect::<Result<_, _>>()
+		.map(Incompatibilities)
+}
+
 #[derive(Default, Deserialize, PartialEq)]
 pub struct ModApi {
-	#[serde(deserialize_with = "parse_glob_rel")]
 	pub include: Vec<PathBuf>,
 }
 
+impl ModResources {
+	fn resolve_paths(&mut self, root: &Path) -> Result<(), String> {
+		self.libraries = expand_globs(root, &self.libraries)?;
+		self.files = expand_globs(root, &self.files)?;
+		self.sprites = expand_globs(root, &self.sprites)?;
+		for sheet in self.spritesheets.values_mut() {
+			sheet.files = expand_globs(root, &sheet.files)?;
+		}
+		for font in self.fonts.values_mut() {
+			font.path = root.join(&font.path);
+		}
+		Ok(())
+	}
+}
+
+impl ModApi {
+	/// Headers stay relative to the mod's directory, as that's how they're
+	/// laid out in the package
+	fn resolve_paths(&mut self, root: &Path) -> Result<(), String> {
+		self.include = expand_globs(root, &self.include)?
+			.into_iter()
+			.map(|p| p.strip_prefix(root).unwrap_or(&p).to_path_buf())
+			.collect();
+		Ok(())
+	}
+}
+
 #[derive(PartialEq)]
 pub struct Developers {
 	list: Vec1<String>,
@@ -444,6 +610,9 @@ impl<'de> Deserialize<'de> for Developers {
 	}
 }
 
+/// Mod IDs are a developer and a name separated by a dot, in lowercase
+pub const MOD_ID_PATTERN: &str = r"^[a-z0-9\-_]+\.[a-z0-9\-_]+$";
+
 #[derive(Deserialize, PartialEq)]
 pub struct ModFileInfo {
 	#[serde(deserialize_with = "parse_version")]
@@ -460,7 +629,29 @@ pub struct ModFileInfo {
 	pub resources: ModResources,
 	#[serde(default, deserialize_with = "parse_dependencies")]
 	pub dependencies: Dependencies,
+	#[serde(default, deserialize_with = "parse_incompatibilities")]
+	pub incompatibilities: Incompatibilities,
 	pub api: Option<ModApi>,
+	#[serde(default, deserialize_with = "parse_settings")]
+	pub settings: Vec<Setting>,
+}
+
+impl ModFileInfo {
+	/// Expand the globs in resources and the API headers, which are written
+	/// relative to `root`, the directory mod.json is in. Deserializing a
+	/// mod.json leaves them as written, [`try_parse_mod_info`] does this
+	/// afterwards
+	pub fn resolve_paths(&mut self, root: &Path) -> Result<(), String> {
+		let root = root
+			.absolutize()
+			.map(|r| r.to_path_buf())
+			.unwrap_or(root.to_path_buf());
+		self.resources.resolve_paths(&root)?;
+		if let Some(api) = &mut self.api {
+			api.resolve_paths(&root)?;
+		}
+		Ok(())
+	}
 }
 
 #[derive(Deserialize, PartialEq)]
@@ -478,39 +669,32 @@ pub enum GDVersion {
 	Detailed(DetailedGDVersion),
 }
 
-pub fn try_parse_mod_info(root_path: &Path) -> Result<ModFileInfo, String> {
+/// Parse the mod.json of either a mod's folder or a .geode package
+pub fn try_parse_mod_info(root_path: &Path) -> Result<ModFileInfo, Error> {
 	let data = if root_path.is_dir() {
-		std::fs::read_to_string(root_path.join("mod.json"))
-			.map_err(|e| format!("Unable to read mod.json: {e}"))?
+		let path = root_path.join("mod.json");
+		std::fs::read_to_string(&path).with_path(&path)?
 	} else {
 		let mut out = String::new();
 
-		zip::ZipArchive::new(fs::File::open(root_path).unwrap())
-			.map_err(|e| format!("Unable to unzip: {e}"))?
-			.by_name("mod.json")
-			.map_err(|e| format!("Unable to find mod.json in package: {e}"))?
+		zip::ZipArchive::new(fs::File::open(root_path).with_path(root_path)?)?
+			.by_name("mod.json")?
 			.read_to_string(&mut out)
-			.map_err(|e| format!("Unable to read mod.json: {e}"))?;
+			.with_path(root_path)?;
 
 		out
 	};
 
-	// to make globs work, relink current directory to the one mod.json is in
-	let old = std::env::current_dir().or(Err("Unable to get current directory"))?;
-
-	std::env::set_current_dir(if root_path.is_dir() {
+	// globs are relative to the directory mod.json is in
+	let root = if root_path.is_dir() {
 		root_path
 	} else {
 		root_path.parent().unwrap()
-	})
-	.or(Err("Unable to relink working directory"))?;
-
-	let res = serde_json::from_str(&data).map_err(|e| format!("Could not parse mod.json: {e}"))?;
-
-	// then link it back to where-ever it was
-	std::env::set_current_dir(old).or(Err("Unable to reset working directory"))?;
-
-	Ok(res)
+	};
+	let mut info: ModFileInfo =
+		serde_json::from_str(&data).map_err(|e| Error::ModJson(e.to_string()))?;
+	info.resolve_paths(root).map_err(Error::ModJson)?;
+	Ok(info)
 }
 
 pub fn parse_mod_info(root_path: &Path) -> ModFileInfo {
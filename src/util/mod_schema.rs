@@ -0,0 +1,517 @@
+use std::path::Path;
+
+use edit_distance::edit_distance;
+use regex::Regex;
+use serde_json::Value;
+
+use crate::json_span::{display_path, line_col, JsonSpans, PathSegment};
+use crate::mod_settings;
+use crate::{fail, fatal, warn};
+
+/// JSON Schema for mod.json, bundled with the CLI
+pub const MOD_JSON_SCHEMA: &str = include_str!("../../schema/mod.schema.json");
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Severity {
+	Error,
+	Warning,
+}
+
+pub struct SchemaIssue {
+	pub severity: Severity,
+	pub path: Vec<PathSegment>,
+	pub message: String,
+}
+
+/// Schema issue together with where it is in the source text
+pub struct LocatedIssue {
+	pub issue: SchemaIssue,
+	pub line: usize,
+	pub column: usize,
+}
+
+impl LocatedIssue {
+	pub fn print(&self, file: &str) {
+		let text = format!(
+			"{file}:{}:{}: {}: {}",
+			self.line,
+			self.column,
+			display_path(&self.issue.path),
+			self.issue.message
+		);
+		match self.issue.severity {
+			Severity::Error => fail!("{}", text),
+			Severity::Warning => warn!("{}", text),
+		}
+	}
+}
+
+struct Validator<'a> {
+	root: &'a Value,
+	issues: Vec<SchemaIssue>,
+}
+
+fn type_name(value: &Value) -> &'static str {
+	match value {
+		Value::Null => "null",
+		Value::Bool(_) => "boolean",
+		Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+		Value::Number(_) => "number",
+		Value::String(_) => "string",
+		Value::Array(_) => "array",
+		Value::Object(_) => "object",
+	}
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+	let actual = type_name(value);
+	actual == expected || (expected == "number" && actual == "integer")
+}
+
+impl<'a> Validator<'a> {
+	fn resolve(&self, schema: &'a Value) -> &'a Value {
+		match schema.get("$ref").and_then(|r| r.as_str()) {
+			Some(reference) => reference
+				.strip_prefix('#')
+				.and_then(|p| self.root.pointer(p))
+				.map(|s| self.resolve(s))
+				.unwrap_or(schema),
+			None => schema,
+		}
+	}
+
+	fn error(&mut self, path: &[PathSegment], message: String) {
+		self.issues.push(SchemaIssue {
+			severity: Severity::Error,
+			path: path.to_vec(),
+			message,
+		});
+	}
+
+	/// Whether the value passes the schema, without recording any issues
+	fn accepts(&self, schema: &'a Value, value: &Value) -> bool {
+		let mut sub = Validator {
+			root: self.root,
+			issues: Vec::new(),
+		};
+		sub.validate(schema, value, &mut Vec::new());
+		!sub.issues.iter().any(|i| i.severity == Severity::Error)
+	}
+
+	fn validate(&mut self, schema: &'a Value, value: &Value, path: &mut Vec<PathSegment>) {
+		let schema = self.resolve(schema);
+
+		if let Some(branches) = schema
+			.get("anyOf")
+			.or_else(|| schema.get("oneOf"))
+			.and_then(|b| b.as_array())
+		{
+			if branches.iter().any(|b| self.accepts(b, value)) {
+				// Still validate the matching branch to get its warnings
+				let branch = branches.iter().find(|b| self.accepts(b, value)).unwrap();
+				self.validate(branch, value, path);
+			} else {
+				// If only one of the branches takes this type of value, its
+				// errors are much more useful than a generic message
+				let same_type: Vec<_> = branches
+					.iter()
+					.filter(|b| {
+						self.resolve(b)
+							.get("type")
+							.and_then(|t| t.as_str())
+							.is_none_or(|t| type_matches(t, value))
+					})
+					.collect();
+				if same_type.len() == 1 {
+					self.validate(same_type[0], value, path);
+				} else {
+					self.error(path, "Value does not match any of the allowed forms".into());
+				}
+			}
+			return;
+		}
+
+		if let Some(expected) = schema.get("type").and_then(|t| t.as_str()) {
+			if !type_matches(expected, value) {
+				self.error(
+					path,
+					format!("Expected {expected}, found {}", type_name(value)),
+				);
+				return;
+			}
+		}
+
+		if let Some(options) = schema.get("enum").and_then(|e| e.as_array()) {
+			if !options.contains(value) {
+				let names: Vec<_> = options.iter().map(|o| o.to_string()).collect();
+				let mut message = format!(
+					"Invalid value {value}, expected one of {}",
+					names.join(", ")
+				);
+				if let Some(s) = value.as_str() {
+					if let Some(close) = closest(s, options.iter().filter_map(|o| o.as_str())) {
+						message += &format!(". Did you mean \"{close}\"?");
+					}
+				}
+				self.error(path, message);
+			}
+		}
+
+		match value {
+			Value::String(s) => {
+				if let Some(min) = schema.get("minLength").and_then(|m| m.as_u64()) {
+					if (s.chars().count() as u64) < min {
+						self.error(path, format!("Must be at least {min} characters long"));
+					}
+				}
+				if let Some(pattern) = schema.get("pattern").and_then(|p| p.as_str()) {
+					if !Regex::new(pattern).is_ok_and(|r| r.is_match(s)) {
+						self.error(path, format!("\"{s}\" is not in a valid format"));
+					}
+				}
+			}
+			Value::Number(n) => {
+				let n = n.as_f64().unwrap_or_default();
+				if let Some(min) = schema.get("minimum").and_then(|m| m.as_f64()) {
+					if n < min {
+						self.error(path, format!("Must be at least {min}"));
+					}
+				}
+				if let Some(max) = schema.get("maximum").and_then(|m| m.as_f64()) {
+					if n > max {
+						self.error(path, format!("Must be at most {max}"));
+					}
+				}
+			}
+			Value::Array(items) => {
+				if let Some(min) = schema.get("minItems").and_then(|m| m.as_u64()) {
+					if (items.len() as u64) < min {
+						self.error(path, format!("Must have at least {min} items"));
+					}
+				}
+				if let Some(max) = schema.get("maxItems").and_then(|m| m.as_u64()) {
+					if (items.len() as u64) > max {
+						self.error(path, format!("Must have at most {max} items"));
+					}
+				}
+				if let Some(item_schema) = schema.get("items") {
+					for (i, item) in items.iter().enumerate() {
+						path.push(PathSegment::Index(i));
+						self.validate(item_schema, item, path);
+						path.pop();
+					}
+				}
+			}
+			Value::Object(members) => {
+				let properties = schema.get("properties").and_then(|p| p.as_object());
+
+				if let Some(required) = schema.get("required").and_then(|r| r.as_array()) {
+					for key in required.iter().filter_map(|k| k.as_str()) {
+						if !members.contains_key(key) {
+							self.error(path, format!("Missing required key \"{key}\""));
+						}
+					}
+				}
+
+				for (key, member) in members {
+					path.push(PathSegment::Key(key.clone()));
+					if let Some(prop_schema) = properties.and_then(|p| p.get(key)) {
+						self.validate(prop_schema, member, path);
+					} else {
+						match schema.get("additionalProperties") {
+							Some(Value::Bool(false)) => {
+								// Unknown keys are ignored by Geode, so they're
+								// only warnings, but they are usually typos
+								let mut message = format!("Unknown key \"{key}\"");
+								if let Some(close) = closest(
+									key,
+									properties
+										.into_iter()
+										.flat_map(|p| p.keys())
+										.map(|k| k.as_str()),
+								) {
+									message += &format!(", did you mean \"{close}\"?");
+								}
+								self.issues.push(SchemaIssue {
+									severity: Severity::Warning,
+									path: path.clone(),
+									message,
+								});
+							}
+							Some(extra @ Value::Object(_)) => self.validate(extra, member, path),
+							_ => {}
+						}
+					}
+					path.pop();
+				}
+			}
+			_ => {}
+		}
+	}
+}
+
+/// Find the most similar candidate that is at most 3 edits away
+pub(crate) fn closest<'s>(
+	name: &str,
+	candidates: impl Iterator<Item = &'s str>,
+) -> Option<&'s str> {
+	candidates
+		.map(|c| (edit_distance(name, c), c))
+		.filter(|(dist, _)| *dist < 4)
+		.min_by_key(|(dist, _)| *dist)
+		.map(|(_, c)| c)
+}
+
+/// IDs of the mods listed under `key`, in either the object or the legacy
+/// array format, along with the path of each entry
+fn listed_mods(value: &Value, key: &str) -> Vec<(String, Vec<PathSegment>)> {
+	let path = |seg| vec![PathSegment::Key(key.into()), seg];
+	match value.get(key) {
+		Some(Value::Object(mods)) => mods
+			.keys()
+			.map(|id| (id.clone(), path(PathSegment::Key(id.clone()))))
+			.collect(),
+		Some(Value::Array(mods)) => mods
+			.iter()
+			.enumerate()
+			.filter_map(|(i, m)| {
+				Some((
+					m.get("id")?.as_str()?.to_string(),
+					path(PathSegment::Index(i)),
+				))
+			})
+			.collect(),
+		_ => Vec::new(),
+	}
+}
+
+/// A mod can't both depend on and be incompatible with another mod
+fn check_incompatible_dependencies(value: &Value) -> Vec<SchemaIssue> {
+	let deps = listed_mods(value, "dependencies");
+	listed_mods(value, "incompatibilities")
+		.into_iter()
+		.filter(|(id, _)| deps.iter().any(|(dep, _)| dep == id))
+		.map(|(id, path)| SchemaIssue {
+			severity: Severity::Error,
+			path,
+			message: format!("'{id}' is listed as both a dependency and an incompatibility"),
+		})
+		.collect()
+}
+
+/// Validate a parsed mod.json against the bundled schema
+pub fn validate(value: &Value) -> Vec<SchemaIssue> {
+	let schema: Value = serde_json::from_str(MOD_JSON_SCHEMA).unwrap();
+	let mut validator = Validator {
+		root: &schema,
+		issues: Vec::new(),
+	};
+	validator.validate(&schema, value, &mut Vec::new());
+
+	// Settings have rules that depend on their type, which the schema
+	// can't express nicely
+	let mut issues = validator.issues;
+	if let Some(settings) = value.get("settings") {
+		issues.extend(mod_settings::parse_settings(settings).1);
+	}
+	issues.extend(check_incompatible_dependencies(value));
+	issues
+}
+
+/// Validate mod.json source text, locating every issue in it. Syntax errors
+/// are returned as `Err`
+pub fn validate_text(text: &str) -> Result<Vec<LocatedIssue>, String> {
+	let value: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+	let spans = JsonSpans::parse(text)?;
+
+	Ok(validate(&value)
+		.into_iter()
+		.map(|issue| {
+			let offset = spans
+				.closest(&issue.path)
+				.map(|s| s.key_start.unwrap_or(s.start))
+				.unwrap_or(0);
+			let (line, column) = line_col(text, offset);
+			LocatedIssue {
+				issue,
+				line,
+				column,
+			}
+		})
+		.collect())
+}
+
+/// Validate the mod.json of a project, printing every issue found and exiting
+/// if any of them are errors
+pub fn check_mod_json(root_path: &Path) {
+	let path = root_path.join("mod.json");
+	let Ok(text) = std::fs::read_to_string(&path) else {
+		// Reading errors are reported by the actual parsing
+		return;
+	};
+
+	let issues = match validate_text(&text) {
+		Ok(issues) => issues,
+		Err(e) => fatal!("Could not parse mod.json: {}", e),
+	};
+
+	for issue in &issues {
+		issue.print("mod.json");
+	}
+
+	if issues.iter().any(|i| i.issue.severity == Severity::Error) {
+		fatal!("mod.json does not match the schema");
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	fn key(k: &str) -> PathSegment {
+		PathSegment::Key(k.into())
+	}
+
+	fn mod_json(extra: Value) -> Value {
+		let mut value = json!({
+			"geode": "4.0.0",
+			"gd": { "win": "2.2074", "android": "2.2074" },
+			"id": "dev.mod",
+			"name": "Mod",
+			"version": "v1.0.0",
+			"developer": "dev",
+		});
+		for (key, member) in extra.as_object().unwrap() {
+			value[key] = member.clone();
+		}
+		value
+	}
+
+	/// The only issue found, as its severity, path and message
+	fn single_issue(value: &Value) -> (Severity, String, String) {
+		let issues = validate(value);
+		assert_eq!(issues.len(), 1, "expected a single issue in {value}");
+		let issue = &issues[0];
+		(
+			issue.severity,
+			display_path(&issue.path),
+			issue.message.clone(),
+		)
+	}
+
+	fn error(path: &str, message: &str) -> (Severity, String, String) {
+		(Severity::Error, path.into(), message.into())
+	}
+
+	#[test]
+	fn valid_mod_json_has_no_issues() {
+		let value = mod_json(json!({
+			"dependencies": {
+				"geode.node-ids": ">=1.0.0",
+				"dev.other": { "version": "1.2.0", "required": false, "platforms": ["win"] },
+			},
+			"incompatibilities": [{ "id": "dev.bad", "version": "*" }],
+		}));
+		assert!(validate(&value).is_empty());
+	}
+
+	#[test]
+	fn missing_required_keys() {
+		let mut value = mod_json(json!({}));
+		value.as_object_mut().unwrap().remove("name");
+		assert_eq!(
+			single_issue(&value),
+			error("$", "Missing required key \"name\"")
+		);
+	}
+
+	#[test]
+	fn wrong_types_and_formats() {
+		assert_eq!(
+			single_issue(&mod_json(json!({ "geode": 4 }))),
+			error("$.geode", "Expected string, found integer")
+		);
+		assert_eq!(
+			single_issue(&mod_json(json!({ "id": "mod" }))),
+			error("$.id", "\"mod\" is not in a valid format")
+		);
+		assert_eq!(
+			single_issue(&mod_json(json!({ "gd": true }))),
+			error("$.gd", "Value does not match any of the allowed forms")
+		);
+	}
+
+	#[test]
+	fn length_limits() {
+		assert_eq!(
+			single_issue(&mod_json(json!({ "developers": [] }))),
+			error("$.developers", "Must have at least 1 items")
+		);
+		assert_eq!(
+			single_issue(&mod_json(json!({ "developers": [""] }))),
+			error("$.developers[0]", "Must be at least 1 characters long")
+		);
+	}
+
+	#[test]
+	fn unknown_keys_are_warnings_with_suggestions() {
+		assert_eq!(
+			single_issue(&mod_json(json!({ "descripton": "Text" }))),
+			(
+				Severity::Warning,
+				"$.descripton".into(),
+				"Unknown key \"descripton\", did you mean \"description\"?".into()
+			)
+		);
+		let (_, _, message) = single_issue(&mod_json(json!({ "something": 1 })));
+		assert_eq!(message, "Unknown key \"something\"");
+	}
+
+	#[test]
+	fn errors_come_from_the_branch_of_the_same_type() {
+		assert_eq!(
+			single_issue(&mod_json(json!({
+				"dependencies": { "dev.other": { "version": "*", "importance": "requried" } }
+			}))),
+			error(
+				"$.dependencies[\"dev.other\"].importance",
+				"Invalid value \"requried\", expected one of \"required\", \"recommended\", \"suggested\". Did you mean \"required\"?"
+			)
+		);
+		assert_eq!(
+			single_issue(&mod_json(json!({
+				"dependencies": { "dev.other": { "required": true } }
+			}))),
+			error(
+				"$.dependencies[\"dev.other\"]",
+				"Missing required key \"version\""
+			)
+		);
+	}
+
+	#[test]
+	fn dependencies_cant_be_incompatible() {
+		assert_eq!(
+			single_issue(&mod_json(json!({
+				"dependencies": { "dev.other": ">=1.0.0" },
+				"incompatibilities": { "dev.other": "*" },
+			}))),
+			error(
+				"$.incompatibilities[\"dev.other\"]",
+				"'dev.other' is listed as both a dependency and an incompatibility"
+			)
+		);
+	}
+
+	#[test]
+	fn issues_are_located_in_the_text() {
+		let text = "{\n\t\"geode\": \"4.0.0\",\n\t\"gd\": \"2.2074\",\n\t\"id\": \"dev.mod\",\n\t\"name\": \"Mod\",\n\t\"version\": 1\n}\n";
+		let issues = validate_text(text).unwrap();
+		assert_eq!(issues.len(), 1);
+		assert_eq!((issues[0].line, issues[0].column), (6, 2));
+		assert_eq!(issues[0].issue.path, [key("version")]);
+
+		assert!(validate_text("{\"id\": }").is_err());
+	}
+}
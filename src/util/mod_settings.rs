@@ -0,0 +1,885 @@
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use crate::json_span::PathSegment;
+use crate::mod_schema::{closest, SchemaIssue, Severity};
+
+/// Type of a setting, along with what's needed to know about its values
+#[derive(Clone, Debug, PartialEq)]
+pub enum SettingKind {
+	Bool,
+	Int {
+		min: Option<i64>,
+		max: Option<i64>,
+	},
+	Float {
+		min: Option<f64>,
+		max: Option<f64>,
+	},
+	/// Strings restricted to a set of choices with `one-of` have them here
+	String {
+		one_of: Option<Vec<String>>,
+	},
+	File,
+	Folder,
+	Color,
+	Rgba,
+	Title,
+	/// Setting implemented by the mod itself, named after `custom:`
+	Custom(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Setting {
+	pub key: String,
+	pub name: Option<String>,
+	pub description: Option<String>,
+	pub kind: SettingKind,
+	/// Default value, or an object of default values keyed by platform
+	pub default: Option<Value>,
+}
+
+/// Every setting type supported by Geode, besides `custom:<name>`
+pub const SETTING_TYPES: &[&str] = &[
+	"bool", "int", "float", "string", "file", "folder", "color", "rgba", "title",
+];
+
+/// Fields every setting can have
+const COMMON_FIELDS: &[&str] = &[
+	"type",
+	"name",
+	"description",
+	"enable-if",
+	"enable-if-description",
+	"requires-restart",
+	"platforms",
+];
+
+/// Platforms that can be given their own default value
+const PLATFORMS: &[&str] = &[
+	"win",
+	"mac",
+	"mac-intel",
+	"mac-arm",
+	"android",
+	"android32",
+	"android64",
+	"ios",
+];
+
+/// Fields a setting type accepts besides the common ones
+fn type_fields(ty: &str) -> &'static [&'static str] {
+	match ty {
+		"bool" | "folder" | "color" | "rgba" => &["default"],
+		"int" | "float" => &["default", "min", "max", "control"],
+		"string" => &["default", "match", "filter", "one-of"],
+		"file" => &["default", "control"],
+		_ => &[],
+	}
+}
+
+/// Kinds of controls a setting type accepts in `control`
+fn control_fields(ty: &str) -> &'static [&'static str] {
+	match ty {
+		"int" | "float" => &[
+			"arrows",
+			"arrow-step",
+			"big-arrows",
+			"big-arrow-step",
+			"slider",
+			"slider-step",
+			"input",
+		],
+		"file" => &["dialog", "filters"],
+		_ => &[],
+	}
+}
+
+fn key_path(path: &[PathSegment], key: &str) -> Vec<PathSegment> {
+	let mut path = path.to_vec();
+	path.push(PathSegment::Key(key.to_string()));
+	path
+}
+
+/// Check a color written as a hex string, an array or an object of
+/// components
+fn check_color(value: &Value, alpha: bool) -> Result<(), String> {
+	let channels = if alpha { "RGBA" } else { "RGB" };
+	let in_range = |v: &Value| v.as_u64().is_some_and(|c| c <= 255);
+	match value {
+		Value::String(hex) => {
+			let digits = hex.strip_prefix('#').unwrap_or(hex);
+			let lengths: &[usize] = if alpha { &[3, 4, 6, 8] } else { &[3, 6] };
+			if !lengths.contains(&digits.len()) || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+				return Err(format!("\"{hex}\" is not a valid {channels} hex color"));
+			}
+		}
+		Value::Array(components) => {
+			let count = if alpha { 4 } else { 3 };
+			if components.len() != count || !components.iter().all(in_range) {
+				return Err(format!(
+					"Colors given as arrays must have {count} components from 0 to 255"
+				));
+			}
+		}
+		Value::Object(components) => {
+			let keys: &[&str] = if alpha {
+				&["r", "g", "b", "a"]
+			} else {
+				&["r", "g", "b"]
+			};
+			if components.len() != keys.len()
+				|| !keys
+					.iter()
+					.all(|k| components.get(*k).is_some_and(in_range))
+			{
+				return Err(format!(
+					"Colors given as objects must have the keys {} from 0 to 255",
+					keys.join(", ")
+				));
+			}
+		}
+		_ => return Err(format!("Expected an {channels} color")),
+	}
+	Ok(())
+}
+
+struct SettingsChecker {
+	issues: Vec<SchemaIssue>,
+}
+
+impl SettingsChecker {
+	fn report(&mut self, severity: Severity, path: &[PathSegment], message: String) {
+		self.issues.push(SchemaIssue {
+			severity,
+			path: path.to_vec(),
+			message,
+		});
+	}
+
+	fn error(&mut self, path: &[PathSegment], message: String) {
+		self.report(Severity::Error, path, message);
+	}
+
+	fn warning(&mut self, path: &[PathSegment], message: String) {
+		self.report(Severity::Warning, path, message);
+	}
+
+	/// Warn about keys of an object that Geode doesn't know about
+	fn check_unknown(
+		&mut self,
+		path: &[PathSegment],
+		obj: &Map<String, Value>,
+		known: &[&str],
+		what: &str,
+	) {
+		for key in obj.keys().filter(|k| !known.contains(&k.as_str())) {
+			let mut message = format!("Unknown {what} \"{key}\"");
+			if let Some(close) = closest(key, known.iter().copied()) {
+				message += &format!(", did you mean \"{close}\"?");
+			}
+			self.warning(&key_path(path, key), message);
+		}
+	}
+
+	fn expect<'v, T>(
+		&mut self,
+		path: &[PathSegment],
+		value: &'v Value,
+		expected: &str,
+		get: impl FnOnce(&'v Value) -> Option<T>,
+	) -> Option<T> {
+		let got = get(value);
+		if got.is_none() {
+			self.error(path, format!("Expected {expected}, found {value}"));
+		}
+		got
+	}
+
+	/// Optional field of a setting with a fixed type
+	fn field<'v, T>(
+		&mut self,
+		path: &[PathSegment],
+		obj: &'v Map<String, Value>,
+		key: &str,
+		expected: &str,
+		get: impl FnOnce(&'v Value) -> Option<T>,
+	) -> Option<T> {
+		let value = obj.get(key)?;
+		self.expect(&key_path(path, key), value, expected, get)
+	}
+
+	/// Every default value of a setting along with its path, expanding
+	/// platform-specific defaults
+	fn defaults<'v>(
+		&mut self,
+		path: &[PathSegment],
+		default: &'v Value,
+	) -> Vec<(Vec<PathSegment>, &'v Value)> {
+		let path = key_path(path, "default");
+		match default {
+			Value::Object(per_platform)
+				if !per_platform.is_empty()
+					&& per_platform.keys().all(|k| PLATFORMS.contains(&k.as_str())) =>
+			{
+				per_platform
+					.iter()
+					.map(|(platform, value)| (key_path(&path, platform), value))
+					.collect()
+			}
+			_ => vec![(path, default)],
+		}
+	}
+
+	fn check_range<T: PartialOrd + std::fmt::Display>(
+		&mut self,
+		path: &[PathSegment],
+		value: T,
+		min: Option<T>,
+		max: Option<T>,
+	) {
+		if let Some(min) = min.filter(|min| value < *min) {
+			self.error(
+				path,
+				format!("Default value {value} is less than min {min}"),
+			);
+		}
+		if let Some(max) = max.filter(|max| value > *max) {
+			self.error(
+				path,
+				format!("Default value {value} is more than max {max}"),
+			);
+		}
+	}
+
+	fn check_control(&mut self, path: &[PathSegment], ty: &str, control: &Value) {
+		let path = key_path(path, "control");
+		let Some(control) = self.expect(&path, control, "an object", Value::as_object) else {
+			return;
+		};
+		let known = control_fields(ty);
+		if known.is_empty() {
+			self.warning(&path, format!("Settings of type \"{ty}\" have no controls"));
+			return;
+		}
+		self.check_unknown(&path, control, known, "control");
+
+		for (key, value) in control {
+			let value_path = key_path(&path, key);
+			match key.as_str() {
+				"arrows" | "big-arrows" | "slider" | "input" => {
+					self.expect(&value_path, value, "a boolean", Value::as_bool);
+				}
+				"arrow-step" | "big-arrow-step" | "slider-step" => {
+					let step = if ty == "int" {
+						self.expect(&value_path, value, "an integer", Value::as_i64)
+							.map(|s| s as f64)
+					} else {
+						self.expect(&value_path, value, "a number", Value::as_f64)
+					};
+					if step.is_some_and(|s| s <= 0.0) {
+						self.error(&value_path, "Steps must be positive".into());
+					}
+				}
+				"dialog" => {
+					if let Some(dialog) = self.expect(&value_path, value, "a string", Value::as_str)
+					{
+						if !["open", "save"].contains(&dialog) {
+							self.error(
+								&value_path,
+								format!(
+									"Invalid dialog \"{dialog}\", expected \"open\" or \"save\""
+								),
+							);
+						}
+					}
+				}
+				"filters" => self.check_file_filters(&value_path, value),
+				_ => {}
+			}
+		}
+	}
+
+	fn check_file_filters(&mut self, path: &[PathSegment], filters: &Value) {
+		let Some(filters) = self.expect(path, filters, "an array", Value::as_array) else {
+			return;
+		};
+		for (i, filter) in filters.iter().enumerate() {
+			let mut path = path.to_vec();
+			path.push(PathSegment::Index(i));
+			let Some(filter) = self.expect(&path, filter, "an object", Value::as_object) else {
+				continue;
+			};
+			self.check_unknown(&path, filter, &["files", "description"], "key");
+			self.field(&path, filter, "description", "a string", Value::as_str);
+			let files = self.field(&path, filter, "files", "an array of strings", |f| {
+				f.as_array().filter(|f| f.iter().all(|f| f.is_string()))
+			});
+			if !filter.contains_key("files") {
+				self.error(&path, "Missing required key \"files\"".into());
+			} else if files.is_some_and(|f| f.is_empty()) {
+				self.error(
+					&key_path(&path, "files"),
+					"Filters need at least one pattern".into(),
+				);
+			}
+		}
+	}
+
+	fn check_number_setting(
+		&mut self,
+		path: &[PathSegment],
+		obj: &Map<String, Value>,
+		ty: &str,
+	) -> SettingKind {
+		if ty == "int" {
+			let min = self.field(path, obj, "min", "an integer", Value::as_i64);
+			let max = self.field(path, obj, "max", "an integer", Value::as_i64);
+			if let (Some(min), Some(max)) = (min, max) {
+				if min > max {
+					self.error(
+						&key_path(path, "max"),
+						format!("max {max} is less than min {min}"),
+					);
+				}
+			}
+			if let Some(default) = obj.get("default") {
+				for (path, value) in self.defaults(path, default) {
+					if let Some(value) = self.expect(&path, value, "an integer", Value::as_i64) {
+						self.check_range(&path, value, min, max);
+					}
+				}
+			}
+			SettingKind::Int { min, max }
+		} else {
+			let min = self.field(path, obj, "min", "a number", Value::as_f64);
+			let max = self.field(path, obj, "max", "a number", Value::as_f64);
+			if let (Some(min), Some(max)) = (min, max) {
+				if min > max {
+					self.error(
+						&key_path(path, "max"),
+						format!("max {max} is less than min {min}"),
+					);
+				}
+			}
+			if let Some(default) = obj.get("default") {
+				for (path, value) in self.defaults(path, default) {
+					if let Some(value) = self.expect(&path, value, "a number", Value::as_f64) {
+						self.check_range(&path, value, min, max);
+					}
+				}
+			}
+			SettingKind::Float { min, max }
+		}
+	}
+
+	fn check_string_setting(
+		&mut self,
+		path: &[PathSegment],
+		obj: &Map<String, Value>,
+	) -> SettingKind {
+		let pattern = self
+			.field(path, obj, "match", "a string", Value::as_str)
+			.and_then(|pattern| {
+				// Geode matches the whole string
+				match Regex::new(&format!("^(?:{pattern})$")) {
+					Ok(regex) => Some(regex),
+					Err(e) => {
+						self.error(&key_path(path, "match"), format!("Invalid regex: {e}"));
+						None
+					}
+				}
+			});
+		let filter = self.field(path, obj, "filter", "a string", Value::as_str);
+		let one_of = self.field(path, obj, "one-of", "an array of strings", |o| {
+			o.as_array()?
+				.iter()
+				.map(|s| s.as_str().map(str::to_string))
+				.collect::<Option<Vec<_>>>()
+		});
+		if let Some(choices) = &one_of {
+			let one_of_path = key_path(path, "one-of");
+			if choices.is_empty() {
+				self.error(&one_of_path, "Must have at least one choice".into());
+			}
+			for (i, choice) in choices.iter().enumerate() {
+				if choices[..i].contains(choice) {
+					self.warning(&one_of_path, format!("Choice \"{choice}\" is listed twice"));
+				}
+			}
+		}
+
+		if let Some(default) = obj.get("default") {
+			for (path, value) in self.defaults(path, default) {
+				let Some(value) = self.expect(&path, value, "a string", Value::as_str) else {
+					continue;
+				};
+				if pattern.as_ref().is_some_and(|p| !p.is_match(value)) {
+					self.error(
+						&path,
+						format!("Default value \"{value}\" does not match \"match\""),
+					);
+				}
+				if let Some(bad) = filter.and_then(|f| value.chars().find(|c| !f.contains(*c))) {
+					self.error(
+						&path,
+						format!("Default value \"{value}\" contains '{bad}', which \"filter\" doesn't allow"),
+					);
+				}
+				if let Some(choices) = &one_of {
+					if !choices.iter().any(|c| c == value) {
+						self.error(
+							&path,
+							format!(
+								"Default value \"{value}\" is not one of {}",
+								choices
+									.iter()
+									.map(|c| format!("\"{c}\""))
+									.collect::<Vec<_>>()
+									.join(", ")
+							),
+						);
+					}
+				}
+			}
+		}
+		SettingKind::String { one_of }
+	}
+
+	fn check_setting(&mut self, key: &str, value: &Value) -> Option<Setting> {
+		let path = vec![
+			PathSegment::Key("settings".into()),
+			PathSegment::Key(key.to_string()),
+		];
+		let obj = self.expect(&path, value, "an object", Value::as_object)?;
+
+		if !key
+			.chars()
+			.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+		{
+			self.warning(
+				&path,
+				format!(
+					"Setting key \"{key}\" should only contain lowercase letters, numbers, - and _"
+				),
+			);
+		}
+
+		let Some(ty) = obj.get("type") else {
+			self.error(&path, "Missing required key \"type\"".into());
+			return None;
+		};
+		let ty = self.expect(&key_path(&path, "type"), ty, "a string", Value::as_str)?;
+
+		let name = self.field(&path, obj, "name", "a string", Value::as_str);
+		let description = self.field(&path, obj, "description", "a string", Value::as_str);
+		self.field(&path, obj, "enable-if", "a string", Value::as_str);
+		self.field(
+			&path,
+			obj,
+			"enable-if-description",
+			"a string",
+			Value::as_str,
+		);
+		self.field(&path, obj, "requires-restart", "a boolean", Value::as_bool);
+
+		// Custom settings are entirely up to the mod
+		if ty == "custom" || ty.starts_with("custom:") {
+			return Some(Setting {
+				key: key.to_string(),
+				name: name.map(str::to_string),
+				description: description.map(str::to_string),
+				kind: SettingKind::Custom(
+					ty.trim_start_matches("custom")
+						.trim_start_matches(':')
+						.to_string(),
+				),
+				default: obj.get("default").cloned(),
+			});
+		}
+
+		if !SETTING_TYPES.contains(&ty) {
+			let mut message = format!("Unknown setting type \"{ty}\"");
+			if let Some(close) = closest(ty, SETTING_TYPES.iter().copied()) {
+				message += &format!(", did you mean \"{close}\"?");
+			}
+			self.error(&key_path(&path, "type"), message);
+			return None;
+		}
+
+		let known: Vec<&str> = COMMON_FIELDS
+			.iter()
+			.chain(type_fields(ty))
+			.copied()
+			.collect();
+		self.check_unknown(&path, obj, &known, "key");
+
+		if ty != "title" && !obj.contains_key("default") {
+			self.error(&path, "Missing required key \"default\"".into());
+		}
+		if let Some(control) = obj.get("control") {
+			self.check_control(&path, ty, control);
+		}
+
+		let kind = match ty {
+			"bool" => {
+				if let Some(default) = obj.get("default") {
+					for (path, value) in self.defaults(&path, default) {
+						self.expect(&path, value, "a boolean", Value::as_bool);
+					}
+				}
+				SettingKind::Bool
+			}
+			"int" | "float" => self.check_number_setting(&path, obj, ty),
+			"string" => self.check_string_setting(&path, obj),
+			"file" | "folder" => {
+				if let Some(default) = obj.get("default") {
+					for (path, value) in self.defaults(&path, default) {
+						self.expect(&path, value, "a path", Value::as_str);
+					}
+				}
+				if ty == "file" {
+					SettingKind::File
+				} else {
+					SettingKind::Folder
+				}
+			}
+			"color" | "rgba" => {
+				let alpha = ty == "rgba";
+				if let Some(default) = obj.get("default") {
+					for (path, value) in self.defaults(&path, default) {
+						if let Err(e) = check_color(value, alpha) {
+							self.error(&path, e);
+						}
+					}
+				}
+				if alpha {
+					SettingKind::Rgba
+				} else {
+					SettingKind::Color
+				}
+			}
+			_ => SettingKind::Title,
+		};
+
+		Some(Setting {
+			key: key.to_string(),
+			name: name.map(str::to_string),
+			description: description.map(str::to_string),
+			kind,
+			default: obj.get("default").cloned(),
+		})
+	}
+}
+
+/// Parse the `settings` object of a mod.json, checking every setting the way
+/// Geode would when loading the mod. Settings that can't be understood at all
+/// are left out of the returned list
+pub fn parse_settings(settings: &Value) -> (Vec<Setting>, Vec<SchemaIssue>) {
+	let mut checker = SettingsChecker { issues: Vec::new() };
+	let parsed = settings
+		.as_object()
+		.into_iter()
+		.flatten()
+		.filter_map(|(key, value)| checker.check_setting(key, value))
+		.collect();
+	(parsed, checker.issues)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::json_span::display_path;
+	use serde_json::json;
+
+	/// Every issue found in the settings, as its severity, path and message
+	fn issues(settings: Value) -> Vec<(Severity, String, String)> {
+		parse_settings(&settings)
+			.1
+			.into_iter()
+			.map(|i| (i.severity, display_path(&i.path), i.message))
+			.collect()
+	}
+
+	fn error(path: &str, message: &str) -> (Severity, String, String) {
+		(Severity::Error, path.into(), message.into())
+	}
+
+	fn warning(path: &str, message: &str) -> (Severity, String, String) {
+		(Severity::Warning, path.into(), message.into())
+	}
+
+	#[test]
+	fn parses_every_kind() {
+		let (settings, issues) = parse_settings(&json!({
+			"enabled": { "type": "bool", "default": true, "name": "Enabled" },
+			"count": { "type": "int", "default": 5, "min": 1, "max": 10 },
+			"speed": { "type": "float", "default": 0.5 },
+			"mode": { "type": "string", "default": "a", "one-of": ["a", "b"] },
+			"path": { "type": "file", "default": "{gd_dir}/a.txt" },
+			"dir": { "type": "folder", "default": "{gd_dir}" },
+			"tint": { "type": "color", "default": "#ff00ff" },
+			"glow": { "type": "rgba", "default": [255, 0, 0, 128] },
+			"section": { "type": "title", "name": "Section" },
+			"keys": { "type": "custom:keybinds", "anything": 1 },
+		}));
+		assert!(issues.is_empty());
+
+		let kinds: Vec<_> = settings.iter().map(|s| s.kind.clone()).collect();
+		assert_eq!(
+			kinds,
+			[
+				SettingKind::Bool,
+				SettingKind::Int {
+					min: Some(1),
+					max: Some(10)
+				},
+				SettingKind::Float {
+					min: None,
+					max: None
+				},
+				SettingKind::String {
+					one_of: Some(vec!["a".into(), "b".into()])
+				},
+				SettingKind::File,
+				SettingKind::Folder,
+				SettingKind::Color,
+				SettingKind::Rgba,
+				SettingKind::Title,
+				SettingKind::Custom("keybinds".into()),
+			]
+		);
+		assert_eq!(settings[0].key, "enabled");
+		assert_eq!(settings[0].name.as_deref(), Some("Enabled"));
+		assert_eq!(settings[0].default, Some(json!(true)));
+	}
+
+	#[test]
+	fn number_defaults_are_checked_against_min_and_max() {
+		assert_eq!(
+			issues(json!({ "x": { "type": "int", "default": 20, "min": 1, "max": 10 } })),
+			[error(
+				"$.settings.x.default",
+				"Default value 20 is more than max 10"
+			)]
+		);
+		assert_eq!(
+			issues(json!({ "x": { "type": "float", "default": 0.5, "min": 1 } })),
+			[error(
+				"$.settings.x.default",
+				"Default value 0.5 is less than min 1"
+			)]
+		);
+		assert_eq!(
+			issues(json!({ "x": { "type": "int", "default": 5, "min": 10, "max": 1 } })),
+			[
+				error("$.settings.x.max", "max 1 is less than min 10"),
+				error(
+					"$.settings.x.default",
+					"Default value 5 is less than min 10"
+				),
+				error("$.settings.x.default", "Default value 5 is more than max 1"),
+			]
+		);
+		assert_eq!(
+			issues(json!({ "x": { "type": "int", "default": 1.5 } })),
+			[error(
+				"$.settings.x.default",
+				"Expected an integer, found 1.5"
+			)]
+		);
+	}
+
+	#[test]
+	fn platform_defaults_are_checked_separately() {
+		assert_eq!(
+			issues(json!({
+				"x": { "type": "int", "default": { "win": 5, "android": 20 }, "max": 10 }
+			})),
+			[error(
+				"$.settings.x.default.android",
+				"Default value 20 is more than max 10"
+			)]
+		);
+		// Objects with other keys aren't per-platform defaults
+		assert_eq!(
+			issues(json!({ "x": { "type": "int", "default": { "win": 5, "other": 1 } } })),
+			[error(
+				"$.settings.x.default",
+				"Expected an integer, found {\"win\":5,\"other\":1}"
+			)]
+		);
+	}
+
+	#[test]
+	fn string_defaults_follow_match_filter_and_one_of() {
+		assert_eq!(
+			issues(json!({ "x": { "type": "string", "default": "abc1", "match": "[a-z]+" } })),
+			[error(
+				"$.settings.x.default",
+				"Default value \"abc1\" does not match \"match\""
+			)]
+		);
+		assert_eq!(
+			issues(json!({ "x": { "type": "string", "default": "ab!", "filter": "abc" } })),
+			[error(
+				"$.settings.x.default",
+				"Default value \"ab!\" contains '!', which \"filter\" doesn't allow"
+			)]
+		);
+		assert_eq!(
+			issues(json!({ "x": { "type": "string", "default": "c", "one-of": ["a", "b", "a"] } })),
+			[
+				warning("$.settings.x[\"one-of\"]", "Choice \"a\" is listed twice"),
+				error(
+					"$.settings.x.default",
+					"Default value \"c\" is not one of \"a\", \"b\", \"a\""
+				),
+			]
+		);
+		assert_eq!(
+			issues(json!({ "x": { "type": "string", "default": "", "one-of": [] } })),
+			[
+				error("$.settings.x[\"one-of\"]", "Must have at least one choice"),
+				error("$.settings.x.default", "Default value \"\" is not one of "),
+			]
+		);
+		let invalid = issues(json!({ "x": { "type": "string", "default": "", "match": "(" } }));
+		assert_eq!(invalid.len(), 1);
+		assert_eq!(invalid[0].1, "$.settings.x.match");
+		assert!(invalid[0].2.starts_with("Invalid regex"));
+	}
+
+	#[test]
+	fn colors_can_be_hex_arrays_or_objects() {
+		for default in [json!("#f0f"), json!("00ff00"), json!([0, 128, 255])] {
+			assert!(issues(json!({ "x": { "type": "color", "default": default } })).is_empty());
+		}
+		for default in [
+			json!("#ff00ff80"),
+			json!({ "r": 1, "g": 2, "b": 3, "a": 4 }),
+		] {
+			assert!(issues(json!({ "x": { "type": "rgba", "default": default } })).is_empty());
+		}
+
+		assert_eq!(
+			issues(json!({ "x": { "type": "color", "default": "#ff00ff80" } })),
+			[error(
+				"$.settings.x.default",
+				"\"#ff00ff80\" is not a valid RGB hex color"
+			)]
+		);
+		assert_eq!(
+			issues(json!({ "x": { "type": "rgba", "default": [255, 0, 0] } })),
+			[error(
+				"$.settings.x.default",
+				"Colors given as arrays must have 4 components from 0 to 255"
+			)]
+		);
+		assert_eq!(
+			issues(json!({ "x": { "type": "color", "default": { "r": 256, "g": 0, "b": 0 } } })),
+			[error(
+				"$.settings.x.default",
+				"Colors given as objects must have the keys r, g, b from 0 to 255"
+			)]
+		);
+		assert_eq!(
+			issues(json!({ "x": { "type": "color", "default": 1 } })),
+			[error("$.settings.x.default", "Expected an RGB color")]
+		);
+	}
+
+	#[test]
+	fn unknown_keys_and_types_suggest_known_ones() {
+		assert_eq!(
+			issues(json!({ "x": { "type": "bool", "defualt": true } })),
+			[
+				warning(
+					"$.settings.x.defualt",
+					"Unknown key \"defualt\", did you mean \"default\"?"
+				),
+				error("$.settings.x", "Missing required key \"default\""),
+			]
+		);
+		assert_eq!(
+			issues(json!({ "x": { "type": "boool", "default": true } })),
+			[error(
+				"$.settings.x.type",
+				"Unknown setting type \"boool\", did you mean \"bool\"?"
+			)]
+		);
+		assert_eq!(
+			issues(json!({ "x": { "type": "bool", "default": true, "min": 1 } })),
+			[warning("$.settings.x.min", "Unknown key \"min\"")]
+		);
+	}
+
+	#[test]
+	fn controls_are_checked_for_the_setting_type() {
+		assert_eq!(
+			issues(json!({
+				"x": { "type": "int", "default": 1, "control": { "arrow-step": 0, "sliderr": true } }
+			})),
+			[
+				warning(
+					"$.settings.x.control.sliderr",
+					"Unknown control \"sliderr\", did you mean \"slider\"?"
+				),
+				error(
+					"$.settings.x.control[\"arrow-step\"]",
+					"Steps must be positive"
+				),
+			]
+		);
+		assert_eq!(
+			issues(json!({
+				"x": {
+					"type": "file",
+					"default": "a.txt",
+					"control": { "dialog": "pick", "filters": [{ "description": "Text" }, { "files": [] }] }
+				}
+			})),
+			[
+				error(
+					"$.settings.x.control.dialog",
+					"Invalid dialog \"pick\", expected \"open\" or \"save\""
+				),
+				error(
+					"$.settings.x.control.filters[0]",
+					"Missing required key \"files\""
+				),
+				error(
+					"$.settings.x.control.filters[1].files",
+					"Filters need at least one pattern"
+				),
+			]
+		);
+	}
+
+	#[test]
+	fn malformed_settings_are_left_out() {
+		let (settings, issues) = parse_settings(&json!({
+			"a": true,
+			"b": { "default": 1 },
+			"Bad-Key": { "type": "title" },
+		}));
+		assert_eq!(settings.len(), 1);
+		assert_eq!(settings[0].key, "Bad-Key");
+
+		let issues: Vec<_> = issues
+			.into_iter()
+			.map(|i| (i.severity, display_path(&i.path), i.message))
+			.collect();
+		assert_eq!(
+			issues,
+			[
+				error("$.settings.a", "Expected an object, found true"),
+				error("$.settings.b", "Missing required key \"type\""),
+				warning(
+					"$.settings[\"Bad-Key\"]",
+					"Setting key \"Bad-Key\" should only contain lowercase letters, numbers, - and _"
+				),
+			]
+		);
+	}
+}
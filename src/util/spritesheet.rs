@@ -8,7 +8,8 @@ use texture_packer::texture::Texture;
 use texture_packer::{TexturePacker, TexturePackerConfig};
 
 use crate::cache::CacheBundle;
-use crate::{done, info, NiceUnwrap};
+use crate::error::{Error, WithPath};
+use crate::{done, info};
 
 use super::mod_file::ModFileInfo;
 
@@ -66,12 +67,12 @@ impl SheetBundles {
 	}
 }
 
-pub fn read_to_image(path: &Path) -> RgbaImage {
-	image::ImageReader::open(path)
-		.nice_unwrap(format!("Error reading sprite '{}'", path.display()))
+pub fn read_to_image(path: &Path) -> Result<RgbaImage, Error> {
+	Ok(image::ImageReader::open(path)
+		.with_path(path)?
 		.decode()
-		.nice_unwrap(format!("Error decoding sprite '{}'", path.display()))
-		.to_rgba8()
+		.with_path(path)?
+		.to_rgba8())
 }
 
 pub fn downscale(img: &mut RgbaImage, factor: u32) {
@@ -120,16 +121,24 @@ fn initialize_spritesheet_bundle(
 	sheet: &SpriteSheet,
 	factor: u32,
 	mod_info: &ModFileInfo,
-) {
+) -> Result<(), Error> {
 	// Convert all files to sprites
 	let mut sprites: Vec<Sprite> = sheet
 		.files
 		.iter()
-		.map(|x| Sprite {
-			name: x.file_stem().unwrap().to_str().unwrap().to_string(),
-			image: read_to_image(x),
+		.map(|x| {
+			Ok(Sprite {
+				name: x.file_stem().unwrap().to_str().unwrap().to_string(),
+				image: read_to_image(x)?,
+			})
 		})
-		.collect();
+		.collect::<Result<_, Error>>()?;
+	if sprites.is_empty() {
+		return Err(Error::Resource(format!(
+			"Spritesheet {} contains no sprites",
+			sheet.name
+		)));
+	}
 
 	// Resize
 	for sprite in &mut sprites {
@@ -159,9 +168,11 @@ fn initialize_spritesheet_bundle(
 
 	// Pack textures
 	info!("Packing sprites");
-	sprites
-		.iter()
-		.for_each(|x| texture_packer.pack_ref(&x.name, &x.image).unwrap());
+	for sprite in &sprites {
+		texture_packer
+			.pack_ref(&sprite.name, &sprite.image)
+			.map_err(|e| Error::Resource(format!("Unable to pack {}: {e:?}", sprite.name)))?;
+	}
 	done!("Packed sprites");
 
 	let sprite_name_in_sheet = |name: &String| {
@@ -209,17 +220,22 @@ fn initialize_spritesheet_bundle(
 		}
 	});
 
-	plist::to_file_xml(&bundle.plist, &plist_file).nice_unwrap("Unable to write to plist file");
+	plist::to_file_xml(&bundle.plist, &plist_file).map_err(|e| {
+		Error::Resource(format!(
+			"Unable to write to plist file {}: {e}",
+			bundle.plist.display()
+		))
+	})?;
 
 	// Write png
-	let mut file = std::fs::File::create(&bundle.png).unwrap();
+	let mut file = std::fs::File::create(&bundle.png).with_path(&bundle.png)?;
 
 	info!("Exporting");
 
-	let exporter = ImageExporter::export(&texture_packer, None).unwrap();
+	let exporter = ImageExporter::export(&texture_packer, None).map_err(Error::Resource)?;
 	exporter
 		.write_to(&mut file, ImageFormat::Png)
-		.nice_unwrap("Unable to write to png file");
+		.with_path(&bundle.png)?;
 
 	done!(
 		"Successfully packed {}",
@@ -232,6 +248,7 @@ fn initialize_spritesheet_bundle(
 			.unwrap()
 			.bright_yellow()
 	);
+	Ok(())
 }
 
 fn try_extract_from_cache(
@@ -291,13 +308,13 @@ pub fn get_spritesheet_bundles(
 	cache: &mut Option<CacheBundle>,
 	mod_info: &ModFileInfo,
 	shut_up: bool,
-) -> SheetBundles {
+) -> Result<SheetBundles, Error> {
 	if !shut_up {
 		info!("Fetching spritesheet {}", sheet.name.bright_yellow());
 	}
 
 	if let Some(cached) = try_extract_bundles_from_cache(sheet, working_dir, cache, shut_up) {
-		return cached;
+		return Ok(cached);
 	}
 
 	if !shut_up {
@@ -308,14 +325,14 @@ pub fn get_spritesheet_bundles(
 	// Initialize all files
 
 	info!("Creating normal sheet");
-	initialize_spritesheet_bundle(&bundles.sd, sheet, 4, mod_info);
+	initialize_spritesheet_bundle(&bundles.sd, sheet, 4, mod_info)?;
 
 	info!("Creating HD sheet");
-	initialize_spritesheet_bundle(&bundles.hd, sheet, 2, mod_info);
+	initialize_spritesheet_bundle(&bundles.hd, sheet, 2, mod_info)?;
 
 	info!("Creating UHD sheet");
-	initialize_spritesheet_bundle(&bundles.uhd, sheet, 1, mod_info);
+	initialize_spritesheet_bundle(&bundles.uhd, sheet, 1, mod_info)?;
 
 	done!("Built spritesheet {}", sheet.name.bright_yellow());
-	bundles
+	Ok(bundles)
 }